@@ -1,20 +1,53 @@
+use base64;
+use config::Credentials;
 use failure::Error;
 use feed::Feed;
+use framework::NuGetFramework;
 use package::Package;
-use reqwest::Body;
-use reqwest::{Client as ReqwestClient, Response};
+use reqwest::header::{HeaderValue, AUTHORIZATION, RANGE};
+use reqwest::multipart::{Form, Part};
+use reqwest::{Body, Client as ReqwestClient, Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use serde_json;
 use serde_xml_rs;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Read;
+use std::ops;
+use std::vec;
 use url::Url;
+use v3::catalog::Catalog;
+use v3::flat_container::{self, FlatContainer};
+use v3::registration::Registrations;
+use v3::search::{Autocomplete, Search, SearchQuery};
+use v3::{self as nuget_v3, ServiceIndex};
+use version::{NuGetVersion, VersionRange};
 
 pub struct Client {
     client: ReqwestClient,
     pub base_url: Url,
     api_key: Option<String>,
+    authorization: Option<HeaderValue>,
+    service_index: Option<ServiceIndex>,
 }
 
 impl Client {
+    /// Creates a client for a V2 OData feed. Use `connect` for a V3 feed.
     pub fn new(url: Url, api_key: Option<String>) -> Result<Client, Error> {
+        Client::with_credentials(url, api_key, None)
+    }
+
+    /// Creates a client for a V2 OData feed that authenticates with `credentials`, using basic authentication.
+    ///
+    /// The credentials are only sent to the feed's own host, not to other
+    /// hosts it links to such as a CDN, and not at all if their
+    /// `valid_authentication_types` exclude basic authentication.
+    pub fn with_credentials(
+        url: Url,
+        api_key: Option<String>,
+        credentials: Option<&Credentials>,
+    ) -> Result<Client, Error> {
         let mut base_url: Url = url.clone();
         // URI joiners will replace the entire path if the base URI
         // doesn't end in '/'
@@ -24,51 +57,132 @@ impl Client {
             .pop_if_empty()
             .push("");
 
+        Client::build(base_url, api_key, credentials)
+    }
+
+    /// Creates a client for a V3 feed if `url` points at its `index.json`, or for a V2 feed otherwise.
+    ///
+    /// Unlike `new`, this makes a request: the V3 service index is fetched up front.
+    pub fn connect(
+        url: Url,
+        api_key: Option<String>,
+        credentials: Option<&Credentials>,
+    ) -> Result<Client, Error> {
+        if !url.path().ends_with(".json") {
+            return Client::with_credentials(url, api_key, credentials);
+        }
+
+        let mut client = Client::build(url.clone(), api_key, credentials)?;
+        client.service_index = Some(client.get_json(&url)?);
+        Ok(client)
+    }
+
+    fn build(
+        base_url: Url,
+        api_key: Option<String>,
+        credentials: Option<&Credentials>,
+    ) -> Result<Client, Error> {
+        let mut authorization = None;
+
+        if let Some(credentials) = credentials {
+            if credentials.allows_basic() {
+                let token = base64::encode(&format!(
+                    "{}:{}",
+                    credentials.username,
+                    credentials
+                        .password
+                        .as_ref()
+                        .map(String::as_str)
+                        .unwrap_or_default()
+                ));
+
+                authorization = Some(HeaderValue::from_str(&format!("Basic {}", token))?);
+            } else {
+                warn!(
+                    "Not sending credentials to {}, as they don't allow basic authentication",
+                    base_url
+                );
+            }
+        }
+
         Ok(Client {
             client: ReqwestClient::new(),
-            base_url: base_url,
-            api_key: api_key,
+            base_url,
+            api_key,
+            authorization,
+            service_index: None,
         })
     }
 
-    pub fn packages(&self, filter: Option<&str>) -> Result<Vec<Package>, Error> {
-        let mut results = Vec::new();
+    /// The V3 service index, if this client is talking to a V3 feed.
+    pub fn service_index(&self) -> Option<&ServiceIndex> {
+        self.service_index.as_ref()
+    }
 
-        let page_size = 15_000u64;
-        let mut skip = 0u64;
+    /// The V3 search resource.
+    pub fn search_service<'a>(&'a self) -> Result<Search<'a>, Error> {
+        Search::new(self)
+    }
 
-        loop {
-            let mut url = self.base_url.join("Packages()")?;
+    /// The V3 package id and version autocomplete resource.
+    pub fn autocomplete<'a>(&'a self) -> Result<Autocomplete<'a>, Error> {
+        Autocomplete::new(self)
+    }
 
-            {
-                let mut query_pairs = url.query_pairs_mut();
+    /// The V3 catalog of every package change.
+    pub fn catalog<'a>(&'a self) -> Result<Catalog<'a>, Error> {
+        Catalog::new(self)
+    }
 
-                query_pairs
-                    .clear()
-                    .append_pair("$top", &page_size.to_string())
-                    .append_pair("$skip", &skip.to_string());
+    /// The V3 package content resource.
+    pub fn flat_container<'a>(&'a self) -> Result<FlatContainer<'a>, Error> {
+        FlatContainer::new(self)
+    }
 
-                if let Some(filter) = filter {
-                    query_pairs.append_pair("$filter", filter);
-                }
-            }
+    /// The V3 package metadata resource.
+    pub fn registrations<'a>(&'a self) -> Result<Registrations<'a>, Error> {
+        Registrations::new(self)
+    }
 
-            debug!("GET {}", &url);
+    pub fn packages(&self, filter: Option<&str>) -> Result<Vec<Package>, Error> {
+        self.packages_iter(filter).collect()
+    }
 
-            let mut feed: Feed = self.get_xml(&url)?;
+    /// Lazily lists packages, fetching the next page only once the current one is exhausted.
+    pub fn packages_iter<'a>(&'a self, filter: Option<&str>) -> PackageIter<'a> {
+        let mut query = PackageQuery::new();
 
-            if feed.packages.is_empty() {
-                break;
-            } else {
-                results.append(&mut feed.packages);
-                skip += page_size;
-            }
+        if let Some(filter) = filter {
+            query = query.filter(Filter::Raw(String::from(filter)));
         }
 
-        Ok(results)
+        self.query_packages(query)
+    }
+
+    /// Lazily lists the packages matching `query`.
+    pub fn query_packages<'a>(&'a self, query: PackageQuery) -> PackageIter<'a> {
+        PackageIter {
+            client: self,
+            query,
+            page: Vec::new().into_iter(),
+            skip: 0,
+            next_url: None,
+            seen_next_urls: HashSet::new(),
+            done: false,
+        }
     }
 
     pub fn package(&self, id: &str, version: &str) -> Result<Package, Error> {
+        if self.service_index.is_some() {
+            let version: NuGetVersion = version.parse()?;
+
+            return self
+                .package_versions(id)?
+                .into_iter()
+                .find(|package| package.version() == Some(&version))
+                .ok_or_else(|| format_err!("{} {} was not found", id, version));
+        }
+
         let url = self
             .base_url
             .join(&format!("Packages(Id='{}',Version='{}')", id, version))?;
@@ -77,18 +191,69 @@ impl Client {
     }
 
     pub fn package_versions(&self, id: &str) -> Result<Vec<Package>, Error> {
+        if self.service_index.is_some() {
+            return self.registrations()?.packages(id);
+        }
+
         let mut url = self.base_url.join(&format!("FindPackagesById()"))?;
         url.query_pairs_mut().append_pair("id", id);
 
         self.get_xml(&url)
     }
 
+    /// The version of a package NuGet would pick for `range`, see `VersionRange::find_best_match`.
+    pub fn best_package_version(&self, id: &str, range: &VersionRange) -> Result<Package, Error> {
+        let mut packages = self.package_versions(id)?;
+
+        let best = range
+            .find_best_match(packages.iter().filter_map(Package::version))
+            .cloned()
+            .ok_or_else(|| format_err!("No version of {} satisfies {}", id, range))?;
+
+        let index = packages
+            .iter()
+            .position(|package| package.version() == Some(&best))
+            .expect("the best match is one of the packages");
+
+        Ok(packages.swap_remove(index))
+    }
+
+    /// Searches for packages, passing `target_framework` to V2 feeds as-is.
+    ///
+    /// V3 feeds can't search by target framework, so fail unless it's empty.
     pub fn search(
         &self,
         search_term: &str,
         target_framework: &str,
         include_prerelease: bool,
     ) -> Result<Vec<Package>, Error> {
+        if let Some(ref service_index) = self.service_index {
+            // V3 search has no notion of a target framework, and ignoring it would
+            // return packages that aren't compatible with it
+            if !target_framework.is_empty() {
+                bail!(
+                    "{} is a V3 feed, which can't search by target framework",
+                    self.base_url
+                );
+            }
+
+            let query = SearchQuery::new(search_term).prerelease(include_prerelease);
+            let response = self.search_service()?.search(&query)?;
+            let base_url = service_index.resource_url(nuget_v3::PACKAGE_BASE_ADDRESS)?;
+
+            return response
+                .data
+                .into_iter()
+                .map(|result| {
+                    let package_content = flat_container::package_content_url(
+                        &base_url,
+                        &result.id,
+                        &result.version,
+                    )?;
+                    Ok(result.into_package(package_content))
+                }).collect();
+        }
+
         let mut url = self.base_url.join(&format!("Search()"))?;
         url.query_pairs_mut()
             .append_pair("searchTerm", search_term)
@@ -98,34 +263,121 @@ impl Client {
         self.get_xml(&url)
     }
 
+    /// Searches for packages compatible with `target_framework`.
+    pub fn search_for_framework(
+        &self,
+        search_term: &str,
+        target_framework: &NuGetFramework,
+        include_prerelease: bool,
+    ) -> Result<Vec<Package>, Error> {
+        // V2 feeds expect no target framework rather than `any`
+        let target_framework_name = if target_framework.is_any() {
+            String::new()
+        } else {
+            target_framework.to_short_name()
+        };
+
+        self.search(search_term, &target_framework_name, include_prerelease)
+    }
+
     pub fn delete_package(&self, id: &str, version: &str) -> Result<Response, Error> {
-        let url = self.base_url.join(id)?.join(version)?;
+        let url = self.publish_url()?.join(&format!("{}/{}", id, version))?;
 
         debug!("DELETE {}", &url);
 
-        self.client
-            .delete(url.as_str())
-            .header("X-NuGet-ApiKey", self.api_key.clone().unwrap())
+        self.request(Method::DELETE, &url)
+            .header("X-NuGet-ApiKey", self.required_api_key()?)
             .send()?
             .error_for_status()
             .map_err(|x| x.into())
     }
 
+    /// Pushes a package to a V2 feed as the raw request body.
+    ///
+    /// V3 feeds only accept multipart uploads; use `push_package_from` for those.
     pub fn push_package<T>(&self, package_content: T) -> Result<Response, Error>
     where
         T: Into<Body>,
     {
+        if self.service_index.is_some() {
+            bail!("V3 feeds only accept multipart pushes; use Client::push_package_from");
+        }
+
+        let request = self
+            .request(Method::POST, &self.base_url)
+            .body(package_content);
+
+        self.send_push(request)
+    }
+
+    /// Pushes `length` bytes of package content streamed from `reader`, to either kind of feed.
+    pub fn push_package_from<R>(&self, reader: R, length: u64) -> Result<Response, Error>
+    where
+        R: Read + Send + 'static,
+    {
+        let request = if self.service_index.is_some() {
+            let url = self.publish_url()?;
+            let part = Part::reader_with_length(reader, length).file_name("package.nupkg");
+
+            self.request(Method::PUT, &url)
+                .multipart(Form::new().part("package", part))
+        } else {
+            self.request(Method::POST, &self.base_url)
+                .body(Body::sized(reader, length))
+        };
+
+        self.send_push(request)
+    }
+
+    fn send_push(&self, request: RequestBuilder) -> Result<Response, Error> {
         debug!("Pushing Package");
 
-        self.client
-            .post(self.base_url.as_str())
-            .header("X-NuGet-ApiKey", self.api_key.clone().unwrap())
-            .body(package_content)
+        request
+            .header("X-NuGet-ApiKey", self.required_api_key()?)
             .send()?
             .error_for_status()
             .map_err(|x| x.into())
     }
 
+    /// The API key used to push and delete packages, if the client has one.
+    pub fn api_key(&self) -> Option<&str> {
+        self.api_key.as_ref().map(String::as_str)
+    }
+
+    fn required_api_key(&self) -> Result<&str, Error> {
+        self.api_key()
+            .ok_or_else(|| format_err!("An API key is required to change {}", self.base_url))
+    }
+
+    // Only the feed's own host gets the credentials
+    fn request(&self, method: Method, url: &Url) -> RequestBuilder {
+        let request = self.client.request(method, url.as_str());
+
+        match self.authorization {
+            Some(ref authorization) if url.origin() == self.base_url.origin() => {
+                request.header(AUTHORIZATION, authorization.clone())
+            }
+            _ => request,
+        }
+    }
+
+    fn publish_url(&self) -> Result<Url, Error> {
+        match self.service_index {
+            Some(ref service_index) => service_index.resource_url(nuget_v3::PACKAGE_PUBLISH),
+            None => Ok(self.base_url.clone()),
+        }
+    }
+
+    pub fn get_json<T>(&self, url: &Url) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        self.get(url).and_then(|res| {
+            serde_json::from_reader::<_, T>(res)
+                .map_err(|_| format_err!("Unable to deserialize {}", url))
+        })
+    }
+
     pub fn get_xml<'de, T>(&self, url: &Url) -> Result<T, Error>
     where
         T: Deserialize<'de>,
@@ -139,13 +391,32 @@ impl Client {
     pub fn get(&self, url: &Url) -> Result<Response, Error> {
         debug!("GET {}", url);
 
-        self.client
-            .get(url.as_str())
+        self.request(Method::GET, url)
             .send()?
             .error_for_status()
             .map_err(|e| e.into())
     }
 
+    /// Requests `url` from byte `offset` onwards.
+    ///
+    /// Servers that don't support ranges respond with the whole body, so
+    /// check for `206 Partial Content` before appending to earlier bytes.
+    pub fn get_from(&self, url: &Url, offset: u64) -> Result<Response, Error> {
+        debug!("GET {} from byte {}", url, offset);
+
+        let res = self
+            .request(Method::GET, url)
+            .header(RANGE, format!("bytes={}-", offset))
+            .send()?;
+
+        // The offset is at or past the end, so start again
+        if res.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            return self.get(url);
+        }
+
+        res.error_for_status().map_err(|e| e.into())
+    }
+
     /*pub fn put_file(&self, path: &str, body: &[u8]) -> Result<Response, Error> {
         let url = self.base_url.join(path)?;
         let api_key = self.api_key.clone().unwrap();
@@ -197,3 +468,681 @@ impl Client {
         resp.error_for_status().map_err(|x| x.into())
     }*/
 }
+
+pub struct PackageIter<'a> {
+    client: &'a Client,
+    query: PackageQuery,
+    page: vec::IntoIter<Package>,
+    skip: u64,
+    next_url: Option<Url>,
+    seen_next_urls: HashSet<Url>,
+    done: bool,
+}
+
+impl<'a> PackageIter<'a> {
+    fn page_url(&mut self) -> Result<Url, Error> {
+        // Prefer the server-provided continuation link, since most feeds
+        // cap the page size below what we ask for and page with a $skiptoken.
+        if let Some(url) = self.next_url.take() {
+            return Ok(url);
+        }
+
+        let mut url = self.client.base_url.join("Packages()")?;
+
+        {
+            let mut query_pairs = url.query_pairs_mut();
+
+            query_pairs.clear();
+
+            for (key, value) in self.query.query_pairs() {
+                query_pairs.append_pair(key, &value);
+            }
+
+            query_pairs.append_pair("$skip", &self.skip.to_string());
+        }
+
+        Ok(url)
+    }
+
+    // Fetches pages with `fetch` rather than from the client, so paging can be tested without a feed
+    fn next_with<F>(&mut self, mut fetch: F) -> Option<Result<Package, Error>>
+    where
+        F: FnMut(&Url) -> Result<Feed, Error>,
+    {
+        loop {
+            if let Some(package) = self.page.next() {
+                return Some(Ok(package));
+            }
+
+            if self.done {
+                return None;
+            }
+
+            if let Err(err) = self.fetch_page(&mut fetch) {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+    }
+
+    fn fetch_page<F>(&mut self, fetch: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(&Url) -> Result<Feed, Error>,
+    {
+        if self.client.service_index.is_some() {
+            bail!("Listing every package is not supported by V3 feeds");
+        }
+
+        let url = self.page_url()?;
+        let feed = fetch(&url)?;
+        let page_len = feed.packages.len() as u64;
+
+        match feed.next_link() {
+            Some(href) => {
+                let next = url.join(href)?;
+
+                if self.seen_next_urls.insert(next.clone()) {
+                    self.next_url = Some(next);
+                } else {
+                    warn!("Feed returned a repeated next link {}, stopping", next);
+                    self.done = true;
+                }
+            }
+            // Once the server has started handing out continuation links,
+            // the absence of one marks the last page.
+            None if !self.seen_next_urls.is_empty() || page_len == 0 => self.done = true,
+            None => self.skip += page_len,
+        }
+
+        self.page = feed.packages.into_iter();
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for PackageIter<'a> {
+    type Item = Result<Package, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let client = self.client;
+        self.next_with(|url| client.get_xml(url))
+    }
+}
+
+/// A package property that can be filtered, ordered or selected on in an OData query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property {
+    Authors,
+    Copyright,
+    Created,
+    Dependencies,
+    Description,
+    DevelopmentDependency,
+    DownloadCount,
+    GalleryDetailsUrl,
+    IconUrl,
+    Id,
+    IsAbsoluteLatestVersion,
+    IsLatestVersion,
+    IsPrerelease,
+    Language,
+    LastEdited,
+    LastUpdated,
+    LicenseNames,
+    LicenseReportUrl,
+    LicenseUrl,
+    Listed,
+    MinClientVersion,
+    NormalizedVersion,
+    Owners,
+    PackageHash,
+    PackageHashAlgorithm,
+    PackageSize,
+    ProjectUrl,
+    Published,
+    ReleaseNotes,
+    ReportAbuseUrl,
+    RequireLicenseAcceptance,
+    Summary,
+    Tags,
+    Title,
+    Version,
+    VersionDownloadCount,
+}
+
+impl Property {
+    /// The OData name of the property, as it appears in `m:properties`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Property::Authors => "Authors",
+            Property::Copyright => "Copyright",
+            Property::Created => "Created",
+            Property::Dependencies => "Dependencies",
+            Property::Description => "Description",
+            Property::DevelopmentDependency => "DevelopmentDependency",
+            Property::DownloadCount => "DownloadCount",
+            Property::GalleryDetailsUrl => "GalleryDetailsUrl",
+            Property::IconUrl => "IconUrl",
+            Property::Id => "Id",
+            Property::IsAbsoluteLatestVersion => "IsAbsoluteLatestVersion",
+            Property::IsLatestVersion => "IsLatestVersion",
+            Property::IsPrerelease => "IsPrerelease",
+            Property::Language => "Language",
+            Property::LastEdited => "LastEdited",
+            Property::LastUpdated => "LastUpdated",
+            Property::LicenseNames => "LicenseNames",
+            Property::LicenseReportUrl => "LicenseReportUrl",
+            Property::LicenseUrl => "LicenseUrl",
+            Property::Listed => "Listed",
+            Property::MinClientVersion => "MinClientVersion",
+            Property::NormalizedVersion => "NormalizedVersion",
+            Property::Owners => "Owners",
+            Property::PackageHash => "PackageHash",
+            Property::PackageHashAlgorithm => "PackageHashAlgorithm",
+            Property::PackageSize => "PackageSize",
+            Property::ProjectUrl => "ProjectUrl",
+            Property::Published => "Published",
+            Property::ReleaseNotes => "ReleaseNotes",
+            Property::ReportAbuseUrl => "ReportAbuseUrl",
+            Property::RequireLicenseAcceptance => "RequireLicenseAcceptance",
+            Property::Summary => "Summary",
+            Property::Tags => "Tags",
+            Property::Title => "Title",
+            Property::Version => "Version",
+            Property::VersionDownloadCount => "VersionDownloadCount",
+        }
+    }
+
+    pub fn equals<V: Into<Value>>(self, value: V) -> Filter {
+        Filter::Eq(self, value.into())
+    }
+
+    pub fn not_equals<V: Into<Value>>(self, value: V) -> Filter {
+        Filter::Ne(self, value.into())
+    }
+
+    pub fn gt<V: Into<Value>>(self, value: V) -> Filter {
+        Filter::Gt(self, value.into())
+    }
+
+    pub fn ge<V: Into<Value>>(self, value: V) -> Filter {
+        Filter::Ge(self, value.into())
+    }
+
+    pub fn lt<V: Into<Value>>(self, value: V) -> Filter {
+        Filter::Lt(self, value.into())
+    }
+
+    pub fn le<V: Into<Value>>(self, value: V) -> Filter {
+        Filter::Le(self, value.into())
+    }
+
+    pub fn starts_with(self, prefix: &str) -> Filter {
+        Filter::StartsWith(self, String::from(prefix))
+    }
+
+    pub fn contains(self, substring: &str) -> Filter {
+        Filter::SubstringOf(String::from(substring), self)
+    }
+}
+
+impl fmt::Display for Property {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A literal on the right hand side of an OData comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Bool(bool),
+    Int(i64),
+    /// An `Edm.DateTime`, e.g. `2015-12-15T15:05:02`
+    DateTime(String),
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(value: &'a str) -> Value {
+        Value::String(String::from(value))
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Value {
+        Value::String(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Value {
+        Value::Bool(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Value {
+        Value::Int(value.into())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Value {
+        Value::Int(value)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::String(ref value) => write!(f, "'{}'", escape(value)),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::DateTime(ref value) => write!(f, "datetime'{}'", escape(value)),
+        }
+    }
+}
+
+// OData string literals escape a single quote by doubling it.
+fn escape(value: &str) -> String {
+    value.replace("'", "''")
+}
+
+/// An OData v2 `$filter` expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Eq(Property, Value),
+    Ne(Property, Value),
+    Gt(Property, Value),
+    Ge(Property, Value),
+    Lt(Property, Value),
+    Le(Property, Value),
+    StartsWith(Property, String),
+    SubstringOf(String, Property),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    /// A hand-written expression, passed through verbatim.
+    Raw(String),
+}
+
+impl Filter {
+    pub fn and(self, other: Filter) -> Filter {
+        Filter::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Filter) -> Filter {
+        Filter::Or(Box::new(self), Box::new(other))
+    }
+}
+
+impl ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        Filter::Not(Box::new(self))
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Filter::Eq(property, ref value) => write!(f, "{} eq {}", property, value),
+            Filter::Ne(property, ref value) => write!(f, "{} ne {}", property, value),
+            Filter::Gt(property, ref value) => write!(f, "{} gt {}", property, value),
+            Filter::Ge(property, ref value) => write!(f, "{} ge {}", property, value),
+            Filter::Lt(property, ref value) => write!(f, "{} lt {}", property, value),
+            Filter::Le(property, ref value) => write!(f, "{} le {}", property, value),
+            Filter::StartsWith(property, ref prefix) => {
+                write!(f, "startswith({}, '{}')", property, escape(prefix))
+            }
+            Filter::SubstringOf(ref substring, property) => {
+                write!(f, "substringof('{}', {})", escape(substring), property)
+            }
+            Filter::And(ref left, ref right) => write!(f, "({}) and ({})", left, right),
+            Filter::Or(ref left, ref right) => write!(f, "({}) or ({})", left, right),
+            Filter::Not(ref filter) => write!(f, "not ({})", filter),
+            Filter::Raw(ref filter) => f.write_str(filter),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Ascending,
+    Descending,
+}
+
+/// Builds the `$filter`, `$orderby`, `$select` and `$top` options for a `Packages()` query.
+///
+/// Note that `select`ing a subset of properties may leave out fields
+/// that `Package` requires in order to deserialize.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageQuery {
+    filter: Option<Filter>,
+    order_by: Vec<(Property, Order)>,
+    select: Vec<Property>,
+    page_size: u64,
+}
+
+impl PackageQuery {
+    pub fn new() -> PackageQuery {
+        PackageQuery {
+            filter: None,
+            order_by: Vec::new(),
+            select: Vec::new(),
+            page_size: 15_000,
+        }
+    }
+
+    /// Adds a filter, combining it with any existing one using `and`.
+    pub fn filter(mut self, filter: Filter) -> PackageQuery {
+        self.filter = Some(match self.filter.take() {
+            Some(existing) => existing.and(filter),
+            None => filter,
+        });
+        self
+    }
+
+    pub fn order_by(mut self, property: Property) -> PackageQuery {
+        self.order_by.push((property, Order::Ascending));
+        self
+    }
+
+    pub fn order_by_desc(mut self, property: Property) -> PackageQuery {
+        self.order_by.push((property, Order::Descending));
+        self
+    }
+
+    pub fn select(mut self, property: Property) -> PackageQuery {
+        self.select.push(property);
+        self
+    }
+
+    /// The number of packages requested per page, sent as `$top`.
+    pub fn page_size(mut self, page_size: u64) -> PackageQuery {
+        self.page_size = page_size;
+        self
+    }
+
+    /// The query options, unencoded, in the order they should be sent.
+    pub fn query_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = vec![("$top", self.page_size.to_string())];
+
+        if let Some(ref filter) = self.filter {
+            pairs.push(("$filter", filter.to_string()));
+        }
+
+        if !self.order_by.is_empty() {
+            let order_by = self
+                .order_by
+                .iter()
+                .map(|&(property, order)| match order {
+                    Order::Ascending => property.to_string(),
+                    Order::Descending => format!("{} desc", property),
+                }).collect::<Vec<_>>()
+                .join(",");
+
+            pairs.push(("$orderby", order_by));
+        }
+
+        if !self.select.is_empty() {
+            let select = self
+                .select
+                .iter()
+                .map(Property::name)
+                .collect::<Vec<_>>()
+                .join(",");
+
+            pairs.push(("$select", select));
+        }
+
+        pairs
+    }
+}
+
+impl Default for PackageQuery {
+    fn default() -> PackageQuery {
+        PackageQuery::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use feed::Link;
+    use package::PackageProperties;
+
+    #[test]
+    fn filter_escaping() {
+        let filter = Property::IsLatestVersion
+            .equals(true)
+            .and(Property::Id.equals("O'Reilly.Foo"));
+
+        assert_eq!(
+            filter.to_string(),
+            "(IsLatestVersion eq true) and (Id eq 'O''Reilly.Foo')"
+        );
+    }
+
+    #[test]
+    fn filter_functions() {
+        let filter = Property::Id
+            .starts_with("Microsoft.")
+            .or(Property::Tags.contains("json"))
+            .and(!Property::IsPrerelease.equals(true));
+
+        assert_eq!(
+            filter.to_string(),
+            "((startswith(Id, 'Microsoft.')) or (substringof('json', Tags))) and (not (IsPrerelease eq true))"
+        );
+    }
+
+    #[test]
+    fn filter_comparisons() {
+        assert_eq!(
+            Property::DownloadCount.gt(1000).to_string(),
+            "DownloadCount gt 1000"
+        );
+        assert_eq!(
+            Property::Published
+                .lt(Value::DateTime(String::from("2018-01-01T00:00:00")))
+                .to_string(),
+            "Published lt datetime'2018-01-01T00:00:00'"
+        );
+    }
+
+    #[test]
+    fn query_pairs() {
+        let query = PackageQuery::new()
+            .filter(Property::IsLatestVersion.equals(true))
+            .filter(Property::Id.not_equals("Foo"))
+            .order_by(Property::Id)
+            .order_by_desc(Property::DownloadCount)
+            .select(Property::Id)
+            .select(Property::Version)
+            .page_size(100);
+
+        assert_eq!(
+            query.query_pairs(),
+            vec![
+                ("$top", String::from("100")),
+                (
+                    "$filter",
+                    String::from("(IsLatestVersion eq true) and (Id ne 'Foo')")
+                ),
+                ("$orderby", String::from("Id,DownloadCount desc")),
+                ("$select", String::from("Id,Version")),
+            ]
+        );
+    }
+
+    #[test]
+    fn default_query_pairs() {
+        assert_eq!(
+            PackageQuery::default().query_pairs(),
+            vec![("$top", String::from("15000"))]
+        );
+    }
+
+    #[test]
+    fn credentials_scope() {
+        let url = Url::parse("https://feed.example.org/").unwrap();
+        let mut credentials = Credentials {
+            username: String::from("builder"),
+            password: Some(String::from("hunter2")),
+            valid_authentication_types: vec![String::from("basic")],
+        };
+
+        let authorization = |client: &Client, url: &str| {
+            let url = Url::parse(url).unwrap();
+            let request = client.request(Method::GET, &url).build().unwrap();
+            request.headers().get(AUTHORIZATION).cloned()
+        };
+
+        let client = Client::with_credentials(url.clone(), None, Some(&credentials)).unwrap();
+        assert_eq!(
+            authorization(&client, "https://feed.example.org/Packages()"),
+            Some(HeaderValue::from_static("Basic YnVpbGRlcjpodW50ZXIy"))
+        );
+        assert_eq!(
+            authorization(&client, "https://cdn.example.org/foo.1.0.0.nupkg"),
+            None
+        );
+        assert_eq!(
+            authorization(&client, "http://feed.example.org/Packages()"),
+            None
+        );
+
+        credentials.valid_authentication_types = vec![String::from("negotiate")];
+        let client = Client::with_credentials(url, None, Some(&credentials)).unwrap();
+        assert_eq!(
+            authorization(&client, "https://feed.example.org/Packages()"),
+            None
+        );
+    }
+
+    #[test]
+    fn new_is_offline() {
+        // Nothing listens here, so fetching the service index would fail
+        let url = Url::parse("https://feed.invalid/v3/index.json").unwrap();
+        let client = Client::new(url, None).unwrap();
+
+        assert!(client.service_index().is_none());
+    }
+
+    #[test]
+    fn v3_search_by_framework() {
+        let url = Url::parse("https://feed.invalid/v3/index.json").unwrap();
+        let mut client = Client::new(url, None).unwrap();
+        client.service_index = Some(ServiceIndex {
+            version: String::from("3.0.0"),
+            resources: Vec::new(),
+        });
+
+        let framework = "net45".parse().unwrap();
+        assert!(client
+            .search_for_framework("sse", &framework, false)
+            .unwrap_err()
+            .to_string()
+            .contains("can't search by target framework"));
+    }
+
+    fn page(ids: &[&str], next: Option<&str>) -> Feed {
+        Feed {
+            id: String::from("https://example.org/api/v2/Packages"),
+            title: String::from("Packages"),
+            updated_at: Utc.timestamp_opt(0, 0).unwrap(),
+            links: next
+                .map(|href| Link::Next(String::from(href)))
+                .into_iter()
+                .collect(),
+            packages: ids
+                .iter()
+                .map(|id| Package::for_test(id, "1.0.0", PackageProperties::default()))
+                .collect(),
+        }
+    }
+
+    // Pages through a listing served from `pages`, returning the package ids and the urls requested
+    fn page_through(pages: Vec<Feed>) -> (Vec<String>, Vec<Url>) {
+        let client = Client::new(Url::parse("https://example.org/api/v2").unwrap(), None).unwrap();
+        let mut iter = client.query_packages(PackageQuery::default());
+        let mut pages = pages.into_iter();
+        let mut ids = Vec::new();
+        let mut urls = Vec::new();
+
+        while let Some(package) = iter.next_with(|url| {
+            urls.push(url.clone());
+            pages
+                .next()
+                .ok_or_else(|| format_err!("{} was requested past the last page", url))
+        }) {
+            ids.push(String::from(package.unwrap().id()));
+        }
+
+        (ids, urls)
+    }
+
+    #[test]
+    fn paging_next_links() {
+        let (ids, urls) = page_through(vec![
+            page(&["A", "B"], Some("Packages?$skiptoken=B")),
+            page(
+                &["C"],
+                Some("https://example.org/api/v2/Packages?$skiptoken=C"),
+            ),
+            page(&["D"], None),
+        ]);
+
+        assert_eq!(ids, vec!["A", "B", "C", "D"]);
+        assert_eq!(urls.len(), 3);
+        assert_eq!(urls[0].path(), "/api/v2/Packages()");
+        assert_eq!(
+            urls[1].as_str(),
+            "https://example.org/api/v2/Packages?$skiptoken=B"
+        );
+        assert_eq!(
+            urls[2].as_str(),
+            "https://example.org/api/v2/Packages?$skiptoken=C"
+        );
+    }
+
+    #[test]
+    fn paging_skip() {
+        let (ids, urls) = page_through(vec![
+            page(&["A", "B"], None),
+            page(&["C"], None),
+            page(&[], None),
+        ]);
+        let skips = urls
+            .iter()
+            .map(|url| {
+                url.query_pairs()
+                    .find(|(key, _)| key == "$skip")
+                    .map(|(_, value)| value.into_owned())
+            }).collect::<Vec<_>>();
+
+        assert_eq!(ids, vec!["A", "B", "C"]);
+        assert_eq!(
+            skips,
+            vec![
+                Some(String::from("0")),
+                Some(String::from("2")),
+                Some(String::from("3")),
+            ]
+        );
+    }
+
+    #[test]
+    fn paging_repeated_next_link() {
+        let (ids, urls) = page_through(vec![
+            page(&["A"], Some("Packages?$skiptoken=A")),
+            page(&["B"], Some("Packages?$skiptoken=A")),
+            page(&["C"], Some("Packages?$skiptoken=A")),
+        ]);
+
+        assert_eq!(ids, vec!["A", "B"]);
+        assert_eq!(urls.len(), 2);
+    }
+}
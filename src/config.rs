@@ -0,0 +1,678 @@
+use client::Client;
+use failure::Error;
+use serde_xml_rs;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// The names NuGet looks for when walking up from a directory, in order.
+const FILE_NAMES: &[&str] = &["nuget.config", "NuGet.config", "NuGet.Config"];
+
+/// A single `NuGet.Config` file, from https://docs.microsoft.com/en-us/nuget/reference/nuget-config-file
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct ConfigFile {
+    #[serde(default)]
+    config: Section,
+    #[serde(default)]
+    package_sources: Section,
+    #[serde(default)]
+    disabled_package_sources: Section,
+    // Keyed by the source name, encoded as an XML name
+    #[serde(default)]
+    package_source_credentials: BTreeMap<String, Section>,
+    #[serde(default)]
+    apikeys: Section,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+struct Section {
+    #[serde(rename = "$value", default)]
+    items: Vec<Item>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+enum Item {
+    Add {
+        key: String,
+        #[serde(default)]
+        value: String,
+        #[serde(rename = "protocolVersion", default)]
+        protocol_version: Option<String>,
+    },
+    Remove {
+        key: String,
+    },
+    Clear,
+}
+
+impl ConfigFile {
+    pub fn from_reader<R: Read>(reader: R) -> Result<ConfigFile, Error> {
+        serde_xml_rs::from_reader(reader)
+            .map_err(|e| format_err!("Unable to deserialize NuGet.Config: {}", e))
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<ConfigFile, Error> {
+        let path = path.as_ref();
+
+        File::open(path)
+            .map_err(Error::from)
+            .and_then(ConfigFile::from_reader)
+            .map_err(|e| format_err!("Unable to read {}: {}", path.display(), e))
+    }
+}
+
+/// A source from `packageSources`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageSource {
+    pub name: String,
+    /// A URL, or the path of a local folder.
+    pub url: String,
+    pub protocol_version: Option<u32>,
+    pub is_enabled: bool,
+}
+
+/// The credentials for a source from `packageSourceCredentials`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Credentials {
+    pub username: String,
+    /// The `ClearTextPassword`, since encrypted passwords can only be read on Windows by NuGet itself.
+    pub password: Option<String>,
+    pub valid_authentication_types: Vec<String>,
+}
+
+impl Credentials {
+    /// Whether the credentials may be sent using basic authentication.
+    ///
+    /// An empty `ValidAuthenticationTypes` allows every type.
+    pub fn allows_basic(&self) -> bool {
+        self.valid_authentication_types.is_empty()
+            || self
+                .valid_authentication_types
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case("basic"))
+    }
+}
+
+/// The settings of a chain of `NuGet.Config` files, merged together.
+///
+/// Files are applied from the lowest priority to the highest, so later files
+/// override the values of earlier ones. A `<clear />` drops everything a
+/// section has accumulated so far, and `<remove key="..." />` drops one key.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Settings {
+    config: Vec<(String, String)>,
+    sources: Vec<PackageSource>,
+    disabled: Vec<(String, String)>,
+    credentials: Vec<(String, Credentials)>,
+    api_keys: Vec<(String, String)>,
+}
+
+impl Settings {
+    /// The settings NuGet would use in `directory`.
+    ///
+    /// Reads the machine-wide files, then the user's file, then every
+    /// `NuGet.Config` from the root down to `directory`.
+    pub fn load<P: AsRef<Path>>(directory: P) -> Result<Settings, Error> {
+        Settings::from_files(config_files(directory.as_ref()))
+    }
+
+    /// Merges the given files, from the lowest priority to the highest.
+    pub fn from_files<I, P>(paths: I) -> Result<Settings, Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut settings = Settings::default();
+
+        for path in paths {
+            let path = path.as_ref();
+            debug!("Reading {}", path.display());
+
+            let directory = path.parent().unwrap_or_else(|| Path::new(""));
+            settings.apply(ConfigFile::open(path)?, directory);
+        }
+
+        Ok(settings)
+    }
+
+    /// Merges `file` over these settings, resolving relative source paths against `directory`.
+    pub fn apply(&mut self, file: ConfigFile, directory: &Path) {
+        apply_section(&mut self.config, &file.config, |_, value, _| {
+            Some(expand_variables(value))
+        });
+
+        let mut sources: Vec<(String, PackageSource)> = self
+            .sources
+            .drain(..)
+            .map(|source| (source.name.clone(), source))
+            .collect();
+        apply_section(
+            &mut sources,
+            &file.package_sources,
+            |name, value, protocol_version| {
+                Some(PackageSource {
+                    name: String::from(name),
+                    url: resolve_source(&expand_variables(value), directory),
+                    protocol_version: protocol_version.and_then(|version| version.parse().ok()),
+                    is_enabled: true,
+                })
+            },
+        );
+        self.sources = sources.into_iter().map(|(_, source)| source).collect();
+
+        apply_section(
+            &mut self.disabled,
+            &file.disabled_package_sources,
+            |_, value, _| Some(String::from(value)),
+        );
+
+        // A `<clear />` deserializes as a source named `clear`, and the order
+        // of the sources is lost, so assume it came first like it should
+        let mut credentials = file.package_source_credentials;
+        if credentials.get("clear").map(|section| section.items.is_empty()) == Some(true) {
+            credentials.remove("clear");
+            self.credentials.clear();
+        }
+
+        for (name, section) in credentials {
+            let name = decode_name(&name);
+            let mut values = Vec::new();
+            apply_section(&mut values, &section, |_, value, _| {
+                Some(expand_variables(value))
+            });
+
+            let value = |key: &str| get(&values, key).map(String::from);
+
+            if value("Password").is_some() && value("ClearTextPassword").is_none() {
+                warn!(
+                    "Ignoring the encrypted password of {}, use ClearTextPassword",
+                    name
+                );
+            }
+
+            let credentials = Credentials {
+                username: value("Username").unwrap_or_default(),
+                password: value("ClearTextPassword"),
+                valid_authentication_types: value("ValidAuthenticationTypes")
+                    .map(|types| {
+                        types
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|t| !t.is_empty())
+                            .map(String::from)
+                            .collect()
+                    }).unwrap_or_default(),
+            };
+
+            insert(&mut self.credentials, &name, credentials);
+        }
+
+        apply_section(&mut self.api_keys, &file.apikeys, |_, value, _| {
+            Some(String::from(value))
+        });
+
+        let disabled = &self.disabled;
+        for source in &mut self.sources {
+            source.is_enabled = match get(disabled, &source.name) {
+                Some(value) => !value.eq_ignore_ascii_case("true"),
+                None => true,
+            };
+        }
+    }
+
+    /// A value from the `config` section, such as `defaultPushSource`.
+    pub fn config(&self, key: &str) -> Option<&str> {
+        get(&self.config, key).map(String::as_str)
+    }
+
+    /// Every source, including disabled ones, in the order they were configured.
+    pub fn sources(&self) -> &[PackageSource] {
+        &self.sources
+    }
+
+    pub fn enabled_sources(&self) -> Vec<&PackageSource> {
+        self.sources
+            .iter()
+            .filter(|source| source.is_enabled)
+            .collect()
+    }
+
+    /// The source named `name`, ignoring case.
+    pub fn source(&self, name: &str) -> Option<&PackageSource> {
+        self.sources
+            .iter()
+            .find(|source| source.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn credentials(&self, name: &str) -> Option<&Credentials> {
+        get(&self.credentials, name)
+    }
+
+    /// The API key for the source at `url`, ignoring case and trailing slashes.
+    ///
+    /// Keys are used as written, so keys NuGet has encrypted can't be used.
+    #[allow(deprecated)]
+    pub fn api_key(&self, url: &str) -> Option<&str> {
+        let url = url.trim_right_matches('/');
+
+        self.api_keys
+            .iter()
+            .find(|entry| entry.0.trim_right_matches('/').eq_ignore_ascii_case(url))
+            .map(|entry| entry.1.as_str())
+    }
+
+    /// A client for the enabled source named `name`, with its API key and credentials.
+    ///
+    /// A V3 source's service index is fetched, see `Client::connect`.
+    pub fn client(&self, name: &str) -> Result<Client, Error> {
+        let source = match self.source(name) {
+            Some(source) => source,
+            None => bail!("There is no package source named {}", name),
+        };
+
+        if !source.is_enabled {
+            bail!("The package source {} is disabled", source.name);
+        }
+
+        let url = Url::parse(&source.url).map_err(|_| {
+            format_err!(
+                "The package source {} is not a URL: {}",
+                source.name,
+                source.url
+            )
+        })?;
+
+        let api_key = self.api_key(&source.url).map(String::from);
+        Client::connect(url, api_key, self.credentials(&source.name))
+    }
+
+    /// A client for each enabled source, in the order they were configured.
+    ///
+    /// Sources there's no client for, such as local folders or V3 feeds whose
+    /// service index can't be fetched, are skipped with a warning.
+    pub fn clients(&self) -> Vec<Client> {
+        self.enabled_sources()
+            .into_iter()
+            .filter_map(|source| match self.client(&source.name) {
+                Ok(client) => Some(client),
+                Err(e) => {
+                    warn!("Skipping the package source {}: {}", source.name, e);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+// Sections are kept in order, with keys compared ignoring case like NuGet does
+fn get<'a, T>(entries: &'a [(String, T)], key: &str) -> Option<&'a T> {
+    entries
+        .iter()
+        .find(|entry| entry.0.eq_ignore_ascii_case(key))
+        .map(|entry| &entry.1)
+}
+
+fn insert<T>(entries: &mut Vec<(String, T)>, key: &str, value: T) {
+    match entries
+        .iter()
+        .position(|entry| entry.0.eq_ignore_ascii_case(key))
+    {
+        Some(index) => entries[index].1 = value,
+        None => entries.push((String::from(key), value)),
+    }
+}
+
+fn apply_section<T, F>(entries: &mut Vec<(String, T)>, section: &Section, mut value: F)
+where
+    F: FnMut(&str, &str, Option<&str>) -> Option<T>,
+{
+    for item in &section.items {
+        match *item {
+            Item::Add {
+                ref key,
+                value: ref raw,
+                ref protocol_version,
+            } => {
+                let protocol_version = protocol_version.as_ref().map(String::as_str);
+                if let Some(value) = value(key, raw, protocol_version) {
+                    insert(entries, key, value);
+                }
+            }
+            Item::Remove { ref key } => entries.retain(|entry| !entry.0.eq_ignore_ascii_case(key)),
+            Item::Clear => entries.clear(),
+        }
+    }
+}
+
+// Local folder sources are relative to the file that configures them
+fn resolve_source(value: &str, directory: &Path) -> String {
+    if Url::parse(value).is_ok() || Path::new(value).is_absolute() {
+        return String::from(value);
+    }
+
+    directory.join(value).to_string_lossy().into_owned()
+}
+
+/// Replaces `%NAME%` with the environment variable `NAME`, leaving unset variables as they are.
+fn expand_variables(value: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find('%') {
+        let after = &rest[start + 1..];
+
+        match after.find('%') {
+            Some(end) if end > 0 => {
+                let name = &after[..end];
+                expanded.push_str(&rest[..start]);
+
+                match env::var(name) {
+                    Ok(variable) => expanded.push_str(&variable),
+                    Err(_) => {
+                        expanded.push('%');
+                        expanded.push_str(name);
+                        expanded.push('%');
+                    }
+                }
+
+                rest = &after[end + 1..];
+            }
+            _ => {
+                expanded.push_str(&rest[..=start]);
+                rest = after;
+            }
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Decodes an element name encoded like .NET's `XmlConvert.EncodeLocalName`, e.g. `My_x0020_Feed`.
+fn decode_name(name: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = name;
+
+    while let Some(start) = rest.find("_x") {
+        let escape = rest[start + 2..]
+            .get(..5)
+            .filter(|escape| escape.ends_with('_'))
+            .and_then(|escape| u32::from_str_radix(&escape[..4], 16).ok())
+            .and_then(::std::char::from_u32);
+
+        match escape {
+            Some(c) => {
+                decoded.push_str(&rest[..start]);
+                decoded.push(c);
+                rest = &rest[start + 7..];
+            }
+            None => {
+                decoded.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The files NuGet reads for `directory`, from the lowest priority to the highest.
+pub fn config_files(directory: &Path) -> Vec<PathBuf> {
+    let mut files = machine_config_files();
+
+    if let Some(file) = user_config_directory().and_then(|directory| find_file(&directory)) {
+        files.push(file);
+    }
+
+    let mut ancestors: Vec<PathBuf> = directory
+        .ancestors()
+        .filter_map(find_file)
+        .filter(|file| !files.contains(file))
+        .collect();
+    ancestors.reverse();
+    files.append(&mut ancestors);
+
+    files
+}
+
+fn find_file(directory: &Path) -> Option<PathBuf> {
+    FILE_NAMES
+        .iter()
+        .map(|name| directory.join(name))
+        .find(|path| path.is_file())
+}
+
+#[cfg(windows)]
+fn user_config_directory() -> Option<PathBuf> {
+    env::var_os("APPDATA").map(|appdata| PathBuf::from(appdata).join("NuGet"))
+}
+
+#[cfg(not(windows))]
+fn user_config_directory() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".nuget").join("NuGet"))
+}
+
+#[cfg(windows)]
+fn machine_config_directory() -> Option<PathBuf> {
+    env::var_os("ProgramFiles(x86)")
+        .or_else(|| env::var_os("ProgramFiles"))
+        .map(|program_files| PathBuf::from(program_files).join("NuGet").join("Config"))
+}
+
+#[cfg(target_os = "macos")]
+fn machine_config_directory() -> Option<PathBuf> {
+    Some(PathBuf::from("/Library/Application Support/NuGet/Config"))
+}
+
+#[cfg(all(not(windows), not(target_os = "macos")))]
+fn machine_config_directory() -> Option<PathBuf> {
+    Some(
+        env::var_os("NUGET_COMMON_APPLICATION_DATA")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("/etc/opt"))
+            .join("NuGet")
+            .join("Config"),
+    )
+}
+
+// Every `.config` file in the machine-wide directory, in name order
+fn machine_config_files() -> Vec<PathBuf> {
+    let entries =
+        match machine_config_directory().and_then(|directory| fs::read_dir(directory).ok()) {
+            Some(entries) => entries,
+            None => return Vec::new(),
+        };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| match path.extension() {
+            Some(extension) => {
+                path.is_file() && extension.to_string_lossy().eq_ignore_ascii_case("config")
+            }
+            None => false,
+        }).collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    const MACHINE: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<configuration>
+  <packageSources>
+    <add key="nuget.org" value="https://api.nuget.org/v3/index.json" protocolVersion="3" />
+    <add key="Legacy" value="https://www.nuget.org/api/v2/" />
+  </packageSources>
+</configuration>"#;
+
+    const USER: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<configuration>
+  <config>
+    <add key="defaultPushSource" value="https://proget.example.org/nuget/Internal/" />
+  </config>
+  <packageSources>
+    <remove key="Legacy" />
+    <add key="Internal Feed" value="https://proget.example.org/nuget/Internal/" />
+  </packageSources>
+  <packageSourceCredentials>
+    <Internal_x0020_Feed>
+      <add key="Username" value="builder" />
+      <add key="ClearTextPassword" value="%NOUGAT_TEST_PASSWORD%" />
+      <add key="ValidAuthenticationTypes" value="basic, negotiate" />
+    </Internal_x0020_Feed>
+  </packageSourceCredentials>
+  <apikeys>
+    <add key="https://proget.example.org/nuget/Internal" value="0123456789abcdef" />
+  </apikeys>
+</configuration>"#;
+
+    const SOLUTION: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<configuration>
+  <packageSources>
+    <add key="Local" value="packages" />
+  </packageSources>
+  <disabledPackageSources>
+    <add key="nuget.org" value="true" />
+  </disabledPackageSources>
+</configuration>"#;
+
+    fn settings(files: &[&str]) -> Settings {
+        let mut settings = Settings::default();
+        for file in files {
+            let file = ConfigFile::from_reader(file.as_bytes()).unwrap();
+            settings.apply(file, Path::new("/src/solution"));
+        }
+        settings
+    }
+
+    #[test]
+    fn merging() {
+        env::set_var("NOUGAT_TEST_PASSWORD", "hunter2");
+
+        let settings = settings(&[MACHINE, USER, SOLUTION]);
+
+        assert_eq!(
+            settings.sources(),
+            &[
+                PackageSource {
+                    name: String::from("nuget.org"),
+                    url: String::from("https://api.nuget.org/v3/index.json"),
+                    protocol_version: Some(3),
+                    is_enabled: false,
+                },
+                PackageSource {
+                    name: String::from("Internal Feed"),
+                    url: String::from("https://proget.example.org/nuget/Internal/"),
+                    protocol_version: None,
+                    is_enabled: true,
+                },
+                PackageSource {
+                    name: String::from("Local"),
+                    url: String::from("/src/solution/packages"),
+                    protocol_version: None,
+                    is_enabled: true,
+                },
+            ][..]
+        );
+
+        assert_eq!(
+            settings.config("DefaultPushSource"),
+            Some("https://proget.example.org/nuget/Internal/")
+        );
+        assert_eq!(
+            settings.credentials("internal feed"),
+            Some(&Credentials {
+                username: String::from("builder"),
+                password: Some(String::from("hunter2")),
+                valid_authentication_types: vec![String::from("basic"), String::from("negotiate")],
+            })
+        );
+        assert_eq!(
+            settings.api_key("https://PROGET.example.org/nuget/Internal/"),
+            Some("0123456789abcdef")
+        );
+        assert_eq!(
+            settings.api_key("https://api.nuget.org/v3/index.json"),
+            None
+        );
+
+        assert!(settings.client("nuget.org").is_err());
+        assert!(settings.client("Local").is_err());
+        assert!(settings.client("Missing").is_err());
+
+        // Only the internal feed is enabled and has a URL
+        assert_eq!(settings.clients().len(), 1);
+    }
+
+    #[test]
+    fn clearing() {
+        let cleared = r#"<configuration>
+  <packageSources>
+    <clear />
+    <add key="Only" value="https://example.org/nuget/" />
+  </packageSources>
+  <disabledPackageSources />
+  <packageSourceCredentials>
+    <clear />
+    <Only>
+      <add key="Username" value="only" />
+    </Only>
+  </packageSourceCredentials>
+</configuration>"#;
+
+        let settings = settings(&[USER, cleared]);
+
+        assert_eq!(
+            settings
+                .sources()
+                .iter()
+                .map(|source| source.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Only"]
+        );
+        assert_eq!(settings.credentials("Internal Feed"), None);
+        assert_eq!(settings.credentials("only").unwrap().username, "only");
+    }
+
+    #[test]
+    fn names_and_variables() {
+        assert_eq!(decode_name("My_x0020_Feed"), "My Feed");
+        assert_eq!(decode_name("My_x0020__x002B_Feed"), "My +Feed");
+        assert_eq!(decode_name("My_xFeed"), "My_xFeed");
+
+        env::set_var("NOUGAT_TEST_VARIABLE", "value");
+        assert_eq!(expand_variables("%NOUGAT_TEST_VARIABLE%/a"), "value/a");
+        assert_eq!(
+            expand_variables("%NOUGAT_UNSET_VARIABLE%"),
+            "%NOUGAT_UNSET_VARIABLE%"
+        );
+        assert_eq!(expand_variables("100% %%"), "100% %%");
+    }
+
+    #[test]
+    fn discovery() {
+        let root = TempDir::new("nougat-config").unwrap();
+        let solution = root.path().join("solution");
+        let project = solution.join("src").join("project");
+        fs::create_dir_all(&project).unwrap();
+
+        fs::write(root.path().join("NuGet.Config"), MACHINE).unwrap();
+        fs::write(solution.join("nuget.config"), SOLUTION).unwrap();
+
+        let files = config_files(&project);
+        let n = files.len();
+
+        assert!(n >= 2);
+        assert_eq!(files[n - 2], root.path().join("NuGet.Config"));
+        assert_eq!(files[n - 1], solution.join("nuget.config"));
+    }
+}
@@ -1,12 +1,15 @@
-use package::Package;
+use chrono::{DateTime, Utc};
+use client::Property;
+use package::{parse_date, Package};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct Feed {
     pub id: String,
     pub title: String,
-    #[serde(rename = "updated")]
-    pub updated_at: String,
+    #[serde(rename = "updated", deserialize_with = "parse_date")]
+    pub updated_at: DateTime<Utc>,
     #[serde(rename = "link", default)]
     pub links: Vec<Link>,
     #[serde(rename = "entry", default)]
@@ -18,6 +21,7 @@ pub struct Feed {
 pub enum Link {
     Edit(String),
     EditMedia(String),
+    Next(String),
     #[serde(rename = "self")]
     _Self(String),
     #[serde(rename = "http://schemas.microsoft.com/ado/2007/08/dataservices/related/Screenshots")]
@@ -25,21 +29,181 @@ pub enum Link {
 }
 
 impl Feed {
+    /// The continuation link for the next page of results, if the server provided one.
+    pub fn next_link(&self) -> Option<&str> {
+        self.links
+            .iter()
+            .filter_map(|link| match *link {
+                Link::Next(ref href) => Some(href.as_str()),
+                _ => None,
+            }).next()
+    }
+
+    /// The packages in this feed that aren't in `other`.
     pub fn subtract(&self, other: &Feed) -> Vec<&Package> {
-        let mut diff = Vec::new();
+        let others: HashSet<_> = other.packages.iter().map(package_key).collect();
+
+        self.packages
+            .iter()
+            .filter(|package| !others.contains(&package_key(package)))
+            .collect()
+    }
 
-        'outer: for package in &self.packages {
-            for other_package in &other.packages {
-                if package == other_package {
-                    continue 'outer;
+    /// What was added, removed and changed in `newer` compared to this feed.
+    pub fn diff<'a>(&'a self, newer: &'a Feed) -> FeedDiff<'a> {
+        FeedDiff::new(&self.packages, &newer.packages)
+    }
+}
+
+/// Identifies a package across feeds, by its case-insensitive id and normalized version.
+///
+/// Versions that don't parse are compared as they were sent, ignoring case.
+pub(crate) fn package_key(package: &Package) -> (String, String) {
+    let version = match package.version() {
+        Some(version) => version.to_normalized_string(),
+        None => String::from(package.raw_version()),
+    };
+
+    (package.id().to_lowercase(), version.to_lowercase())
+}
+
+/// The differences between two sets of packages.
+///
+/// Packages are matched by `package_key`, so `Foo 1.0` and `foo 1.0.0` are
+/// the same package.
+#[derive(Debug, Default)]
+pub struct FeedDiff<'a> {
+    /// Packages only in the newer set.
+    pub added: Vec<&'a Package>,
+    /// Packages only in the older set.
+    pub removed: Vec<&'a Package>,
+    pub changed: Vec<PackageChange<'a>>,
+}
+
+/// A package in both sets whose properties differ.
+#[derive(Debug)]
+pub struct PackageChange<'a> {
+    pub old: &'a Package,
+    pub new: &'a Package,
+    /// The properties that differ, see `FeedDiff::new` for which are compared.
+    pub properties: Vec<Property>,
+}
+
+impl<'a> FeedDiff<'a> {
+    /// Compares two sets of packages, such as the pages of two feeds.
+    ///
+    /// A package has changed if it was listed or unlisted, if its hash
+    /// changed, or if its metadata (title, summary, description, authors,
+    /// owners, tags, copyright, release notes, URLs, dependencies, license
+    /// acceptance or minimum client version) differs. Hashes are only compared
+    /// when both sides report one with the same algorithm.
+    pub fn new<I, J>(old: I, new: J) -> FeedDiff<'a>
+    where
+        I: IntoIterator<Item = &'a Package>,
+        J: IntoIterator<Item = &'a Package>,
+    {
+        let old: Vec<&Package> = old.into_iter().collect();
+        let new: Vec<&Package> = new.into_iter().collect();
+
+        let mut old_index = HashMap::with_capacity(old.len());
+        for &package in &old {
+            old_index.entry(package_key(package)).or_insert(package);
+        }
+
+        let new_keys: HashSet<_> = new.iter().map(|package| package_key(package)).collect();
+
+        let mut diff = FeedDiff::default();
+
+        for &package in &new {
+            match old_index.get(&package_key(package)) {
+                Some(&previous) => {
+                    let properties = changed_properties(previous, package);
+
+                    if !properties.is_empty() {
+                        diff.changed.push(PackageChange {
+                            old: previous,
+                            new: package,
+                            properties,
+                        });
+                    }
                 }
+                None => diff.added.push(package),
             }
-
-            diff.push(package);
         }
 
+        diff.removed = old
+            .into_iter()
+            .filter(|package| !new_keys.contains(&package_key(package)))
+            .collect();
+
         diff
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+fn changed_properties(old: &Package, new: &Package) -> Vec<Property> {
+    let (a, b) = (&old.properties, &new.properties);
+    let mut changed = Vec::new();
+
+    if old.is_listed() != new.is_listed() {
+        changed.push(Property::Listed);
+    }
+
+    if let (Some(old_hash), Some(new_hash)) = (a.package_hash(), b.package_hash()) {
+        if a.package_hash_algorithm() == b.package_hash_algorithm() && old_hash != new_hash {
+            changed.push(Property::PackageHash);
+        }
+    }
+
+    let metadata = [
+        (Property::Title, a.title() != b.title()),
+        (Property::Summary, a.summary() != b.summary()),
+        (Property::Description, a.description() != b.description()),
+        (Property::Authors, a.authors() != b.authors()),
+        (Property::Owners, a.owners() != b.owners()),
+        (Property::Tags, a.tags() != b.tags()),
+        (Property::Copyright, a.copyright() != b.copyright()),
+        (
+            Property::ReleaseNotes,
+            a.release_notes() != b.release_notes(),
+        ),
+        (
+            Property::ProjectUrl,
+            a.project_url().url() != b.project_url().url(),
+        ),
+        (
+            Property::LicenseUrl,
+            a.license_url().url() != b.license_url().url(),
+        ),
+        (Property::IconUrl, a.icon_url().url() != b.icon_url().url()),
+        (
+            Property::Dependencies,
+            match (a.dependencies(), b.dependencies()) {
+                (Some(a_dependencies), Some(b_dependencies)) => a_dependencies != b_dependencies,
+                _ => a.raw_dependencies() != b.raw_dependencies(),
+            },
+        ),
+        (
+            Property::RequireLicenseAcceptance,
+            a.require_license_acceptance() != b.require_license_acceptance(),
+        ),
+        (
+            Property::MinClientVersion,
+            a.minimum_client_version() != b.minimum_client_version(),
+        ),
+    ];
+
+    changed.extend(
+        metadata
+            .iter()
+            .filter(|&&(_, differs)| differs)
+            .map(|&(property, _)| property),
+    );
+
+    changed
 }
 
 impl fmt::Display for Feed {
@@ -58,6 +222,11 @@ mod tests {
     extern crate serde_xml_rs;
 
     use super::*;
+    use package::{date_from_str, PackageProperties};
+
+    fn date(s: &str) -> DateTime<Utc> {
+        date_from_str(s).unwrap()
+    }
 
     #[test]
     fn no_package_feed() {
@@ -77,7 +246,7 @@ mod tests {
             Feed {
                 id: String::from("http://schemas.datacontract.org/2004/07/"),
                 title: String::from(""),
-                updated_at: String::from("2017-06-14T19:49:57Z"),
+                updated_at: date("2017-06-14T19:49:57Z"),
                 links: vec![Link::_Self(String::from(
                     "https://www.nuget.\
                      org/api/v2/Packages"
@@ -153,7 +322,7 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
             String::from("http://schemas.datacontract.org/2004/07/")
         );
         assert_eq!(feed.title, String::from(""));
-        assert_eq!(feed.updated_at, String::from("2017-06-16T15:13:03Z"));
+        assert_eq!(feed.updated_at, date("2017-06-16T15:13:03Z"));
         assert_eq!(
             feed.links,
             vec![Link::_Self(String::from(
@@ -229,7 +398,7 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
             String::from("https://www.myget.org/F/omnisharp/api/v2/Packages")
         );
         assert_eq!(feed.title, String::from("Packages"));
-        assert_eq!(feed.updated_at, String::from("2017-06-16T15:14:44Z"));
+        assert_eq!(feed.updated_at, date("2017-06-16T15:14:44Z"));
         assert_eq!(feed.links, vec![Link::_Self(String::from("Packages"))]);
 
         assert_eq!(
@@ -300,7 +469,7 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
             String::from("http://proget/nuget/Default/Packages()/")
         );
         assert_eq!(feed.title, String::from("Packages"));
-        assert_eq!(feed.updated_at, String::from("2017-06-16T15:27:31Z"));
+        assert_eq!(feed.updated_at, date("2017-06-16T15:27:31Z"));
         assert_eq!(feed.links, vec![Link::_Self(String::from("Packages"))]);
 
         assert_eq!(format!("{}", feed), "Antlr4.Runtime 4.5.3-rc1\n");
@@ -362,12 +531,57 @@ r##"<feed xmlns="http://www.w3.org/2005/Atom" xmlns:d="http://schemas.microsoft.
             String::from("http://schemas.datacontract.org/2004/07/")
         );
         assert_eq!(feed.title, String::from(""));
-        assert_eq!(feed.updated_at, String::from("2017-06-16T16:06:10Z"));
+        assert_eq!(feed.updated_at, date("2017-06-16T16:06:10Z"));
         assert_eq!(feed.links, vec![Link::_Self(String::from("Packages"))]);
 
         assert_eq!(format!("{}", feed), "fint-eventsource 0.4.0.1\n");
     }
 
+    #[test]
+    fn paged_feed() {
+        // From https://www.nuget.org/api/v2/Packages()?$filter=Id eq 'EventSource4Net'
+        let feed_serialized =
+r##"<?xml version="1.0" encoding="utf-8"?>
+<feed xml:base="https://www.nuget.org/api/v2" xmlns="http://www.w3.org/2005/Atom" xmlns:d="http://schemas.microsoft.com/ado/2007/08/dataservices" xmlns:m="http://schemas.microsoft.com/ado/2007/08/dataservices/metadata" xmlns:georss="http://www.georss.org/georss" xmlns:gml="http://www.opengis.net/gml">
+   <id>http://schemas.datacontract.org/2004/07/</id>
+   <title />
+   <updated>2018-10-02T09:12:45Z</updated>
+   <link rel="self" href="https://www.nuget.org/api/v2/Packages" />
+   <link rel="next" href="https://www.nuget.org/api/v2/Packages?$filter=Id%20eq%20'EventSource4Net'&amp;$skiptoken='EventSource4Net','0.4.0.1'" />
+</feed>"##;
+
+        let feed: Feed = serde_xml_rs::from_reader(feed_serialized.as_bytes()).unwrap();
+
+        assert_eq!(
+            feed.links,
+            vec![
+                Link::_Self(String::from("https://www.nuget.org/api/v2/Packages")),
+                Link::Next(String::from(
+                    "https://www.nuget.org/api/v2/Packages?$filter=Id%20eq%20'EventSource4Net'&$skiptoken='EventSource4Net','0.4.0.1'"
+                )),
+            ]
+        );
+        assert_eq!(
+            feed.next_link(),
+            Some("https://www.nuget.org/api/v2/Packages?$filter=Id%20eq%20'EventSource4Net'&$skiptoken='EventSource4Net','0.4.0.1'")
+        );
+    }
+
+    #[test]
+    fn unpaged_feed() {
+        let feed_serialized = r##"<?xml version="1.0" encoding="utf-8"?>
+<feed xml:base="https://www.nuget.org/api/v2" xmlns="http://www.w3.org/2005/Atom">
+   <id>http://schemas.datacontract.org/2004/07/</id>
+   <title />
+   <updated>2018-10-02T09:12:45Z</updated>
+   <link rel="self" href="https://www.nuget.org/api/v2/Packages" />
+</feed>"##;
+
+        let feed: Feed = serde_xml_rs::from_reader(feed_serialized.as_bytes()).unwrap();
+
+        assert_eq!(feed.next_link(), None);
+    }
+
     #[test]
     fn many_package_feed() {
         let feed_serialized =
@@ -847,4 +1061,66 @@ r##"<?xml version="1.0" encoding="utf-8"?>
         assert_eq!(feed_1_only_packages, "_51Wp.AccountSdk 1.0.0");
         assert_eq!(feed_2_only_packages, "fint-eventsource 0.4.0.1");
     }
+
+    fn package(id: &str, version: &str, hash: &str) -> Package {
+        Package::for_test(
+            id,
+            version,
+            PackageProperties {
+                description: String::from("A package"),
+                package_hash: Some(String::from(hash)),
+                package_hash_algorithm: Some(String::from("SHA512")),
+                ..PackageProperties::default()
+            },
+        )
+    }
+
+    #[test]
+    fn diffing() {
+        let old = vec![
+            package("Foo", "1.0", "aaaa"),
+            package("Bar", "1.0.0", "bbbb"),
+            package("Baz", "1.0.0", "cccc"),
+            package("Qux", "2.0.0-beta", "dddd"),
+        ];
+
+        let mut unlisted = package("foo", "1.0.0", "eeee");
+        unlisted.properties.is_listed = Some(false);
+        let mut described = package("Bar", "1.0.0", "bbbb");
+        described.properties.description = String::from("A better package");
+        let mut unhashed = package("Qux", "2.0.0-BETA", "");
+        unhashed.properties.package_hash = None;
+
+        let new = vec![
+            unlisted,
+            described,
+            unhashed,
+            package("Baz", "1.1.0", "ffff"),
+        ];
+
+        let diff = FeedDiff::new(&old, &new);
+
+        assert_eq!(
+            diff.added.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+            vec!["Baz 1.1.0"]
+        );
+        assert_eq!(
+            diff.removed
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>(),
+            vec!["Baz 1.0.0"]
+        );
+        assert_eq!(diff.changed.len(), 2);
+        assert_eq!(diff.changed[0].old.to_string(), "Foo 1.0");
+        assert_eq!(diff.changed[0].new.to_string(), "foo 1.0.0");
+        assert_eq!(
+            diff.changed[0].properties,
+            vec![Property::Listed, Property::PackageHash]
+        );
+        assert_eq!(diff.changed[1].new.to_string(), "Bar 1.0.0");
+        assert_eq!(diff.changed[1].properties, vec![Property::Description]);
+
+        assert!(FeedDiff::new(&old, &old).is_empty());
+    }
 }
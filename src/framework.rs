@@ -0,0 +1,781 @@
+use failure::Error;
+use serde::de::Error as DeserializeError;
+use serde::{Deserialize, Deserializer};
+use std::cmp::Reverse;
+use std::fmt;
+use std::str::FromStr;
+
+const ANY: &str = "Any";
+const NET_CORE: &str = ".NETCore";
+const NET_CORE_APP: &str = ".NETCoreApp";
+const NET_FRAMEWORK: &str = ".NETFramework";
+const NET_PLATFORM: &str = ".NETPlatform";
+const NET_STANDARD: &str = ".NETStandard";
+const PORTABLE: &str = ".NETPortable";
+const UAP: &str = "UAP";
+const WINDOWS: &str = "Windows";
+const WINDOWS_PHONE: &str = "WindowsPhone";
+const WINDOWS_PHONE_APP: &str = "WindowsPhoneApp";
+
+// Short folder names and the framework identifiers they stand for.
+const IDENTIFIERS: &[(&str, &str)] = &[
+    ("any", ANY),
+    ("net", NET_FRAMEWORK),
+    ("netstandard", NET_STANDARD),
+    ("netcoreapp", NET_CORE_APP),
+    ("netcore", NET_CORE),
+    ("dotnet", NET_PLATFORM),
+    ("portable", PORTABLE),
+    ("uap", UAP),
+    ("win", WINDOWS),
+    ("winrt", WINDOWS),
+    ("wp", WINDOWS_PHONE),
+    ("wpa", WINDOWS_PHONE_APP),
+    ("sl", "Silverlight"),
+    ("netmf", ".NETMicroFramework"),
+    ("monoandroid", "MonoAndroid"),
+    ("monotouch", "MonoTouch"),
+    ("monomac", "MonoMac"),
+    ("xamarinios", "Xamarin.iOS"),
+    ("xamarinmac", "Xamarin.Mac"),
+    ("xamarintvos", "Xamarin.TVOS"),
+    ("xamarinwatchos", "Xamarin.WatchOS"),
+    ("tizen", "Tizen"),
+    ("native", "native"),
+];
+
+// Frameworks whose short names always use dotted versions, e.g. `netstandard2.0`.
+const DOTTED_VERSIONS: &[&str] = &[NET_STANDARD, NET_CORE_APP, NET_PLATFORM, UAP, "Tizen"];
+
+// Frameworks whose short names can have a single digit version, e.g. `win8`.
+const SINGLE_DIGIT_VERSIONS: &[&str] = &[WINDOWS, WINDOWS_PHONE, "Silverlight"];
+
+// Short and long profile names.
+const PROFILES: &[(&str, &str)] = &[("client", "Client"), ("cf", "CompactFramework")];
+
+// The frameworks in each numbered portable class library profile.
+const PORTABLE_PROFILES: &[(u32, &str)] = &[
+    (2, "net4+win8+sl4+wp7"),
+    (3, "net4+sl4"),
+    (4, "net45+sl4+win8+wp7"),
+    (5, "net4+win8"),
+    (6, "net403+win8"),
+    (7, "net45+win8"),
+    (14, "net4+sl5"),
+    (18, "net403+sl4"),
+    (19, "net403+sl5"),
+    (23, "net45+sl4"),
+    (24, "net45+sl5"),
+    (31, "win81+wp81"),
+    (32, "win81+wpa81"),
+    (36, "net4+sl4+win8+wp8"),
+    (37, "net4+sl5+win8"),
+    (41, "net403+sl4+win8"),
+    (42, "net403+sl5+win8"),
+    (44, "net451+win81"),
+    (46, "net45+sl4+win8"),
+    (47, "net45+sl5+win8"),
+    (49, "net45+wp8"),
+    (78, "net45+win8+wp8"),
+    (84, "wp81+wpa81"),
+    (88, "net4+sl4+win8+wp75"),
+    (92, "net4+win8+wpa81"),
+    (95, "net403+sl4+win8+wp7"),
+    (96, "net403+sl4+win8+wp75"),
+    (102, "net403+win8+wpa81"),
+    (104, "net45+sl4+win8+wp75"),
+    (111, "net45+win8+wpa81"),
+    (136, "net4+sl5+win8+wp8"),
+    (143, "net403+sl4+win8+wp8"),
+    (147, "net403+sl5+win8+wp8"),
+    (151, "net451+win81+wpa81"),
+    (154, "net45+sl4+win8+wp8"),
+    (157, "win81+wp81+wpa81"),
+    (158, "net45+sl5+win8+wp8"),
+    (225, "net4+sl5+win8+wpa81"),
+    (240, "net403+sl5+win8+wpa8"),
+    (255, "net45+sl5+win8+wpa81"),
+    (259, "net45+win8+wpa81+wp8"),
+    (328, "net4+sl5+win8+wpa81+wp8"),
+    (336, "net403+sl5+win8+wpa81+wp8"),
+    (344, "net45+sl5+win8+wpa81+wp8"),
+];
+
+/// A target framework, following https://docs.microsoft.com/en-us/nuget/reference/target-frameworks
+///
+/// Parses short folder names (`net45`, `netstandard2.0`, `net6.0-windows`,
+/// `portable-net45+win8`) as well as long names (`.NETFramework,Version=v4.5`
+/// and the `.NETCore50` style used by V2 dependencies). An empty string is
+/// the `Any` framework, which every framework supports.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NuGetFramework {
+    framework: String,
+    version: [u64; 4],
+    profile: String,
+    platform: String,
+    platform_version: [u64; 4],
+}
+
+impl NuGetFramework {
+    pub fn new(framework: &str, version: [u64; 4]) -> NuGetFramework {
+        NuGetFramework {
+            framework: String::from(canonical_identifier(framework).unwrap_or(framework)),
+            version,
+            profile: String::new(),
+            platform: String::new(),
+            platform_version: [0; 4],
+        }
+    }
+
+    /// The framework that packages without any framework specific assets or dependencies target.
+    pub fn any() -> NuGetFramework {
+        NuGetFramework::new(ANY, [0; 4])
+    }
+
+    /// Whether the identifier is one NuGet knows, rather than e.g. an arbitrary folder name.
+    pub fn is_known(&self) -> bool {
+        canonical_identifier(&self.framework).is_some()
+    }
+
+    /// The framework identifier, e.g. `.NETFramework`.
+    pub fn framework(&self) -> &str {
+        &self.framework
+    }
+
+    pub fn version(&self) -> [u64; 4] {
+        self.version
+    }
+
+    /// The profile, e.g. `Client`, or the frameworks of a portable profile, e.g. `net45+win8`.
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    /// The operating system of a `net5.0` or later framework, e.g. `windows`.
+    pub fn platform(&self) -> Option<&str> {
+        if self.platform.is_empty() {
+            None
+        } else {
+            Some(&self.platform)
+        }
+    }
+
+    pub fn platform_version(&self) -> [u64; 4] {
+        self.platform_version
+    }
+
+    pub fn is_any(&self) -> bool {
+        self.framework == ANY
+    }
+
+    pub fn is_portable(&self) -> bool {
+        self.framework == PORTABLE
+    }
+
+    /// The frameworks a portable framework targets.
+    pub fn portable_frameworks(&self) -> Vec<NuGetFramework> {
+        if !self.is_portable() {
+            return Vec::new();
+        }
+
+        self.profile
+            .split('+')
+            .filter_map(|framework| framework.parse().ok())
+            .collect()
+    }
+
+    /// The short folder name, e.g. `net45` or `net6.0-windows`.
+    pub fn to_short_name(&self) -> String {
+        if self.is_any() {
+            return String::from("any");
+        }
+
+        if self.is_portable() {
+            return format!("portable-{}", self.profile);
+        }
+
+        let is_net = self.framework == NET_CORE_APP && self.version[0] >= 5;
+        let mut short_name = if is_net {
+            String::from("net")
+        } else {
+            IDENTIFIERS
+                .iter()
+                .find(|&&(_, long)| long == self.framework)
+                .map(|&(short, _)| String::from(short))
+                .unwrap_or_else(|| self.framework.to_lowercase())
+        };
+
+        if self.version != [0; 4] {
+            let dotted = is_net
+                || DOTTED_VERSIONS.contains(&&*self.framework)
+                || self.version.iter().any(|&part| part > 9);
+
+            if dotted {
+                short_name.push_str(&dotted_version(&self.version, 2));
+            } else {
+                let min_parts = if SINGLE_DIGIT_VERSIONS.contains(&&*self.framework) {
+                    1
+                } else {
+                    2
+                };
+                short_name.push_str(&dotted_version(&self.version, min_parts).replace('.', ""));
+            }
+        }
+
+        if !self.profile.is_empty() {
+            let profile = PROFILES
+                .iter()
+                .find(|&&(_, long)| long == self.profile)
+                .map(|&(short, _)| short)
+                .unwrap_or(&self.profile);
+
+            short_name.push('-');
+            short_name.push_str(profile);
+        }
+
+        if !self.platform.is_empty() {
+            short_name.push('-');
+            short_name.push_str(&self.platform);
+
+            if self.platform_version != [0; 4] {
+                short_name.push_str(&dotted_version(&self.platform_version, 2));
+            }
+        }
+
+        short_name
+    }
+
+    /// The long name, e.g. `.NETFramework,Version=v4.5,Profile=Client`.
+    pub fn to_long_name(&self) -> String {
+        let mut long_name = format!(
+            "{},Version=v{}",
+            self.framework,
+            dotted_version(&self.version, 2)
+        );
+
+        if !self.profile.is_empty() {
+            long_name.push_str(",Profile=");
+            long_name.push_str(&self.profile);
+        }
+
+        long_name
+    }
+
+    /// Whether a project targeting this framework can use assets built for `package`.
+    pub fn supports(&self, package: &NuGetFramework) -> bool {
+        if package.is_any() || self.supports_directly(package) {
+            return true;
+        }
+
+        if package.is_portable() {
+            let package_frameworks = package.portable_frameworks();
+
+            if self.is_portable() {
+                let frameworks = self.portable_frameworks();
+                return !frameworks.is_empty()
+                    && frameworks.iter().all(|framework| {
+                        package_frameworks
+                            .iter()
+                            .any(|package| framework.supports(package))
+                    });
+            }
+
+            return package_frameworks
+                .iter()
+                .any(|package| self.supports(package));
+        }
+
+        if self.is_portable() {
+            let frameworks = self.portable_frameworks();
+            return !frameworks.is_empty()
+                && frameworks
+                    .iter()
+                    .all(|framework| framework.supports(package));
+        }
+
+        self.compatible_frameworks().iter().any(|framework| {
+            // .NETStandard is the only framework whose compatible frameworks are worth following
+            if framework.framework == NET_STANDARD {
+                framework.supports(package)
+            } else {
+                framework.supports_directly(package)
+            }
+        })
+    }
+
+    /// The framework in `candidates` that is nearest to this one, using NuGet's preferences.
+    ///
+    /// A framework of the same family is preferred, then a platform agnostic
+    /// version of it, then other compatible families, `.NETStandard`,
+    /// `.NETPlatform`, the portable profile targeting the fewest frameworks,
+    /// and finally `Any`. Higher versions win within each of those.
+    pub fn nearest<'a, I>(&self, candidates: I) -> Option<&'a NuGetFramework>
+    where
+        I: IntoIterator<Item = &'a NuGetFramework>,
+    {
+        candidates
+            .into_iter()
+            .filter(|candidate| self.supports(candidate))
+            .min_by_key(|candidate| {
+                let tier = if candidate.is_any() {
+                    6
+                } else if candidate.is_portable() {
+                    5
+                } else if candidate.framework == NET_PLATFORM && self.framework != NET_PLATFORM {
+                    4
+                } else if candidate.framework == NET_STANDARD && self.framework != NET_STANDARD {
+                    3
+                } else if !candidate.framework.eq_ignore_ascii_case(&self.framework) {
+                    2
+                } else if candidate.platform != self.platform {
+                    1
+                } else {
+                    0
+                };
+
+                (
+                    tier,
+                    candidate.portable_frameworks().len(),
+                    Reverse(candidate.version),
+                    !candidate.profile.eq_ignore_ascii_case(&self.profile),
+                    Reverse(candidate.platform_version),
+                )
+            })
+    }
+
+    // Compatibility within a single framework family.
+    fn supports_directly(&self, package: &NuGetFramework) -> bool {
+        let profile =
+            package.profile.is_empty() || package.profile.eq_ignore_ascii_case(&self.profile);
+        let platform = package.platform.is_empty()
+            || (package.platform.eq_ignore_ascii_case(&self.platform)
+                && self.platform_version >= package.platform_version);
+
+        self.framework.eq_ignore_ascii_case(&package.framework)
+            && self.version >= package.version
+            && profile
+            && platform
+    }
+
+    // The highest versions of other framework families that this framework supports.
+    fn compatible_frameworks(&self) -> Vec<NuGetFramework> {
+        let at_least = |version: [u64; 4]| self.version >= version;
+        let netstandard = |major, minor| NuGetFramework::new(NET_STANDARD, [major, minor, 0, 0]);
+
+        let mut frameworks = Vec::new();
+
+        match &*self.framework {
+            NET_FRAMEWORK if at_least([4, 6, 1, 0]) => frameworks.push(netstandard(2, 0)),
+            NET_FRAMEWORK if at_least([4, 6, 0, 0]) => frameworks.push(netstandard(1, 3)),
+            NET_FRAMEWORK if at_least([4, 5, 1, 0]) => frameworks.push(netstandard(1, 2)),
+            NET_FRAMEWORK if at_least([4, 5, 0, 0]) => frameworks.push(netstandard(1, 1)),
+            NET_CORE_APP if at_least([3, 0, 0, 0]) => frameworks.push(netstandard(2, 1)),
+            NET_CORE_APP if at_least([2, 0, 0, 0]) => frameworks.push(netstandard(2, 0)),
+            NET_CORE_APP if at_least([1, 0, 0, 0]) => frameworks.push(netstandard(1, 6)),
+            UAP if at_least([10, 0, 0, 0]) => {
+                frameworks.push(if at_least([10, 0, 16299, 0]) {
+                    netstandard(2, 0)
+                } else {
+                    netstandard(1, 4)
+                });
+                frameworks.push(NuGetFramework::new(NET_CORE, [5, 0, 0, 0]));
+                frameworks.push(NuGetFramework::new(WINDOWS, [8, 1, 0, 0]));
+                frameworks.push(NuGetFramework::new(WINDOWS_PHONE_APP, [8, 1, 0, 0]));
+            }
+            NET_CORE if at_least([5, 0, 0, 0]) => {
+                frameworks.push(netstandard(1, 4));
+                frameworks.push(NuGetFramework::new(WINDOWS, [8, 1, 0, 0]));
+                frameworks.push(NuGetFramework::new(WINDOWS_PHONE_APP, [8, 1, 0, 0]));
+            }
+            NET_CORE if at_least([4, 5, 1, 0]) => {
+                frameworks.push(netstandard(1, 2));
+                frameworks.push(NuGetFramework::new(WINDOWS, [8, 1, 0, 0]));
+            }
+            NET_CORE if at_least([4, 5, 0, 0]) => {
+                frameworks.push(netstandard(1, 1));
+                frameworks.push(NuGetFramework::new(WINDOWS, [8, 0, 0, 0]));
+            }
+            WINDOWS if at_least([8, 1, 0, 0]) => {
+                frameworks.push(netstandard(1, 2));
+                frameworks.push(NuGetFramework::new(NET_CORE, [4, 5, 1, 0]));
+            }
+            WINDOWS if at_least([8, 0, 0, 0]) => {
+                frameworks.push(netstandard(1, 1));
+                frameworks.push(NuGetFramework::new(NET_CORE, [4, 5, 0, 0]));
+            }
+            WINDOWS_PHONE_APP if at_least([8, 1, 0, 0]) => frameworks.push(netstandard(1, 2)),
+            WINDOWS_PHONE if at_least([8, 0, 0, 0]) => frameworks.push(netstandard(1, 0)),
+            "MonoAndroid" | "MonoTouch" | "MonoMac" | "Xamarin.iOS" | "Xamarin.Mac"
+            | "Xamarin.TVOS" | "Xamarin.WatchOS" => frameworks.push(netstandard(2, 0)),
+            "Tizen" if at_least([4, 0, 0, 0]) => frameworks.push(netstandard(2, 0)),
+            "Tizen" if at_least([3, 0, 0, 0]) => frameworks.push(netstandard(1, 6)),
+            // netstandard1.x is the successor of dotnet5.(x+1)
+            NET_STANDARD => {
+                let minor = if self.version[0] >= 2 {
+                    5
+                } else {
+                    self.version[1]
+                };
+                frameworks.push(NuGetFramework::new(NET_PLATFORM, [5, minor + 1, 0, 0]));
+            }
+            _ => {}
+        }
+
+        frameworks
+    }
+}
+
+impl Default for NuGetFramework {
+    fn default() -> NuGetFramework {
+        NuGetFramework::any()
+    }
+}
+
+#[allow(deprecated)]
+fn canonical_identifier(identifier: &str) -> Option<&'static str> {
+    IDENTIFIERS
+        .iter()
+        .find(|&&(short, long)| {
+            short.eq_ignore_ascii_case(identifier)
+                || long
+                    .trim_left_matches('.')
+                    .eq_ignore_ascii_case(identifier.trim_left_matches('.'))
+        })
+        .map(|&(_, long)| long)
+}
+
+// Renders `version` with trailing zeros removed, keeping at least `min_parts` parts.
+fn dotted_version(version: &[u64; 4], min_parts: usize) -> String {
+    let parts = version
+        .iter()
+        .rposition(|&part| part != 0)
+        .map_or(0, |last| last + 1)
+        .max(min_parts);
+
+    version[..parts]
+        .iter()
+        .map(|part| part.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// Parses `4.5.1` or the compact `451` form, where each digit is a part.
+fn parse_version(version: &str, framework: &str) -> Result<[u64; 4], Error> {
+    let parts = if version.contains('.') {
+        version
+            .split('.')
+            .map(|part| part.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format_err!("{} has an invalid version {}", framework, version))?
+    } else {
+        version
+            .chars()
+            .map(|digit| {
+                digit
+                    .to_digit(10)
+                    .map(u64::from)
+                    .ok_or_else(|| format_err!("{} has an invalid version {}", framework, version))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    if parts.is_empty() || parts.len() > 4 {
+        bail!("{} has an invalid version {}", framework, version);
+    }
+
+    let mut padded = [0; 4];
+    padded[..parts.len()].copy_from_slice(&parts);
+    Ok(padded)
+}
+
+// Splits `net45` into its identifier and version, at the first digit.
+fn split_version(s: &str) -> (&str, &str) {
+    match s.find(|c: char| c.is_ascii_digit()) {
+        Some(index) => (&s[..index], &s[index..]),
+        None => (s, ""),
+    }
+}
+
+// Normalizes a portable profile into its sorted short framework names.
+fn parse_portable_profile(profile: &str, framework: &str) -> Result<String, Error> {
+    let frameworks = if profile.len() > 7 && profile[..7].eq_ignore_ascii_case("Profile") {
+        let number = profile[7..].parse::<u32>()?;
+        PORTABLE_PROFILES
+            .iter()
+            .find(|&&(known, _)| known == number)
+            .map(|&(_, frameworks)| frameworks)
+            .ok_or_else(|| format_err!("{} has an unknown portable profile", framework))?
+    } else {
+        profile
+    };
+
+    let mut frameworks = frameworks
+        .split('+')
+        .map(|framework| {
+            let framework = framework.parse::<NuGetFramework>()?;
+            if framework.is_portable() || framework.is_any() {
+                bail!("{} is not a valid portable profile", profile);
+            }
+            Ok(framework.to_short_name())
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    frameworks.sort();
+    frameworks.dedup();
+    Ok(frameworks.join("+"))
+}
+
+impl FromStr for NuGetFramework {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if s.is_empty() {
+            return Ok(NuGetFramework::any());
+        }
+
+        // `.NETFramework,Version=v4.5,Profile=Client`
+        if s.contains(',') {
+            let mut properties = s.split(',').map(str::trim);
+            let identifier = properties.next().unwrap_or_default();
+            let mut framework = NuGetFramework::new(identifier, [0; 4]);
+
+            for property in properties {
+                let mut key_value = property.splitn(2, '=');
+                let key = key_value.next().unwrap_or_default();
+                let value = key_value
+                    .next()
+                    .ok_or_else(|| format_err!("{} has an invalid property {}", s, property))?
+                    .trim();
+
+                if key.eq_ignore_ascii_case("Version") {
+                    #[allow(deprecated)]
+                    let version = value.trim_left_matches(&['v', 'V'][..]);
+                    framework.version = parse_version(version, s)?;
+                } else if key.eq_ignore_ascii_case("Profile") {
+                    framework.profile = String::from(value);
+                } else {
+                    bail!("{} has an unknown property {}", s, key);
+                }
+            }
+
+            if framework.is_portable() {
+                framework.profile = parse_portable_profile(&framework.profile, s)?;
+            }
+
+            return Ok(framework);
+        }
+
+        let mut segments = s.splitn(2, '-');
+        let (identifier, version) = split_version(segments.next().unwrap_or_default());
+        let suffix = segments.next();
+
+        if identifier.is_empty() {
+            bail!("{} is not a valid framework", s);
+        }
+
+        let mut framework = NuGetFramework::new(identifier, [0; 4]);
+        if !version.is_empty() {
+            framework.version = parse_version(version, s)?;
+        }
+
+        // `net5.0` and later are .NETCoreApp, with an optional platform instead of a profile
+        if framework.framework == NET_FRAMEWORK && framework.version[0] >= 5 {
+            framework.framework = String::from(NET_CORE_APP);
+        }
+
+        match suffix {
+            Some(suffix) if framework.is_portable() => {
+                framework.profile = parse_portable_profile(suffix, s)?;
+            }
+            Some(suffix) if framework.framework == NET_CORE_APP && framework.version[0] >= 5 => {
+                let (platform, platform_version) = split_version(suffix);
+                if platform.is_empty() {
+                    bail!("{} is not a valid platform", suffix);
+                }
+
+                framework.platform = platform.to_lowercase();
+                if !platform_version.is_empty() {
+                    framework.platform_version = parse_version(platform_version, s)?;
+                }
+            }
+            Some(suffix) => {
+                framework.profile = PROFILES
+                    .iter()
+                    .find(|&&(short, long)| {
+                        short.eq_ignore_ascii_case(suffix) || long.eq_ignore_ascii_case(suffix)
+                    })
+                    .map(|&(_, long)| String::from(long))
+                    .unwrap_or_else(|| String::from(suffix));
+            }
+            None if framework.is_portable() => bail!("{} has no portable profile", s),
+            None => {}
+        }
+
+        Ok(framework)
+    }
+}
+
+impl fmt::Display for NuGetFramework {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_short_name())
+    }
+}
+
+impl<'de> Deserialize<'de> for NuGetFramework {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(DeserializeError::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn framework(s: &str) -> NuGetFramework {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parsing() {
+        let parsed = framework("net451");
+        assert_eq!(parsed.framework(), ".NETFramework");
+        assert_eq!(parsed.version(), [4, 5, 1, 0]);
+        assert_eq!(parsed, framework(".NETFramework,Version=v4.5.1"));
+        assert_eq!(parsed, framework(".NETFramework4.5.1"));
+
+        assert_eq!(framework(".NETCore50"), framework("netcore50"));
+        assert_eq!(framework("dotnet5.4").framework(), ".NETPlatform");
+        assert_eq!(framework("net40-client").profile(), "Client");
+        assert_eq!(
+            framework("net40-client"),
+            framework(".NETFramework,Version=v4.0,Profile=Client")
+        );
+        assert_eq!(framework("netstandard2.0"), framework(".NETStandard2.0"));
+        assert_eq!(framework(""), NuGetFramework::any());
+
+        let net = framework("net6.0-windows10.0.19041");
+        assert_eq!(net.framework(), ".NETCoreApp");
+        assert_eq!(net.platform(), Some("windows"));
+        assert_eq!(net.platform_version(), [10, 0, 19041, 0]);
+        assert_eq!(framework("net5.0"), framework("netcoreapp5.0"));
+
+        for invalid in &["45", "net4.x", "net12345", "portable", "portable-Profile1"] {
+            assert!(invalid.parse::<NuGetFramework>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn portable_profiles() {
+        let portable = framework("portable-win8+net45");
+        assert!(portable.is_portable());
+        assert_eq!(portable.profile(), "net45+win8");
+        assert_eq!(
+            portable.portable_frameworks(),
+            vec![framework("net45"), framework("win8")]
+        );
+        assert_eq!(
+            framework(".NETPortable,Version=v0.0,Profile=Profile259"),
+            framework("portable-net45+win8+wpa81+wp8")
+        );
+    }
+
+    #[test]
+    fn normalization() {
+        for &(name, short_name) in &[
+            ("net45", "net45"),
+            (".NETFramework,Version=v4.0", "net40"),
+            (".NETFramework,Version=v4.0,Profile=Client", "net40-client"),
+            ("NETStandard2.0", "netstandard2.0"),
+            (".NETCoreApp,Version=v3.1", "netcoreapp3.1"),
+            ("net6.0-Windows", "net6.0-windows"),
+            ("net6.0-windows10.0", "net6.0-windows10.0"),
+            ("net50", "net5.0"),
+            ("Windows8", "win8"),
+            ("WindowsPhoneApp8.1", "wpa81"),
+            ("uap10.0.16299", "uap10.0.16299"),
+            ("portable-net45+win8", "portable-net45+win8"),
+            ("any", "any"),
+        ] {
+            assert_eq!(framework(name).to_short_name(), short_name, "{}", name);
+        }
+
+        assert_eq!(
+            framework("net40-client").to_long_name(),
+            ".NETFramework,Version=v4.0,Profile=Client"
+        );
+    }
+
+    #[test]
+    fn compatibility() {
+        let supports =
+            |project: &str, package: &str| framework(project).supports(&framework(package));
+
+        assert!(supports("net45", "net40"));
+        assert!(!supports("net40", "net45"));
+        assert!(supports("net40-client", "net40"));
+        assert!(!supports("net40", "net40-client"));
+        assert!(supports("net461", "netstandard2.0"));
+        assert!(supports("net461", "netstandard1.4"));
+        assert!(!supports("net45", "netstandard1.2"));
+        assert!(supports("netcoreapp3.1", "netstandard2.1"));
+        assert!(supports("net6.0", "netcoreapp3.1"));
+        assert!(supports("net6.0-windows", "net5.0"));
+        assert!(!supports("net6.0", "net6.0-windows"));
+        assert!(supports("net6.0-windows10.0.19041", "net6.0-windows10.0"));
+        assert!(supports("netstandard1.3", "dotnet5.4"));
+        assert!(supports("net46", "dotnet5.4"));
+        assert!(supports("uap10.0", "win81"));
+        assert!(supports("win8", "netcore45"));
+        assert!(supports("netstandard1.0", "any"));
+        assert!(supports("net45", "portable-net45+win8"));
+        assert!(!supports("net40", "portable-net45+win8"));
+        assert!(supports("portable-net45+win8", "portable-net45+win8+wp8"));
+        assert!(!supports("portable-net45+win8+wp8", "portable-net45+win8"));
+        assert!(supports("portable-net45+win8", "netstandard1.0"));
+    }
+
+    #[test]
+    fn nearest() {
+        let candidates = [
+            "net40",
+            "net45",
+            "netstandard1.0",
+            "netstandard2.0",
+            "portable-net45+win8+wp8",
+            "portable-net45+win8",
+            "net5.0",
+            "net5.0-windows",
+            "any",
+        ]
+        .iter()
+        .map(|candidate| framework(candidate))
+        .collect::<Vec<_>>();
+
+        let nearest = |project: &str| {
+            framework(project)
+                .nearest(&candidates)
+                .map(NuGetFramework::to_short_name)
+        };
+
+        assert_eq!(nearest("net48"), Some(String::from("net45")));
+        assert_eq!(nearest("net6.0"), Some(String::from("net5.0")));
+        assert_eq!(
+            nearest("net6.0-windows"),
+            Some(String::from("net5.0-windows"))
+        );
+        assert_eq!(
+            nearest("netcoreapp2.1"),
+            Some(String::from("netstandard2.0"))
+        );
+        assert_eq!(nearest("win8"), Some(String::from("netstandard1.0")));
+        assert_eq!(nearest("wp8"), Some(String::from("netstandard1.0")));
+        assert_eq!(nearest("sl5"), Some(String::from("any")));
+    }
+}
@@ -0,0 +1,424 @@
+use base64;
+use failure::{Error, Fail};
+use package::Package;
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt;
+use std::io::{Read, Write};
+
+/// A package download that doesn't match what the feed declared for it.
+#[derive(Debug, PartialEq)]
+pub enum IntegrityError {
+    HashMismatch {
+        package: String,
+        algorithm: String,
+        expected: String,
+        actual: String,
+    },
+    Truncated {
+        package: String,
+        expected: u64,
+        actual: u64,
+    },
+    /// More bytes were received than expected, which is as far as was read.
+    TooLarge {
+        package: String,
+        expected: u64,
+        actual: u64,
+    },
+    SizeMismatch {
+        package: String,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntegrityError::HashMismatch {
+                package,
+                algorithm,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} has a {} hash of {}, but {} was expected",
+                package, algorithm, actual, expected
+            ),
+            IntegrityError::Truncated {
+                package,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} is {} bytes, but {} were expected",
+                package, actual, expected
+            ),
+            IntegrityError::TooLarge {
+                package,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} is at least {} bytes, but only {} were expected",
+                package, actual, expected
+            ),
+            IntegrityError::SizeMismatch {
+                package,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} is served as {} bytes, but is declared as {}",
+                package, actual, expected
+            ),
+        }
+    }
+}
+
+impl Fail for IntegrityError {}
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+/// Checks a package's content against its declared hash and size as it is read.
+pub struct Verifier {
+    package: String,
+    hasher: Option<(String, Hasher)>,
+    expected_hash: Option<String>,
+    expected_size: Option<u64>,
+    received: u64,
+}
+
+impl Verifier {
+    /// Fails if the package declares a hash with an algorithm other than SHA256 or SHA512.
+    pub fn new(package: &Package) -> Result<Verifier, Error> {
+        let properties = &package.properties;
+
+        let hasher = match (
+            properties.package_hash(),
+            properties.package_hash_algorithm(),
+        ) {
+            (Some(hash), algorithm) if !hash.is_empty() => {
+                // nuget.org only sends SHA512, which is also the default for pushes
+                let algorithm = algorithm
+                    .filter(|algorithm| !algorithm.is_empty())
+                    .unwrap_or("SHA512")
+                    .to_uppercase();
+
+                let hasher = match &*algorithm {
+                    "SHA256" => Hasher::Sha256(Sha256::new()),
+                    "SHA512" => Hasher::Sha512(Sha512::new()),
+                    _ => bail!(
+                        "{} has an unsupported hash algorithm {}",
+                        package,
+                        algorithm
+                    ),
+                };
+
+                Some((algorithm, hasher))
+            }
+            _ => None,
+        };
+
+        let expected_size = match properties.package_size() {
+            0 => None,
+            size => Some(size),
+        };
+
+        Ok(Verifier {
+            package: package.to_string(),
+            hasher,
+            expected_hash: properties.package_hash().map(String::from),
+            expected_size,
+            received: 0,
+        })
+    }
+
+    /// Checks the size a server reports, e.g. in a response's `Content-Length`.
+    ///
+    /// Fails if the package declares a different size, and otherwise expects
+    /// the reported size if the package doesn't declare one.
+    pub fn expect_size(&mut self, size: u64) -> Result<(), IntegrityError> {
+        match self.expected_size {
+            Some(expected) if expected != size => Err(IntegrityError::SizeMismatch {
+                package: self.package.clone(),
+                expected,
+                actual: size,
+            }),
+            _ => {
+                self.expected_size = Some(size);
+                Ok(())
+            }
+        }
+    }
+
+    /// The number of bytes received so far.
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    pub fn expected_size(&self) -> Option<u64> {
+        self.expected_size
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.received += bytes.len() as u64;
+
+        match self.hasher {
+            Some((_, Hasher::Sha256(ref mut hasher))) => hasher.input(bytes),
+            Some((_, Hasher::Sha512(ref mut hasher))) => hasher.input(bytes),
+            None => {}
+        }
+    }
+
+    /// Feeds everything in `reader` to `update`.
+    pub fn update_from<R: Read>(&mut self, mut reader: R) -> Result<(), Error> {
+        let mut chunk = [0; 64 * 1024];
+        loop {
+            let read = reader.read(&mut chunk)?;
+            if read == 0 {
+                return Ok(());
+            }
+
+            self.update(&chunk[..read]);
+        }
+    }
+
+    /// Checks everything has been received, and that it has the declared hash.
+    pub fn finish(self) -> Result<(), IntegrityError> {
+        match self.expected_size {
+            Some(expected) if self.received < expected => {
+                return Err(IntegrityError::Truncated {
+                    package: self.package,
+                    expected,
+                    actual: self.received,
+                })
+            }
+            Some(expected) if self.received > expected => {
+                return Err(IntegrityError::TooLarge {
+                    package: self.package,
+                    expected,
+                    actual: self.received,
+                })
+            }
+            _ => {}
+        }
+
+        if let (Some((algorithm, hasher)), Some(expected)) = (self.hasher, self.expected_hash) {
+            let actual = match hasher {
+                Hasher::Sha256(hasher) => hasher.result().to_vec(),
+                Hasher::Sha512(hasher) => hasher.result().to_vec(),
+            };
+
+            // Compare the decoded bytes so differences in padding don't matter
+            if base64::decode(&expected).ok() != Some(actual.clone()) {
+                return Err(IntegrityError::HashMismatch {
+                    package: self.package,
+                    algorithm,
+                    expected,
+                    actual: base64::encode(&actual),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies `reader` into `writer`, verifying it once `reader` is exhausted.
+    ///
+    /// Stops without writing a chunk that would take it past the expected size.
+    ///
+    /// `progress` is called after each chunk with the bytes received so far,
+    /// including any passed to `update` beforehand, and the expected size if known.
+    pub fn copy<R, W, F>(
+        mut self,
+        mut reader: R,
+        mut writer: W,
+        mut progress: F,
+    ) -> Result<u64, Error>
+    where
+        R: Read,
+        W: Write,
+        F: FnMut(u64, Option<u64>),
+    {
+        let mut chunk = [0; 64 * 1024];
+        loop {
+            let read = reader.read(&mut chunk)?;
+            if read == 0 {
+                break;
+            }
+
+            let received = self.received + read as u64;
+            match self.expected_size {
+                Some(expected) if received > expected => {
+                    return Err(IntegrityError::TooLarge {
+                        package: self.package,
+                        expected,
+                        actual: received,
+                    }
+                    .into())
+                }
+                _ => {}
+            }
+
+            writer.write_all(&chunk[..read])?;
+            self.update(&chunk[..read]);
+            progress(self.received, self.expected_size);
+        }
+
+        writer.flush()?;
+
+        let received = self.received;
+        self.finish()?;
+        Ok(received)
+    }
+}
+
+/// Checks `bytes` against the hash and size `package` declares.
+pub fn verify(package: &Package, bytes: &[u8]) -> Result<(), Error> {
+    let mut verifier = Verifier::new(package)?;
+    verifier.update(bytes);
+    verifier.finish().map_err(Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use package::PackageProperties;
+
+    // The SHA512 of `hello`
+    const HELLO_SHA512: &str =
+        "m3HSJL1i83hdltRq0+o9czGb+8KJDKra4t/3JRlnPKcjI8PZm6XBHXx6zG4UuMXaDEZjR1wuXDre9G9zvN7AQw==";
+
+    fn package(hash: &str, algorithm: &str, size: u64) -> Package {
+        Package::for_test(
+            "Hello",
+            "1.0.0",
+            PackageProperties {
+                package_hash: Some(String::from(hash)),
+                package_hash_algorithm: Some(String::from(algorithm)),
+                package_size: size,
+                ..PackageProperties::default()
+            },
+        )
+    }
+
+    fn integrity_error(result: Result<(), Error>) -> IntegrityError {
+        result.unwrap_err().downcast::<IntegrityError>().unwrap()
+    }
+
+    #[test]
+    fn matching_hash() {
+        verify(&package(HELLO_SHA512, "SHA512", 5), b"hello").unwrap();
+        verify(&package(HELLO_SHA512, "", 0), b"hello").unwrap();
+        verify(&package("", "", 0), b"anything").unwrap();
+
+        // Hashed in chunks, as when streaming
+        let package = package(HELLO_SHA512, "sha512", 5);
+        let mut verifier = Verifier::new(&package).unwrap();
+        verifier.update(b"he");
+        verifier.update(b"llo");
+        verifier.finish().unwrap();
+    }
+
+    #[test]
+    fn mismatched_hash() {
+        match integrity_error(verify(&package(HELLO_SHA512, "SHA512", 0), b"hellO")) {
+            IntegrityError::HashMismatch {
+                algorithm,
+                expected,
+                ..
+            } => {
+                assert_eq!(algorithm, "SHA512");
+                assert_eq!(expected, HELLO_SHA512);
+            }
+            other => panic!("unexpected error {}", other),
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(
+            integrity_error(verify(&package(HELLO_SHA512, "SHA512", 10), b"hello")),
+            IntegrityError::Truncated {
+                package: String::from("Hello 1.0.0"),
+                expected: 10,
+                actual: 5,
+            }
+        );
+    }
+
+    #[test]
+    fn too_large() {
+        assert_eq!(
+            integrity_error(verify(&package(HELLO_SHA512, "SHA512", 5), b"hello!")),
+            IntegrityError::TooLarge {
+                package: String::from("Hello 1.0.0"),
+                expected: 5,
+                actual: 6,
+            }
+        );
+    }
+
+    #[test]
+    fn served_size() {
+        let mut verifier = Verifier::new(&package(HELLO_SHA512, "SHA512", 5)).unwrap();
+        verifier.expect_size(5).unwrap();
+        assert_eq!(
+            verifier.expect_size(10),
+            Err(IntegrityError::SizeMismatch {
+                package: String::from("Hello 1.0.0"),
+                expected: 5,
+                actual: 10,
+            })
+        );
+        assert_eq!(verifier.expected_size(), Some(5));
+
+        // Without a declared size, the served one is expected
+        let mut verifier = Verifier::new(&package(HELLO_SHA512, "SHA512", 0)).unwrap();
+        verifier.expect_size(10).unwrap();
+        assert_eq!(verifier.expected_size(), Some(10));
+    }
+
+    #[test]
+    fn unsupported_algorithm() {
+        assert!(Verifier::new(&package(HELLO_SHA512, "MD5", 0)).is_err());
+    }
+
+    #[test]
+    fn copy() {
+        let package = package(HELLO_SHA512, "SHA512", 5);
+        let mut progress = vec![];
+
+        // Resuming after the first two bytes
+        let mut verifier = Verifier::new(&package).unwrap();
+        verifier.update_from(&b"he"[..]).unwrap();
+        let mut written = vec![];
+        let size = verifier
+            .copy(&b"llo"[..], &mut written, |received, total| {
+                progress.push((received, total))
+            })
+            .unwrap();
+
+        assert_eq!(size, 5);
+        assert_eq!(written, b"llo");
+        assert_eq!(progress, vec![(5, Some(5))]);
+
+        let verifier = Verifier::new(&package).unwrap();
+        assert!(verifier.copy(&b"hellO"[..], vec![], |_, _| {}).is_err());
+
+        let verifier = Verifier::new(&package).unwrap();
+        let mut written = vec![];
+        let err = verifier
+            .copy(&b"hello, world"[..], &mut written, |_, _| {})
+            .unwrap_err();
+        assert!(written.is_empty());
+        match err.downcast::<IntegrityError>().unwrap() {
+            IntegrityError::TooLarge { actual, .. } => assert_eq!(actual, 12),
+            other => panic!("unexpected error {}", other),
+        }
+    }
+}
@@ -1,3 +1,6 @@
+extern crate base64;
+extern crate chrono;
+extern crate crossbeam_utils;
 extern crate hyper;
 #[macro_use]
 extern crate log;
@@ -5,12 +8,24 @@ extern crate reqwest;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
+extern crate sha2;
 #[macro_use]
 extern crate failure;
+extern crate openssl;
 extern crate serde_xml_rs;
 extern crate tempdir;
 extern crate url;
+extern crate zip;
 
 pub mod client;
+pub mod config;
 pub mod feed;
+pub mod framework;
+pub mod integrity;
+pub mod mirror;
+pub mod nupkg;
 pub mod package;
+pub mod resolver;
+pub mod v3;
+pub mod version;
@@ -0,0 +1,753 @@
+extern crate chrono;
+#[macro_use]
+extern crate failure;
+#[macro_use]
+extern crate log;
+extern crate nougat;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate stderrlog;
+extern crate structopt;
+extern crate url;
+
+use chrono::{DateTime, Utc};
+use failure::Error;
+use nougat::client::Client;
+use nougat::config::Settings;
+use nougat::framework::NuGetFramework;
+use nougat::mirror::Mirror;
+use nougat::nupkg::Nupkg;
+use nougat::package::Package;
+use std::env;
+use std::fs::File;
+use std::path::PathBuf;
+use std::process;
+use std::str::FromStr;
+use structopt::StructOpt;
+use url::Url;
+
+#[structopt(name = "nougat", about = "A client for NuGet feeds")]
+#[derive(Debug, StructOpt)]
+struct Options {
+    /// The feed to talk to, either a V2 OData URL, a V3 index.json or the name of a source in NuGet.Config
+    #[structopt(
+        short = "s",
+        long = "source",
+        env = "NOUGAT_SOURCE",
+        default_value = "https://api.nuget.org/v3/index.json"
+    )]
+    source: String,
+    /// The API key used to push and delete packages, instead of any in NuGet.Config
+    #[structopt(
+        short = "k",
+        long = "api-key",
+        env = "NOUGAT_API_KEY",
+        hide_env_values = true
+    )]
+    api_key: Option<String>,
+    /// Logs more, repeat for even more
+    #[structopt(
+        short = "v",
+        long = "verbose",
+        parse(from_occurrences),
+        raw(global = "true")
+    )]
+    verbose: usize,
+    /// Logs nothing, not even errors
+    #[structopt(short = "q", long = "quiet", raw(global = "true"))]
+    quiet: bool,
+    /// How to print results
+    #[structopt(
+        short = "f",
+        long = "format",
+        default_value = "table",
+        raw(possible_values = r#"&["json", "table"]"#, global = "true")
+    )]
+    format: Format,
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Lists the packages in a V2 feed
+    #[structopt(name = "list")]
+    List {
+        /// An OData filter, only supported by V2 feeds
+        #[structopt(long = "filter")]
+        filter: Option<String>,
+    },
+    /// Lists the versions of a package
+    #[structopt(name = "versions")]
+    Versions { id: String },
+    /// Shows the details of a package, the latest version unless one is given
+    #[structopt(name = "info")]
+    Info { id: String, version: Option<String> },
+    /// Searches the feed
+    #[structopt(name = "search")]
+    Search {
+        term: String,
+        /// Only finds packages compatible with this framework, e.g. net45, which V3 feeds don't support
+        #[structopt(long = "framework", default_value = "any")]
+        framework: NuGetFramework,
+        /// Includes prerelease versions
+        #[structopt(long = "prerelease")]
+        prerelease: bool,
+    },
+    /// Downloads and verifies a package, the latest version unless one is given
+    #[structopt(name = "download")]
+    Download {
+        id: String,
+        version: Option<String>,
+        /// Where to write the package, `<id>.<version>.nupkg` by default
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Pushes a .nupkg to the feed
+    #[structopt(name = "push")]
+    Push {
+        #[structopt(parse(from_os_str))]
+        package: PathBuf,
+    },
+    /// Deletes, or on most feeds unlists, a package version
+    #[structopt(name = "delete")]
+    Delete { id: String, version: String },
+    /// Copies the packages the source has and another feed is missing
+    #[structopt(name = "mirror")]
+    Mirror {
+        /// The feed to copy packages to, using the API key, like the source
+        #[structopt(long = "destination", env = "NOUGAT_DESTINATION")]
+        destination: String,
+        /// Only copies packages whose id matches this pattern, e.g. `Newtonsoft.*`.
+        /// V3 sources can't list every package, so need an exact id
+        #[structopt(long = "id")]
+        id: Option<String>,
+        /// Skips prerelease versions
+        #[structopt(long = "no-prerelease")]
+        no_prerelease: bool,
+        /// Also copies unlisted packages, which will be listed on the destination
+        #[structopt(long = "include-unlisted")]
+        include_unlisted: bool,
+        /// Only copies the latest versions of each package
+        #[structopt(long = "latest")]
+        latest: Option<usize>,
+        /// Only copies packages published since this date, e.g. 2018-06-01T00:00:00Z
+        #[structopt(long = "since")]
+        since: Option<DateTime<Utc>>,
+        /// How many packages to copy at once
+        #[structopt(short = "j", long = "parallelism", default_value = "4")]
+        parallelism: usize,
+        /// Lists the packages that would be copied without copying them
+        #[structopt(long = "dry-run")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    Table,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "table" => Ok(Format::Table),
+            _ => bail!("{} is not a format, expected json or table", s),
+        }
+    }
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Serialize)]
+struct PackageRow {
+    id: String,
+    version: String,
+    title: String,
+    authors: Vec<String>,
+    description: String,
+    download_count: i32,
+    listed: bool,
+}
+
+impl<'a> From<&'a Package> for PackageRow {
+    fn from(package: &'a Package) -> PackageRow {
+        let properties = &package.properties;
+
+        PackageRow {
+            id: String::from(package.id()),
+            version: version_string(package),
+            title: String::from(properties.title()),
+            authors: strings(properties.authors()),
+            description: String::from(properties.description()),
+            download_count: properties.download_count(),
+            listed: package.is_listed(),
+        }
+    }
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Serialize)]
+struct PackageInfo {
+    id: String,
+    version: String,
+    title: String,
+    summary: Option<String>,
+    description: String,
+    authors: Vec<String>,
+    owners: Vec<String>,
+    tags: Vec<String>,
+    project_url: Option<String>,
+    license_url: Option<String>,
+    published: Option<String>,
+    listed: bool,
+    download_count: i32,
+    version_download_count: i32,
+    package_size: u64,
+    package_hash: Option<String>,
+    package_hash_algorithm: Option<String>,
+    dependencies: Vec<DependencyRow>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Serialize)]
+struct DependencyRow {
+    framework: String,
+    id: String,
+    range: String,
+}
+
+impl<'a> From<&'a Package> for PackageInfo {
+    fn from(package: &'a Package) -> PackageInfo {
+        let properties = &package.properties;
+
+        let dependencies = properties
+            .dependencies()
+            .iter()
+            .flat_map(|groups| groups.iter())
+            .flat_map(|group| group.dependencies.iter())
+            .map(|dependency| DependencyRow {
+                framework: dependency.framework.to_short_name(),
+                id: dependency.package_id.clone(),
+                range: dependency.version.to_normalized_string(),
+            }).collect();
+
+        PackageInfo {
+            id: String::from(package.id()),
+            version: version_string(package),
+            title: String::from(properties.title()),
+            summary: properties.summary().map(String::from),
+            description: String::from(properties.description()),
+            authors: strings(properties.authors()),
+            owners: strings(properties.owners()),
+            tags: strings(properties.tags()),
+            project_url: properties.project_url().url().map(Url::to_string),
+            license_url: properties.license_url().url().map(Url::to_string),
+            published: properties
+                .published_at()
+                .and_then(|published| published.date())
+                .map(|date| date.to_rfc3339()),
+            listed: package.is_listed(),
+            download_count: properties.download_count(),
+            version_download_count: properties.version_download_count(),
+            package_size: properties.package_size(),
+            package_hash: properties.package_hash().map(String::from),
+            package_hash_algorithm: properties.package_hash_algorithm().map(String::from),
+            dependencies,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Outcome {
+    action: &'static str,
+    id: String,
+    version: Option<String>,
+    path: Option<PathBuf>,
+    status: Option<u16>,
+}
+
+// Versions that don't parse are shown as the feed sent them
+fn version_string(package: &Package) -> String {
+    match package.version() {
+        Some(version) => version.to_normalized_string(),
+        None => String::from(package.raw_version()),
+    }
+}
+
+fn strings(values: Vec<&str>) -> Vec<String> {
+    values.into_iter().map(String::from).collect()
+}
+
+/// Lays out `rows` in left aligned columns under `headers`.
+fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect();
+
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let headers: Vec<String> = headers.iter().map(|header| header.to_uppercase()).collect();
+
+    let mut output = String::new();
+    for row in Some(&headers).into_iter().chain(rows) {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:1$}", cell, width))
+            .collect::<Vec<_>>()
+            .join("  ");
+
+        #[allow(deprecated)]
+        output.push_str(line.trim_right());
+        output.push('\n');
+    }
+
+    output
+}
+
+fn print_packages(format: Format, packages: &[Package]) -> Result<(), Error> {
+    let packages: Vec<PackageRow> = packages.iter().map(PackageRow::from).collect();
+
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&packages)?),
+        Format::Table => {
+            let rows: Vec<Vec<String>> = packages
+                .into_iter()
+                .map(|package| {
+                    vec![
+                        package.id,
+                        package.version,
+                        package.download_count.to_string(),
+                        package.authors.join(", "),
+                    ]
+                }).collect();
+
+            print!(
+                "{}",
+                table(&["id", "version", "downloads", "authors"], &rows)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn print_info(format: Format, info: &PackageInfo) -> Result<(), Error> {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(info)?),
+        Format::Table => {
+            let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+
+            let mut rows = vec![
+                vec![String::from("id"), info.id.clone()],
+                vec![String::from("version"), info.version.clone()],
+                vec![String::from("title"), info.title.clone()],
+                vec![String::from("summary"), optional(&info.summary)],
+                vec![String::from("authors"), info.authors.join(", ")],
+                vec![String::from("owners"), info.owners.join(", ")],
+                vec![String::from("tags"), info.tags.join(" ")],
+                vec![String::from("project url"), optional(&info.project_url)],
+                vec![String::from("license url"), optional(&info.license_url)],
+                vec![String::from("published"), optional(&info.published)],
+                vec![String::from("listed"), info.listed.to_string()],
+                vec![String::from("downloads"), info.download_count.to_string()],
+                vec![String::from("size"), info.package_size.to_string()],
+            ];
+
+            for dependency in &info.dependencies {
+                rows.push(vec![
+                    format!("dependency ({})", dependency.framework),
+                    format!("{} {}", dependency.id, dependency.range),
+                ]);
+            }
+
+            print!("{}", table(&["property", "value"], &rows));
+            println!("\n{}", info.description);
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct MirrorRow {
+    id: String,
+    version: String,
+    status: &'static str,
+    error: Option<String>,
+}
+
+impl MirrorRow {
+    fn new(package: &Package, status: &'static str, error: Option<&Error>) -> MirrorRow {
+        MirrorRow {
+            id: String::from(package.id()),
+            version: version_string(package),
+            status,
+            error: error.map(Error::to_string),
+        }
+    }
+}
+
+fn print_mirror(format: Format, rows: &[MirrorRow]) -> Result<(), Error> {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(rows)?),
+        Format::Table => {
+            let rows: Vec<Vec<String>> = rows
+                .iter()
+                .map(|row| {
+                    vec![
+                        row.id.clone(),
+                        row.version.clone(),
+                        String::from(row.status),
+                        row.error.clone().unwrap_or_default(),
+                    ]
+                }).collect();
+
+            print!("{}", table(&["id", "version", "status", "error"], &rows));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_outcome(format: Format, outcome: &Outcome) -> Result<(), Error> {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(outcome)?),
+        Format::Table => {
+            let mut line = format!("{} {}", outcome.action, outcome.id);
+            if let Some(ref version) = outcome.version {
+                line.push_str(&format!(" {}", version));
+            }
+            if let Some(ref path) = outcome.path {
+                line.push_str(&format!(" to {}", path.display()));
+            }
+            println!("{}", line);
+        }
+    }
+
+    Ok(())
+}
+
+/// The given version of a package, or its latest listed version.
+fn find_package(client: &Client, id: &str, version: Option<&str>) -> Result<Package, Error> {
+    if let Some(version) = version {
+        return client.package(id, version);
+    }
+
+    let latest = client
+        .package_versions(id)?
+        .into_iter()
+        .filter(Package::is_listed)
+        .max_by(|a, b| a.version().cmp(&b.version()));
+
+    match latest {
+        Some(package) => Ok(package),
+        None => bail!("{} has no listed versions", id),
+    }
+}
+
+fn require_api_key(client: &Client) -> Result<(), Error> {
+    if client.api_key().is_none() {
+        bail!(
+            "An API key is required, pass --api-key, set NOUGAT_API_KEY or add it to NuGet.Config"
+        );
+    }
+
+    Ok(())
+}
+
+/// A client for `source`, which is either a URL or the name of a source in NuGet.Config.
+fn connect(source: &str, api_key: Option<&String>) -> Result<Client, Error> {
+    if let Ok(url) = Url::parse(source) {
+        return Client::connect(url, api_key.cloned(), None);
+    }
+
+    let settings = Settings::load(env::current_dir()?)?;
+
+    match api_key {
+        None => settings.client(source),
+        Some(api_key) => {
+            let package_source = settings
+                .source(source)
+                .ok_or_else(|| format_err!("There is no package source named {}", source))?;
+            let url = Url::parse(&package_source.url)?;
+
+            Client::connect(
+                url,
+                Some(api_key.clone()),
+                settings.credentials(&package_source.name),
+            )
+        }
+    }
+}
+
+fn run(options: Options) -> Result<(), Error> {
+    let client = connect(&options.source, options.api_key.as_ref())?;
+    let format = options.format;
+
+    match options.command {
+        Command::List { ref filter } => {
+            if client.service_index().is_some() {
+                bail!(
+                    "{} is a V3 feed, which can't list every package, try search instead",
+                    client.base_url
+                );
+            }
+
+            let packages = client
+                .packages_iter(filter.as_ref().map(String::as_str))
+                .collect::<Result<Vec<_>, _>>()?;
+            print_packages(format, &packages)
+        }
+        Command::Versions { ref id } => {
+            let mut packages = client.package_versions(id)?;
+            packages.sort_by(|a, b| a.version().cmp(&b.version()));
+            print_packages(format, &packages)
+        }
+        Command::Info {
+            ref id,
+            ref version,
+        } => {
+            let package = find_package(&client, id, version.as_ref().map(String::as_str))?;
+            print_info(format, &PackageInfo::from(&package))
+        }
+        Command::Search {
+            ref term,
+            ref framework,
+            prerelease,
+        } => {
+            let packages = client.search_for_framework(term, framework, prerelease)?;
+            print_packages(format, &packages)
+        }
+        Command::Download {
+            ref id,
+            ref version,
+            ref output,
+        } => {
+            let package = find_package(&client, id, version.as_ref().map(String::as_str))?;
+            let version = version_string(&package);
+            let path = output
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(format!("{}.{}.nupkg", package.id(), version)));
+
+            let mut reported = 0;
+            let size = package.download_to_path(&client, &path, |received, total| {
+                if let Some(total) = total.filter(|&total| total > 0) {
+                    let percent = received * 100 / total;
+                    if percent >= reported + 10 || received == total {
+                        reported = percent;
+                        info!("Downloaded {} of {} bytes", received, total);
+                    }
+                }
+            })?;
+            debug!("Wrote {} bytes to {}", size, path.display());
+
+            print_outcome(
+                format,
+                &Outcome {
+                    action: "Downloaded",
+                    id: String::from(package.id()),
+                    version: Some(version),
+                    path: Some(path),
+                    status: None,
+                },
+            )
+        }
+        Command::Push { ref package } => {
+            require_api_key(&client)?;
+
+            let nupkg = Nupkg::open(package)?;
+            let metadata = &nupkg.nuspec().metadata;
+
+            let file = File::open(package)?;
+            let length = file.metadata()?.len();
+            let response = client.push_package_from(file, length)?;
+
+            print_outcome(
+                format,
+                &Outcome {
+                    action: "Pushed",
+                    id: metadata.id.clone(),
+                    version: metadata
+                        .version
+                        .as_ref()
+                        .map(|version| version.to_normalized_string()),
+                    path: Some(package.clone()),
+                    status: Some(response.status().as_u16()),
+                },
+            )
+        }
+        Command::Delete {
+            ref id,
+            ref version,
+        } => {
+            require_api_key(&client)?;
+
+            let response = client.delete_package(id, version)?;
+
+            print_outcome(
+                format,
+                &Outcome {
+                    action: "Deleted",
+                    id: id.clone(),
+                    version: Some(version.clone()),
+                    path: None,
+                    status: Some(response.status().as_u16()),
+                },
+            )
+        }
+        Command::Mirror {
+            ref destination,
+            ref id,
+            no_prerelease,
+            include_unlisted,
+            latest,
+            since,
+            parallelism,
+            dry_run,
+        } => {
+            let source = client;
+            let destination = connect(destination, options.api_key.as_ref())?;
+
+            if !dry_run {
+                require_api_key(&destination)?;
+            }
+
+            let mut mirror = Mirror::new(&source, &destination)
+                .prerelease(!no_prerelease)
+                .listed_only(!include_unlisted)
+                .parallelism(parallelism);
+            if let Some(ref id) = *id {
+                mirror = mirror.id(id);
+            }
+            if let Some(latest) = latest {
+                mirror = mirror.latest(latest);
+            }
+            if let Some(since) = since {
+                mirror = mirror.since(since);
+            }
+
+            let plan = mirror.plan()?;
+
+            if dry_run {
+                let rows: Vec<MirrorRow> = plan
+                    .missing
+                    .iter()
+                    .map(|package| MirrorRow::new(package, "missing", None))
+                    .chain(
+                        plan.present
+                            .iter()
+                            .map(|package| MirrorRow::new(package, "present", None)),
+                    ).collect();
+                return print_mirror(format, &rows);
+            }
+
+            let summary = mirror.execute(plan);
+
+            let rows: Vec<MirrorRow> = summary
+                .copied
+                .iter()
+                .map(|package| MirrorRow::new(package, "copied", None))
+                .chain(
+                    summary
+                        .skipped
+                        .iter()
+                        .map(|package| MirrorRow::new(package, "skipped", None)),
+                ).chain(
+                    summary
+                        .failed
+                        .iter()
+                        .map(|failure| MirrorRow::new(&failure.0, "failed", Some(&failure.1))),
+                ).collect();
+            print_mirror(format, &rows)?;
+
+            if !summary.is_success() {
+                bail!("{} packages failed to mirror", summary.failed.len());
+            }
+
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let options = Options::from_args();
+
+    stderrlog::new()
+        .module(module_path!())
+        .quiet(options.quiet)
+        .verbosity(options.verbose + 1)
+        .init()
+        .unwrap();
+
+    if let Err(e) = run(options) {
+        error!("{}", e);
+        for cause in e.iter_causes() {
+            error!("Caused by: {}", cause);
+        }
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        assert_eq!("json".parse::<Format>().unwrap(), Format::Json);
+        assert_eq!("table".parse::<Format>().unwrap(), Format::Table);
+        assert!("yaml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn tables() {
+        let rows = vec![
+            vec![String::from("Newtonsoft.Json"), String::from("12.0.1")],
+            vec![String::from("xunit"), String::from("2.4.1-pre.build.4059")],
+        ];
+
+        assert_eq!(
+            table(&["id", "version"], &rows),
+            "ID               VERSION\n\
+             Newtonsoft.Json  12.0.1\n\
+             xunit            2.4.1-pre.build.4059\n"
+        );
+    }
+
+    #[test]
+    fn arguments() {
+        let options = Options::from_iter(vec![
+            "nougat",
+            "--source",
+            "https://www.nuget.org/api/v2",
+            "info",
+            "xunit",
+            "-f",
+            "json",
+            "-vv",
+        ]);
+
+        assert_eq!(options.source, "https://www.nuget.org/api/v2");
+        assert_eq!(options.format, Format::Json);
+        assert_eq!(options.verbose, 2);
+
+        match options.command {
+            Command::Info { id, version } => {
+                assert_eq!(id, "xunit");
+                assert_eq!(version, None);
+            }
+            command => panic!("Unexpected command {:?}", command),
+        }
+    }
+}
@@ -0,0 +1,523 @@
+use chrono::{DateTime, Utc};
+use client::Client;
+use crossbeam_utils::thread;
+use failure::Error;
+use feed::package_key;
+use package::Package;
+use reqwest::{self, StatusCode};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::sync::Mutex;
+
+/// Copies the packages a source feed has and a destination feed is missing.
+///
+/// Packages are downloaded with `Package::content`, so they're verified
+/// against their declared hash before being pushed to the destination.
+pub struct Mirror<'a> {
+    source: &'a Client,
+    destination: &'a Client,
+    filter: MirrorFilter,
+    parallelism: usize,
+}
+
+impl<'a> Mirror<'a> {
+    pub fn new(source: &'a Client, destination: &'a Client) -> Mirror<'a> {
+        Mirror {
+            source,
+            destination,
+            filter: MirrorFilter::new(),
+            parallelism: 4,
+        }
+    }
+
+    /// Only mirrors packages whose id matches `pattern`, see `MirrorFilter::id`.
+    pub fn id(mut self, pattern: &str) -> Mirror<'a> {
+        self.filter = self.filter.id(pattern);
+        self
+    }
+
+    pub fn prerelease(mut self, include_prerelease: bool) -> Mirror<'a> {
+        self.filter = self.filter.prerelease(include_prerelease);
+        self
+    }
+
+    pub fn latest(mut self, count: usize) -> Mirror<'a> {
+        self.filter = self.filter.latest(count);
+        self
+    }
+
+    pub fn since(mut self, date: DateTime<Utc>) -> Mirror<'a> {
+        self.filter = self.filter.since(date);
+        self
+    }
+
+    /// Whether only listed packages are mirrored, see `MirrorFilter::listed_only`.
+    pub fn listed_only(mut self, listed_only: bool) -> Mirror<'a> {
+        self.filter = self.filter.listed_only(listed_only);
+        self
+    }
+
+    /// How many packages are copied at once, at least one.
+    pub fn parallelism(mut self, parallelism: usize) -> Mirror<'a> {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    /// Works out which packages would be copied, without copying anything.
+    ///
+    /// V3 sources can't list every package, so mirroring one needs an exact `id`.
+    pub fn plan(&self) -> Result<MirrorPlan, Error> {
+        if self.source.service_index().is_some() && self.filter.exact_id().is_none() {
+            bail!(
+                "{} is a V3 feed, which can't list every package, so mirror a single id from it",
+                self.source.base_url
+            );
+        }
+
+        let candidates = self.filter.select(self.source_packages()?);
+
+        let mut ids: Vec<String> = candidates
+            .iter()
+            .map(|package| package.id().to_lowercase())
+            .collect();
+        ids.sort();
+        ids.dedup();
+
+        let existing = self.destination_packages(&ids)?;
+
+        Ok(MirrorPlan::new(candidates, &existing))
+    }
+
+    /// Copies the missing packages, continuing past packages that fail.
+    pub fn run(&self) -> Result<MirrorSummary, Error> {
+        let plan = self.plan()?;
+        Ok(self.execute(plan))
+    }
+
+    /// Copies the missing packages of a plan made by `plan`.
+    pub fn execute(&self, plan: MirrorPlan) -> MirrorSummary {
+        let (source, destination) = (self.source, self.destination);
+
+        let mut summary = transfer(plan.missing, self.parallelism, |package| {
+            let content = package.content(source)?;
+            let length = content.len() as u64;
+            destination.push_package_from(Cursor::new(content), length)?;
+            Ok(())
+        });
+
+        summary.skipped = plan.present;
+        summary
+    }
+
+    fn source_packages(&self) -> Result<Vec<Package>, Error> {
+        match self.filter.exact_id() {
+            Some(id) => self.source.package_versions(id),
+            None => self.source.packages(None),
+        }
+    }
+
+    // Listing a whole V2 feed is cheaper than asking for each id, but V3
+    // feeds can only be asked for one id at a time.
+    fn destination_packages(&self, ids: &[String]) -> Result<Vec<Package>, Error> {
+        if self.destination.service_index().is_none() && ids.len() > 1 {
+            return self.destination.packages(None);
+        }
+
+        let mut packages = Vec::new();
+
+        for id in ids {
+            match self.destination.package_versions(id) {
+                Ok(mut versions) => packages.append(&mut versions),
+                Err(ref e) if is_not_found(e) => debug!("{} is not in the destination", id),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(packages)
+    }
+}
+
+fn is_not_found(error: &Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        == Some(StatusCode::NOT_FOUND)
+}
+
+/// Which of a source's packages to mirror.
+#[derive(Debug, Clone, Default)]
+pub struct MirrorFilter {
+    id: Option<String>,
+    exclude_prerelease: bool,
+    include_unlisted: bool,
+    latest: Option<usize>,
+    since: Option<DateTime<Utc>>,
+}
+
+impl MirrorFilter {
+    pub fn new() -> MirrorFilter {
+        MirrorFilter::default()
+    }
+
+    /// Only selects packages whose id matches `pattern`, ignoring case.
+    ///
+    /// `*` matches any run of characters and `?` matches a single one.
+    pub fn id(mut self, pattern: &str) -> MirrorFilter {
+        self.id = Some(String::from(pattern));
+        self
+    }
+
+    /// Whether prerelease versions are selected, which they are by default.
+    pub fn prerelease(mut self, include_prerelease: bool) -> MirrorFilter {
+        self.exclude_prerelease = !include_prerelease;
+        self
+    }
+
+    /// Whether only listed packages are selected, which is the default.
+    ///
+    /// Feeds list every package pushed to them, so unlisted packages show up
+    /// listed on the destination.
+    pub fn listed_only(mut self, listed_only: bool) -> MirrorFilter {
+        self.include_unlisted = !listed_only;
+        self
+    }
+
+    /// Only selects the highest `count` versions of each package.
+    pub fn latest(mut self, count: usize) -> MirrorFilter {
+        self.latest = Some(count);
+        self
+    }
+
+    /// Only selects packages published at or after `date`.
+    ///
+    /// Packages without a publish date, such as unlisted ones, are compared by when they were created.
+    pub fn since(mut self, date: DateTime<Utc>) -> MirrorFilter {
+        self.since = Some(date);
+        self
+    }
+
+    /// The id to mirror, if the pattern matches a single id.
+    pub fn exact_id(&self) -> Option<&str> {
+        self.id
+            .as_ref()
+            .map(String::as_str)
+            .filter(|id| !id.contains(&['*', '?'][..]))
+    }
+
+    pub fn matches(&self, package: &Package) -> bool {
+        if let Some(ref pattern) = self.id {
+            if !glob_matches(pattern, package.id()) {
+                return false;
+            }
+        }
+
+        let is_prerelease = match package.version() {
+            Some(version) => version.is_prerelease(),
+            None => package.properties.is_prerelease(),
+        };
+        if self.exclude_prerelease && is_prerelease {
+            return false;
+        }
+
+        if !self.include_unlisted && !package.is_listed() {
+            return false;
+        }
+
+        if let Some(since) = self.since {
+            let properties = &package.properties;
+            let date = properties
+                .published_at()
+                .and_then(|published| published.date())
+                .or_else(|| properties.created_at());
+
+            // Without any date there's no telling, so keep it
+            if let Some(date) = date {
+                if date < since {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// The packages that match, sorted by id and then version.
+    pub fn select(&self, packages: Vec<Package>) -> Vec<Package> {
+        let mut selected: Vec<Package> = packages
+            .into_iter()
+            .filter(|package| self.matches(package))
+            .collect();
+
+        selected.sort_by(by_id_and_version);
+
+        if let Some(count) = self.latest {
+            let mut totals: HashMap<String, usize> = HashMap::new();
+            for package in &selected {
+                *totals.entry(package.id().to_lowercase()).or_insert(0) += 1;
+            }
+
+            // Versions are ascending, so drop all but the last `count` of each id
+            let mut seen: HashMap<String, usize> = HashMap::new();
+            selected.retain(|package| {
+                let id = package.id().to_lowercase();
+                let index = seen.entry(id.clone()).or_insert(0);
+                *index += 1;
+                *index + count > totals[&id]
+            });
+        }
+
+        selected
+    }
+}
+
+fn by_id_and_version(a: &Package, b: &Package) -> Ordering {
+    a.id()
+        .to_lowercase()
+        .cmp(&b.id().to_lowercase())
+        .then_with(|| a.version().cmp(&b.version()))
+}
+
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // The last `*` seen, and the text position it's currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(&'*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    t = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The packages a mirror would copy.
+#[derive(Debug, Default)]
+pub struct MirrorPlan {
+    /// The selected packages the destination is missing.
+    pub missing: Vec<Package>,
+    /// The selected packages the destination already has.
+    pub present: Vec<Package>,
+}
+
+impl MirrorPlan {
+    fn new(candidates: Vec<Package>, existing: &[Package]) -> MirrorPlan {
+        let existing: HashSet<_> = existing.iter().map(package_key).collect();
+
+        let (present, missing) = candidates
+            .into_iter()
+            .partition(|package| existing.contains(&package_key(package)));
+
+        MirrorPlan { missing, present }
+    }
+}
+
+/// What a mirror did with each of the selected packages.
+#[derive(Debug, Default)]
+pub struct MirrorSummary {
+    pub copied: Vec<Package>,
+    /// Packages the destination already had.
+    pub skipped: Vec<Package>,
+    pub failed: Vec<(Package, Error)>,
+}
+
+impl MirrorSummary {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+// Runs `copy` over the packages on `parallelism` threads.
+fn transfer<F>(packages: Vec<Package>, parallelism: usize, copy: F) -> MirrorSummary
+where
+    F: Fn(&Package) -> Result<(), Error> + Sync,
+{
+    let queue = Mutex::new(packages.into_iter());
+    let summary = Mutex::new(MirrorSummary::default());
+
+    thread::scope(|scope| {
+        for _ in 0..parallelism.max(1) {
+            scope.spawn(|_| loop {
+                let package = match queue.lock().unwrap().next() {
+                    Some(package) => package,
+                    None => break,
+                };
+
+                let result = copy(&package);
+                let mut summary = summary.lock().unwrap();
+
+                match result {
+                    Ok(()) => {
+                        info!("Copied {}", package);
+                        summary.copied.push(package);
+                    }
+                    Err(e) => {
+                        warn!("Failed to copy {}: {}", package, e);
+                        summary.failed.push((package, e));
+                    }
+                }
+            });
+        }
+    }).expect("a mirror thread panicked");
+
+    let mut summary = summary.into_inner().unwrap();
+
+    // Threads finish in any order
+    summary.copied.sort_by(by_id_and_version);
+    summary.failed.sort_by(|a, b| by_id_and_version(&a.0, &b.0));
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use package::{PackageProperties, Published};
+
+    fn package(id: &str, version: &str, published: &str) -> Package {
+        Package::for_test(
+            id,
+            version,
+            PackageProperties {
+                published_at: Some(Published::Listed(published.parse().unwrap())),
+                ..PackageProperties::default()
+            },
+        )
+    }
+
+    fn source() -> Vec<Package> {
+        vec![
+            package("Foo", "1.0.0", "2018-01-01T00:00:00Z"),
+            package("Foo", "2.0.0-beta", "2018-02-01T00:00:00Z"),
+            package("Foo", "1.1.0", "2018-03-01T00:00:00Z"),
+            package("Foo.Extensions", "1.0.0", "2018-04-01T00:00:00Z"),
+            package("Bar", "3.0.0", "2018-05-01T00:00:00Z"),
+        ]
+    }
+
+    fn names(packages: &[Package]) -> Vec<String> {
+        packages.iter().map(Package::to_string).collect()
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_matches("Foo", "foo"));
+        assert!(glob_matches("foo.*", "Foo.Extensions"));
+        assert!(glob_matches("*.Ext*s", "Foo.Extensions"));
+        assert!(glob_matches("F?o*", "Foo"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("foo.*", "Foo"));
+        assert!(!glob_matches("F?o", "Fooo"));
+        assert!(!glob_matches("*.Bar", "Foo.Barr"));
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(
+            names(&MirrorFilter::new().select(source())),
+            vec![
+                "Bar 3.0.0",
+                "Foo 1.0.0",
+                "Foo 1.1.0",
+                "Foo 2.0.0-beta",
+                "Foo.Extensions 1.0.0",
+            ]
+        );
+
+        assert_eq!(
+            names(&MirrorFilter::new().id("foo").select(source())),
+            vec!["Foo 1.0.0", "Foo 1.1.0", "Foo 2.0.0-beta"]
+        );
+
+        assert_eq!(
+            names(
+                &MirrorFilter::new()
+                    .prerelease(false)
+                    .latest(1)
+                    .select(source())
+            ),
+            vec!["Bar 3.0.0", "Foo 1.1.0", "Foo.Extensions 1.0.0"]
+        );
+
+        let since = "2018-03-01T00:00:00Z".parse().unwrap();
+        assert_eq!(
+            names(&MirrorFilter::new().id("Foo*").since(since).select(source())),
+            vec!["Foo 1.1.0", "Foo.Extensions 1.0.0"]
+        );
+
+        let mut unlisted = package("Baz", "1.0.0", "2018-06-01T00:00:00Z");
+        unlisted.properties.published_at = Some(Published::Unlisted);
+        assert!(!MirrorFilter::new().matches(&unlisted));
+        assert!(MirrorFilter::new().listed_only(false).matches(&unlisted));
+
+        assert_eq!(MirrorFilter::new().id("Foo").exact_id(), Some("Foo"));
+        assert_eq!(MirrorFilter::new().id("Foo.*").exact_id(), None);
+    }
+
+    #[test]
+    fn plans() {
+        let existing = vec![
+            package("foo", "1.0", "2018-01-01T00:00:00Z"),
+            package("Bar", "2.0.0", "2018-01-01T00:00:00Z"),
+        ];
+
+        let plan = MirrorPlan::new(MirrorFilter::new().select(source()), &existing);
+
+        assert_eq!(
+            names(&plan.missing),
+            vec![
+                "Bar 3.0.0",
+                "Foo 1.1.0",
+                "Foo 2.0.0-beta",
+                "Foo.Extensions 1.0.0",
+            ]
+        );
+        assert_eq!(names(&plan.present), vec!["Foo 1.0.0"]);
+    }
+
+    #[test]
+    fn transfers() {
+        let summary = transfer(source(), 3, |package| {
+            if package.version().unwrap().is_prerelease() {
+                bail!("{} was rejected", package);
+            }
+            Ok(())
+        });
+
+        assert!(!summary.is_success());
+        assert_eq!(
+            names(&summary.copied),
+            vec![
+                "Bar 3.0.0",
+                "Foo 1.0.0",
+                "Foo 1.1.0",
+                "Foo.Extensions 1.0.0",
+            ]
+        );
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].0.to_string(), "Foo 2.0.0-beta");
+        assert_eq!(
+            summary.failed[0].1.to_string(),
+            "Foo 2.0.0-beta was rejected"
+        );
+    }
+}
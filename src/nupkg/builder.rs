@@ -0,0 +1,416 @@
+use failure::Error;
+use nupkg::nuspec::{self, Nuspec};
+use nupkg::{escape_part_name, is_packaging_file};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Cursor, Write};
+use std::path::{Path, PathBuf};
+use version::NuGetVersion;
+use zip::write::{FileOptions, ZipWriter};
+use zip::{CompressionMethod, DateTime};
+
+const MAX_ID_LENGTH: usize = 100;
+
+/// Builds a package from a nuspec and files on disk, ready for `Client::push_package_from`.
+///
+/// ```no_run
+/// # use nougat::nupkg::builder::PackageBuilder;
+/// # use nougat::nupkg::nuspec::Nuspec;
+/// # let nuspec = Nuspec::default();
+/// let nupkg = PackageBuilder::new(nuspec)
+///     .file("bin/Release/net45/Fint.Sse.dll", "lib/net45/Fint.Sse.dll")
+///     .build()
+///     .unwrap();
+/// ```
+pub struct PackageBuilder {
+    nuspec: Nuspec,
+    // Source paths, and the paths they have in the package
+    files: Vec<(PathBuf, String)>,
+}
+
+impl PackageBuilder {
+    pub fn new(nuspec: Nuspec) -> PackageBuilder {
+        PackageBuilder {
+            nuspec,
+            files: Vec::new(),
+        }
+    }
+
+    /// Adds the file at `source` to the package as `target`, e.g. `lib/net45/Fint.Sse.dll`.
+    pub fn file<P: AsRef<Path>>(mut self, source: P, target: &str) -> PackageBuilder {
+        self.files
+            .push((source.as_ref().to_path_buf(), target.replace('\\', "/")));
+        self
+    }
+
+    pub fn files<I, P, S>(self, files: I) -> PackageBuilder
+    where
+        I: IntoIterator<Item = (P, S)>,
+        P: AsRef<Path>,
+        S: AsRef<str>,
+    {
+        files.into_iter().fold(self, |builder, (source, target)| {
+            builder.file(source, target.as_ref())
+        })
+    }
+
+    /// Checks the nuspec and file paths follow NuGet's rules.
+    pub fn validate(&self) -> Result<(), Error> {
+        let metadata = &self.nuspec.metadata;
+
+        validate_id(&metadata.id)?;
+
+        if metadata.version.is_none() {
+            bail!("{} has no version", metadata.id);
+        }
+        if metadata.authors().is_empty() {
+            bail!("{} has no authors", metadata.id);
+        }
+        if metadata.description.trim().is_empty() {
+            bail!("{} has no description", metadata.id);
+        }
+
+        for group in &metadata.dependency_groups {
+            for dependency in &group.dependencies {
+                validate_id(&dependency.id)?;
+
+                if dependency.id.eq_ignore_ascii_case(&metadata.id) {
+                    bail!("{} can't depend on itself", metadata.id);
+                }
+            }
+        }
+
+        let has_dependencies = metadata
+            .dependency_groups
+            .iter()
+            .any(|group| !group.dependencies.is_empty());
+        if self.files.is_empty() && !has_dependencies {
+            bail!("{} has no files or dependencies", metadata.id);
+        }
+
+        let mut targets = HashSet::new();
+        for (_, target) in &self.files {
+            if target.is_empty()
+                || target.starts_with('/')
+                || target.ends_with('/')
+                || target
+                    .split('/')
+                    .any(|segment| segment == ".." || segment == ".")
+            {
+                bail!("{} is not a valid path in a package", target);
+            }
+
+            if is_packaging_file(target)
+                || (!target.contains('/') && target.to_lowercase().ends_with(".nuspec"))
+            {
+                bail!("{} is reserved for the package's metadata", target);
+            }
+
+            if !targets.insert(target.to_lowercase()) {
+                bail!("{} is in the package more than once", target);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates and writes the package, returning its bytes.
+    pub fn build(&self) -> Result<Vec<u8>, Error> {
+        self.validate()?;
+
+        let metadata = &self.nuspec.metadata;
+        let nuspec = self.nuspec.to_xml();
+        let nuspec_name = format!("{}.nuspec", metadata.id);
+
+        // Derived from the nuspec, so building the same package twice gives the same bytes
+        let digest = Sha256::digest(nuspec.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        let core_properties_name = format!(
+            "package/services/metadata/core-properties/{}.psmdcp",
+            &digest[..32]
+        );
+
+        // Entries default to the current time, which would make every build differ
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .last_modified_time(DateTime::default());
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+        writer.start_file("_rels/.rels", options)?;
+        writer.write_all(relationships(&nuspec_name, &core_properties_name, &digest).as_bytes())?;
+
+        writer.start_file(escape_part_name(&nuspec_name), options)?;
+        writer.write_all(nuspec.as_bytes())?;
+
+        for (source, target) in &self.files {
+            let mut file = File::open(source)
+                .map_err(|e| format_err!("Unable to open {}: {}", source.display(), e))?;
+
+            writer.start_file(escape_part_name(target), options)?;
+            io::copy(&mut file, &mut writer)?;
+        }
+
+        writer.start_file(core_properties_name, options)?;
+        writer.write_all(core_properties(&self.nuspec).as_bytes())?;
+
+        writer.start_file("[Content_Types].xml", options)?;
+        writer
+            .write_all(content_types(self.files.iter().map(|(_, target)| &**target)).as_bytes())?;
+
+        Ok(writer.finish()?.into_inner())
+    }
+}
+
+// Ids are made of letters, digits and underscores, separated by single `.` or `-`
+fn validate_id(id: &str) -> Result<(), Error> {
+    if id.len() > MAX_ID_LENGTH {
+        bail!("{} is longer than {} characters", id, MAX_ID_LENGTH);
+    }
+
+    // `_` counts as a letter, so unlike `.` and `-` it can lead, trail or repeat
+    let valid = id
+        .split(&['.', '-'][..])
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '_'));
+
+    if !valid {
+        bail!("{} is not a valid package id", id);
+    }
+
+    Ok(())
+}
+
+fn relationships(nuspec_name: &str, core_properties_name: &str, digest: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Type="http://schemas.microsoft.com/packaging/2010/07/manifest" Target="/{}" Id="R{}" />
+  <Relationship Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" Target="/{}" Id="R{}" />
+</Relationships>
+"#,
+        nuspec::escape(&escape_part_name(nuspec_name)),
+        &digest[32..48],
+        nuspec::escape(core_properties_name),
+        &digest[48..64]
+    )
+}
+
+fn core_properties(nuspec: &Nuspec) -> String {
+    let metadata = &nuspec.metadata;
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<coreProperties xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns="http://schemas.openxmlformats.org/package/2006/metadata/core-properties">
+  <dc:creator>{}</dc:creator>
+  <dc:description>{}</dc:description>
+  <dc:identifier>{}</dc:identifier>
+  <version>{}</version>
+  <keywords>{}</keywords>
+  <lastModifiedBy>nougat</lastModifiedBy>
+</coreProperties>
+"#,
+        nuspec::escape(&metadata.authors),
+        nuspec::escape(&metadata.description),
+        nuspec::escape(&metadata.id),
+        nuspec::escape(
+            &metadata
+                .version
+                .as_ref()
+                .map_or(String::new(), NuGetVersion::to_full_string)
+        ),
+        nuspec::escape(&metadata.tags)
+    )
+}
+
+// Every part needs a content type, by its extension or by its name if it has none
+fn content_types<'a, I: IntoIterator<Item = &'a str>>(targets: I) -> String {
+    let mut extensions = vec![
+        (
+            String::from("rels"),
+            "application/vnd.openxmlformats-package.relationships+xml",
+        ),
+        (String::from("nuspec"), "application/octet"),
+        (
+            String::from("psmdcp"),
+            "application/vnd.openxmlformats-package.core-properties+xml",
+        ),
+    ];
+    let mut overrides = Vec::new();
+
+    for target in targets {
+        let file_name = target.rsplit('/').next().unwrap_or(target);
+
+        match file_name.rfind('.') {
+            Some(index) if index + 1 < file_name.len() => {
+                let extension = file_name[index + 1..].to_lowercase();
+                if !extensions
+                    .iter()
+                    .any(|(existing, _)| *existing == extension)
+                {
+                    extensions.push((extension, "application/octet"));
+                }
+            }
+            _ => overrides.push(escape_part_name(target)),
+        }
+    }
+
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+"#,
+    );
+    for (extension, content_type) in extensions {
+        xml.push_str(&format!(
+            "  <Default Extension=\"{}\" ContentType=\"{}\" />\n",
+            nuspec::escape(&extension),
+            content_type
+        ));
+    }
+    for part_name in overrides {
+        xml.push_str(&format!(
+            "  <Override PartName=\"/{}\" ContentType=\"application/octet\" />\n",
+            nuspec::escape(&part_name)
+        ));
+    }
+    xml.push_str("</Types>\n");
+
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nupkg::nuspec::{Dependency, DependencyGroup, License, Metadata};
+    use nupkg::{FileKind, Nupkg};
+    use tempdir::TempDir;
+
+    fn nuspec() -> Nuspec {
+        Nuspec {
+            metadata: Metadata {
+                id: String::from("Fint.Sse"),
+                version: Some("1.0.0-beta+build.1".parse().unwrap()),
+                authors: String::from("FINTprosjektet"),
+                description: String::from("Server-sent events & more"),
+                tags: String::from("fint sse"),
+                license: Some(License {
+                    license_type: String::from("expression"),
+                    version: None,
+                    value: String::from("MIT"),
+                }),
+                dependency_groups: vec![DependencyGroup {
+                    target_framework: "netstandard2.0".parse().unwrap(),
+                    dependencies: vec![Dependency {
+                        id: String::from("Newtonsoft.Json"),
+                        version: "[12.0.1, )".parse().unwrap(),
+                        include: None,
+                        exclude: Some(String::from("Build,Analyzers")),
+                    }],
+                }],
+                ..Metadata::default()
+            },
+        }
+    }
+
+    #[test]
+    fn build() {
+        let dir = TempDir::new("nougat").unwrap();
+        let dll = dir.path().join("Fint.Sse.dll");
+        let readme = dir.path().join("README");
+        File::create(&dll).unwrap().write_all(b"dll").unwrap();
+        File::create(&readme).unwrap().write_all(b"readme").unwrap();
+
+        let builder = PackageBuilder::new(nuspec()).files(vec![
+            (&dll, "lib/netstandard2.0/Fint.Sse.dll"),
+            (&dll, "lib/portable-net45+win8/Fint.Sse.dll"),
+            (&readme, "docs\\My README"),
+        ]);
+        let bytes = builder.build().unwrap();
+
+        // Building is deterministic
+        assert_eq!(bytes, builder.build().unwrap());
+
+        let mut nupkg = Nupkg::from_bytes(bytes).unwrap();
+        assert_eq!(nupkg.nuspec(), &nuspec());
+        assert_eq!(
+            nupkg.files(),
+            [
+                "lib/netstandard2.0/Fint.Sse.dll",
+                "lib/portable-net45%2Bwin8/Fint.Sse.dll",
+                "docs/My%20README",
+            ]
+        );
+        assert_eq!(
+            nupkg.frameworks(FileKind::Lib),
+            vec![
+                "netstandard2.0".parse().unwrap(),
+                "portable-net45+win8".parse().unwrap(),
+            ]
+        );
+        assert_eq!(nupkg.read_file("docs/My%20README").unwrap(), b"readme");
+
+        let content_types =
+            String::from_utf8(nupkg.read_file("[Content_Types].xml").unwrap()).unwrap();
+        assert!(content_types
+            .contains(r#"<Default Extension="dll" ContentType="application/octet" />"#));
+        assert!(content_types.contains(
+            r#"<Override PartName="/docs/My%20README" ContentType="application/octet" />"#
+        ));
+
+        let relationships = String::from_utf8(nupkg.read_file("_rels/.rels").unwrap()).unwrap();
+        assert!(relationships.contains(r#"Target="/Fint.Sse.nuspec""#));
+    }
+
+    #[test]
+    fn invalid_packages() {
+        let invalid = |builder: PackageBuilder| builder.validate().is_err();
+
+        let with_id = |id: &str| {
+            let mut nuspec = nuspec();
+            nuspec.metadata.id = String::from(id);
+            PackageBuilder::new(nuspec)
+        };
+
+        assert!(!invalid(with_id("Fint.Sse")));
+        assert!(!invalid(with_id("_Fint__Sse_")));
+        assert!(invalid(with_id("")));
+        assert!(invalid(with_id("Fint..Sse")));
+        assert!(invalid(with_id("-Fint")));
+        assert!(invalid(with_id("Fint Sse")));
+        assert!(invalid(with_id(&"a".repeat(101))));
+        assert!(invalid(with_id("Newtonsoft.Json")));
+
+        let mut nuspec = self::nuspec();
+        nuspec.metadata.version = None;
+        assert!(invalid(PackageBuilder::new(nuspec)));
+
+        let mut nuspec = self::nuspec();
+        nuspec.metadata.description = String::new();
+        assert!(invalid(PackageBuilder::new(nuspec)));
+
+        let mut nuspec = self::nuspec();
+        nuspec.metadata.dependency_groups.clear();
+        assert!(invalid(PackageBuilder::new(nuspec)));
+
+        for target in &[
+            "",
+            "/lib/a.dll",
+            "lib/../a.dll",
+            "_rels/.rels",
+            "Other.nuspec",
+        ] {
+            assert!(
+                invalid(PackageBuilder::new(self::nuspec()).file("a.dll", target)),
+                "{}",
+                target
+            );
+        }
+
+        assert!(invalid(
+            PackageBuilder::new(self::nuspec())
+                .file("a.dll", "lib/a.dll")
+                .file("b.dll", "LIB/A.dll")
+        ));
+    }
+}
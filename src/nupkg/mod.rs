@@ -0,0 +1,404 @@
+use failure::Error;
+use framework::NuGetFramework;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+use zip::ZipArchive;
+
+pub mod builder;
+pub mod nuspec;
+pub mod signature;
+
+use self::nuspec::Nuspec;
+use self::signature::{Signature, SIGNATURE_FILE};
+
+/// A package archive, from https://docs.microsoft.com/en-us/nuget/reference/nupkg
+pub struct Nupkg<R> {
+    archive: ZipArchive<R>,
+    nuspec: Nuspec,
+    files: Vec<String>,
+    is_signed: bool,
+}
+
+impl Nupkg<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Nupkg<File>, Error> {
+        Nupkg::new(File::open(path)?)
+    }
+}
+
+impl Nupkg<Cursor<Vec<u8>>> {
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Nupkg<Cursor<Vec<u8>>>, Error> {
+        Nupkg::new(Cursor::new(bytes))
+    }
+}
+
+impl<R: Read + Seek> Nupkg<R> {
+    pub fn new(reader: R) -> Result<Nupkg<R>, Error> {
+        let mut archive = ZipArchive::new(reader)?;
+
+        let mut names = Vec::new();
+        for index in 0..archive.len() {
+            names.push(String::from(archive.by_index(index)?.name()));
+        }
+
+        // The nuspec is the only one at the root of the archive
+        let nuspec_name = names
+            .iter()
+            .find(|name| !name.contains('/') && name.to_lowercase().ends_with(".nuspec"))
+            .cloned()
+            .ok_or_else(|| format_err!("The package has no nuspec"))?;
+        let nuspec = Nuspec::from_reader(archive.by_name(&nuspec_name)?)?;
+
+        let is_signed = names.iter().any(|name| name == SIGNATURE_FILE);
+        let files = names
+            .into_iter()
+            .filter(|name| *name != nuspec_name && !is_packaging_file(name))
+            .collect();
+
+        Ok(Nupkg {
+            archive,
+            nuspec,
+            files,
+            is_signed,
+        })
+    }
+
+    pub fn nuspec(&self) -> &Nuspec {
+        &self.nuspec
+    }
+
+    /// The paths of the package's files, excluding the nuspec and packaging metadata.
+    ///
+    /// Paths are as stored in the archive, so may be percent-encoded.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// The package's files grouped by kind, framework and runtime, in archive order.
+    pub fn file_groups(&self) -> Vec<FileGroup> {
+        let mut groups: Vec<FileGroup> = Vec::new();
+
+        for file in &self.files {
+            let (kind, framework, runtime) = match classify(file) {
+                Some(classified) => classified,
+                None => continue,
+            };
+
+            match groups.iter_mut().find(|group| {
+                group.kind == kind && group.framework == framework && group.runtime == runtime
+            }) {
+                Some(group) => group.files.push(file.clone()),
+                None => groups.push(FileGroup {
+                    kind,
+                    framework,
+                    runtime,
+                    files: vec![file.clone()],
+                }),
+            }
+        }
+
+        groups
+    }
+
+    /// The frameworks the package has files for in `kind` folders.
+    pub fn frameworks(&self, kind: FileKind) -> Vec<NuGetFramework> {
+        let mut frameworks: Vec<NuGetFramework> = Vec::new();
+
+        for group in self.file_groups() {
+            if group.kind == kind && !frameworks.contains(&group.framework) {
+                frameworks.push(group.framework);
+            }
+        }
+
+        frameworks
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.is_signed
+    }
+
+    /// The package's signature, if it's signed.
+    ///
+    /// Use `Signature::verify` with the package's bytes to check it.
+    pub fn signature(&mut self) -> Result<Option<Signature>, Error> {
+        if !self.is_signed {
+            return Ok(None);
+        }
+
+        let mut der = Vec::new();
+        self.archive
+            .by_name(SIGNATURE_FILE)?
+            .read_to_end(&mut der)?;
+        Signature::from_der(&der).map(Some)
+    }
+
+    pub fn read_file(&mut self, path: &str) -> Result<Vec<u8>, Error> {
+        let mut file = self
+            .archive
+            .by_name(path)
+            .map_err(|_| format_err!("The package has no file {}", path))?;
+
+        let mut buffer = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+/// The top level folder of a package file, which determines how it's used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    /// Assemblies referenced at compile time and copied to the output.
+    Lib,
+    /// Reference assemblies, used at compile time instead of `lib`.
+    Ref,
+    /// Assets for a runtime identifier, e.g. `runtimes/win-x64/native/`.
+    Runtimes,
+    /// MSBuild `.props` and `.targets` files.
+    Build,
+    /// Scripts and executables, e.g. `tools/init.ps1`.
+    Tools,
+    /// Files copied into projects, for `packages.config` projects.
+    Content,
+}
+
+impl FileKind {
+    fn from_folder(folder: &str) -> Option<FileKind> {
+        let kinds = &[
+            ("lib", FileKind::Lib),
+            ("ref", FileKind::Ref),
+            ("runtimes", FileKind::Runtimes),
+            ("build", FileKind::Build),
+            ("tools", FileKind::Tools),
+            ("content", FileKind::Content),
+        ];
+
+        kinds
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(folder))
+            .map(|&(_, kind)| kind)
+    }
+}
+
+/// Files of the same kind, for the same framework and runtime.
+#[derive(Debug, PartialEq)]
+pub struct FileGroup {
+    pub kind: FileKind,
+    /// From the framework folder, e.g. `lib/net45/`, or any for files outside of one.
+    pub framework: NuGetFramework,
+    /// The runtime identifier of files under `runtimes/`, e.g. `win-x64`.
+    pub runtime: Option<String>,
+    pub files: Vec<String>,
+}
+
+// The parts that make the archive a valid Open Packaging Conventions package
+pub(crate) fn is_packaging_file(path: &str) -> bool {
+    let lowercase = path.to_lowercase();
+
+    lowercase == "[content_types].xml"
+        || lowercase == SIGNATURE_FILE
+        || lowercase.starts_with("_rels/")
+        || lowercase.starts_with("package/")
+        || lowercase.ends_with('/')
+}
+
+fn classify(path: &str) -> Option<(FileKind, NuGetFramework, Option<String>)> {
+    let mut segments = path.split('/').collect::<Vec<_>>();
+    // Drop the file name, leaving the folders
+    segments.pop();
+
+    let kind = FileKind::from_folder(segments.first()?)?;
+    let mut folders = &segments[1..];
+    let mut runtime = None;
+
+    // `runtimes/{rid}/lib/{tfm}/` or `runtimes/{rid}/native/`
+    if kind == FileKind::Runtimes {
+        runtime = Some(String::from(*folders.first()?));
+        folders = &folders[1..];
+
+        match folders.first() {
+            Some(folder) if folder.eq_ignore_ascii_case("lib") => folders = &folders[1..],
+            _ => return Some((kind, NuGetFramework::any(), runtime)),
+        }
+    }
+
+    let framework = folders
+        .first()
+        .and_then(|folder| unescape(folder).parse().ok())
+        .filter(NuGetFramework::is_known)
+        .unwrap_or_else(NuGetFramework::any);
+
+    Some((kind, framework, runtime))
+}
+
+// Part names are percent-encoded, e.g. `portable-net45%2Bwin8`
+pub(crate) fn escape_part_name(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                escaped.push(byte as char)
+            }
+            byte => escaped.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    escaped
+}
+
+fn unescape(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes.get(index + 1..index + 3).and_then(|hex| {
+            ::std::str::from_utf8(hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        });
+
+        match (bytes[index], escaped) {
+            (b'%', Some(byte)) => {
+                unescaped.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                unescaped.push(byte);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::{FileOptions, ZipWriter};
+
+    const NUSPEC: &str = r##"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://schemas.microsoft.com/packaging/2013/05/nuspec.xsd">
+  <metadata>
+    <id>Fint.Sse</id>
+    <version>1.0.0</version>
+    <authors>FINTprosjektet</authors>
+    <description>Server-sent events</description>
+  </metadata>
+</package>"##;
+
+    fn nupkg(files: &[&str]) -> Nupkg<Cursor<Vec<u8>>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+        writer
+            .start_file("Fint.Sse.nuspec", FileOptions::default())
+            .unwrap();
+        writer.write_all(NUSPEC.as_bytes()).unwrap();
+
+        for file in files {
+            writer.start_file(*file, FileOptions::default()).unwrap();
+            writer.write_all(file.as_bytes()).unwrap();
+        }
+
+        Nupkg::from_bytes(writer.finish().unwrap().into_inner()).unwrap()
+    }
+
+    #[test]
+    fn files() {
+        let mut nupkg = nupkg(&[
+            "_rels/.rels",
+            "[Content_Types].xml",
+            "package/services/metadata/core-properties/1.psmdcp",
+            "lib/net45/Fint.Sse.dll",
+            "README.md",
+        ]);
+
+        assert_eq!(nupkg.nuspec().metadata.id, "Fint.Sse");
+        assert_eq!(nupkg.files(), ["lib/net45/Fint.Sse.dll", "README.md"]);
+        assert_eq!(
+            nupkg.read_file("lib/net45/Fint.Sse.dll").unwrap(),
+            b"lib/net45/Fint.Sse.dll"
+        );
+        assert!(nupkg.read_file("lib/net40/Fint.Sse.dll").is_err());
+
+        assert!(Nupkg::from_bytes(b"not a zip".to_vec()).is_err());
+    }
+
+    #[test]
+    fn file_groups() {
+        let nupkg = nupkg(&[
+            "lib/net45/Fint.Sse.dll",
+            "lib/net45/Fint.Sse.xml",
+            "lib/netstandard2.0/Fint.Sse.dll",
+            "lib/portable-net45%2Bwin8/Fint.Sse.dll",
+            "lib/Legacy.dll",
+            "ref/netstandard2.0/Fint.Sse.dll",
+            "runtimes/win-x64/native/sse.dll",
+            "runtimes/linux-x64/lib/netstandard2.0/Fint.Sse.dll",
+            "build/Fint.Sse.targets",
+            "tools/init.ps1",
+            "content/scripts/sse.js",
+            "README.md",
+        ]);
+
+        let group = |kind, framework: &str, runtime: Option<&str>, files: &[&str]| FileGroup {
+            kind,
+            framework: framework.parse().unwrap(),
+            runtime: runtime.map(String::from),
+            files: files.iter().map(|file| String::from(*file)).collect(),
+        };
+
+        assert_eq!(
+            nupkg.file_groups(),
+            vec![
+                group(
+                    FileKind::Lib,
+                    "net45",
+                    None,
+                    &["lib/net45/Fint.Sse.dll", "lib/net45/Fint.Sse.xml"]
+                ),
+                group(
+                    FileKind::Lib,
+                    "netstandard2.0",
+                    None,
+                    &["lib/netstandard2.0/Fint.Sse.dll"]
+                ),
+                group(
+                    FileKind::Lib,
+                    "portable-net45+win8",
+                    None,
+                    &["lib/portable-net45%2Bwin8/Fint.Sse.dll"]
+                ),
+                group(FileKind::Lib, "any", None, &["lib/Legacy.dll"]),
+                group(
+                    FileKind::Ref,
+                    "netstandard2.0",
+                    None,
+                    &["ref/netstandard2.0/Fint.Sse.dll"]
+                ),
+                group(
+                    FileKind::Runtimes,
+                    "any",
+                    Some("win-x64"),
+                    &["runtimes/win-x64/native/sse.dll"]
+                ),
+                group(
+                    FileKind::Runtimes,
+                    "netstandard2.0",
+                    Some("linux-x64"),
+                    &["runtimes/linux-x64/lib/netstandard2.0/Fint.Sse.dll"]
+                ),
+                group(FileKind::Build, "any", None, &["build/Fint.Sse.targets"]),
+                group(FileKind::Tools, "any", None, &["tools/init.ps1"]),
+                group(FileKind::Content, "any", None, &["content/scripts/sse.js"]),
+            ]
+        );
+
+        assert_eq!(
+            nupkg.frameworks(FileKind::Ref),
+            vec!["netstandard2.0".parse().unwrap()]
+        );
+    }
+}
@@ -0,0 +1,728 @@
+use failure::Error;
+use framework::NuGetFramework;
+use package::{self, DependencyGroups, PackageDependency, UrlProperty};
+use serde::de::Error as DeserializeError;
+use serde::{Deserialize, Deserializer};
+use serde_xml_rs;
+use std::io::Read;
+use version::{NuGetVersion, VersionRange};
+
+const NAMESPACE: &str = "http://schemas.microsoft.com/packaging/2013/05/nuspec.xsd";
+
+/// A package manifest, from https://docs.microsoft.com/en-us/nuget/reference/nuspec
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct Nuspec {
+    pub metadata: Metadata,
+}
+
+impl Nuspec {
+    pub fn from_reader<R: Read>(reader: R) -> Result<Nuspec, Error> {
+        serde_xml_rs::from_reader(reader)
+            .map_err(|e| format_err!("Unable to deserialize nuspec: {}", e))
+    }
+
+    pub fn to_xml(&self) -> String {
+        let metadata = &self.metadata;
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str(&format!("<package xmlns=\"{}\">\n", NAMESPACE));
+
+        match metadata.min_client_version {
+            Some(ref version) => xml.push_str(&format!(
+                "  <metadata minClientVersion=\"{}\">\n",
+                escape(version)
+            )),
+            None => xml.push_str("  <metadata>\n"),
+        }
+
+        let mut element = |name: &str, value: &str| {
+            if !value.is_empty() {
+                xml.push_str(&format!("    <{0}>{1}</{0}>\n", name, escape(value)));
+            }
+        };
+
+        element("id", &metadata.id);
+        element(
+            "version",
+            &metadata
+                .version
+                .as_ref()
+                .map_or(String::new(), NuGetVersion::to_full_string),
+        );
+        element("title", metadata.title.as_ref().map_or("", String::as_str));
+        element("authors", &metadata.authors);
+        element(
+            "owners",
+            metadata.owners.as_ref().map_or("", String::as_str),
+        );
+        if metadata.require_license_acceptance {
+            element("requireLicenseAcceptance", "true");
+        }
+        if metadata.development_dependency {
+            element("developmentDependency", "true");
+        }
+        element("licenseUrl", metadata.license_url.raw());
+        element("projectUrl", metadata.project_url.raw());
+        element("iconUrl", metadata.icon_url.raw());
+        element("icon", metadata.icon.as_ref().map_or("", String::as_str));
+        element(
+            "readme",
+            metadata.readme.as_ref().map_or("", String::as_str),
+        );
+        element("description", &metadata.description);
+        element(
+            "summary",
+            metadata.summary.as_ref().map_or("", String::as_str),
+        );
+        element(
+            "releaseNotes",
+            metadata.release_notes.as_ref().map_or("", String::as_str),
+        );
+        element(
+            "copyright",
+            metadata.copyright.as_ref().map_or("", String::as_str),
+        );
+        element(
+            "language",
+            metadata.language.as_ref().map_or("", String::as_str),
+        );
+        element("tags", &metadata.tags);
+        if metadata.serviceable {
+            element("serviceable", "true");
+        }
+
+        if let Some(ref license) = metadata.license {
+            xml.push_str(&format!(
+                "    <license type=\"{}\"{}>{}</license>\n",
+                escape(&license.license_type),
+                attribute("version", license.version.as_ref()),
+                escape(&license.value)
+            ));
+        }
+
+        if let Some(ref repository) = metadata.repository {
+            xml.push_str(&format!(
+                "    <repository{}{}{}{} />\n",
+                attribute("type", repository.repository_type.as_ref()),
+                attribute(
+                    "url",
+                    Some(repository.url.raw()).filter(|url| !url.is_empty())
+                ),
+                attribute("branch", repository.branch.as_ref()),
+                attribute("commit", repository.commit.as_ref())
+            ));
+        }
+
+        if !metadata.package_types.is_empty() {
+            xml.push_str("    <packageTypes>\n");
+            for package_type in &metadata.package_types {
+                xml.push_str(&format!(
+                    "      <packageType{}{} />\n",
+                    attribute("name", Some(&package_type.name)),
+                    attribute("version", package_type.version.as_ref())
+                ));
+            }
+            xml.push_str("    </packageTypes>\n");
+        }
+
+        if !metadata.dependency_groups.is_empty() {
+            xml.push_str("    <dependencies>\n");
+            for group in &metadata.dependency_groups {
+                xml.push_str(&format!(
+                    "      <group{}>\n",
+                    target_framework(&group.target_framework)
+                ));
+                for dependency in &group.dependencies {
+                    let version = Some(dependency.version.to_normalized_string())
+                        .filter(|_| dependency.version != VersionRange::all());
+
+                    xml.push_str(&format!(
+                        "        <dependency{}{}{}{} />\n",
+                        attribute("id", Some(&dependency.id)),
+                        attribute("version", version.as_ref()),
+                        attribute("include", dependency.include.as_ref()),
+                        attribute("exclude", dependency.exclude.as_ref())
+                    ));
+                }
+                xml.push_str("      </group>\n");
+            }
+            xml.push_str("    </dependencies>\n");
+        }
+
+        if !metadata.framework_assemblies.is_empty() {
+            xml.push_str("    <frameworkAssemblies>\n");
+            for assembly in &metadata.framework_assemblies {
+                let frameworks = assembly
+                    .target_frameworks
+                    .iter()
+                    .map(NuGetFramework::to_short_name)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                xml.push_str(&format!(
+                    "      <frameworkAssembly{}{} />\n",
+                    attribute("assemblyName", Some(&assembly.assembly_name)),
+                    attribute(
+                        "targetFramework",
+                        Some(&frameworks).filter(|f| !f.is_empty())
+                    )
+                ));
+            }
+            xml.push_str("    </frameworkAssemblies>\n");
+        }
+
+        if !metadata.reference_groups.is_empty() {
+            xml.push_str("    <references>\n");
+            for group in &metadata.reference_groups {
+                xml.push_str(&format!(
+                    "      <group{}>\n",
+                    target_framework(&group.target_framework)
+                ));
+                for reference in &group.references {
+                    xml.push_str(&format!(
+                        "        <reference{} />\n",
+                        attribute("file", Some(&reference.file))
+                    ));
+                }
+                xml.push_str("      </group>\n");
+            }
+            xml.push_str("    </references>\n");
+        }
+
+        if !metadata.content_files.is_empty() {
+            xml.push_str("    <contentFiles>\n");
+            for files in &metadata.content_files {
+                xml.push_str(&format!(
+                    "      <files{}{}{}{}{} />\n",
+                    attribute("include", Some(&files.include)),
+                    attribute("exclude", files.exclude.as_ref()),
+                    attribute("buildAction", files.build_action.as_ref()),
+                    attribute(
+                        "copyToOutput",
+                        files.copy_to_output.map(|b| b.to_string()).as_ref()
+                    ),
+                    attribute("flatten", files.flatten.map(|b| b.to_string()).as_ref())
+                ));
+            }
+            xml.push_str("    </contentFiles>\n");
+        }
+
+        xml.push_str("  </metadata>\n</package>\n");
+        xml
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct Metadata {
+    #[serde(rename = "minClientVersion")]
+    pub min_client_version: Option<String>,
+    pub id: String,
+    pub version: Option<NuGetVersion>,
+    pub title: Option<String>,
+    #[serde(default)]
+    pub authors: String,
+    pub owners: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "releaseNotes")]
+    pub release_notes: Option<String>,
+    pub summary: Option<String>,
+    pub language: Option<String>,
+    #[serde(rename = "projectUrl", default)]
+    pub project_url: UrlProperty,
+    #[serde(rename = "iconUrl", default)]
+    pub icon_url: UrlProperty,
+    /// The path to an icon inside the package.
+    pub icon: Option<String>,
+    /// The path to a readme inside the package.
+    pub readme: Option<String>,
+    pub license: Option<License>,
+    #[serde(rename = "licenseUrl", default)]
+    pub license_url: UrlProperty,
+    pub copyright: Option<String>,
+    #[serde(rename = "requireLicenseAcceptance", default)]
+    pub require_license_acceptance: bool,
+    #[serde(rename = "developmentDependency", default)]
+    pub development_dependency: bool,
+    #[serde(default)]
+    pub serviceable: bool,
+    #[serde(default)]
+    pub tags: String,
+    pub repository: Option<Repository>,
+    #[serde(rename = "packageTypes", default, deserialize_with = "package_types")]
+    pub package_types: Vec<PackageType>,
+    /// Dependencies declared outside of a group are in a group for any framework.
+    #[serde(
+        rename = "dependencies",
+        default,
+        deserialize_with = "dependency_groups"
+    )]
+    pub dependency_groups: Vec<DependencyGroup>,
+    #[serde(
+        rename = "frameworkAssemblies",
+        default,
+        deserialize_with = "framework_assemblies"
+    )]
+    pub framework_assemblies: Vec<FrameworkAssembly>,
+    /// References declared outside of a group are in a group for any framework.
+    #[serde(rename = "references", default, deserialize_with = "reference_groups")]
+    pub reference_groups: Vec<ReferenceGroup>,
+    #[serde(rename = "contentFiles", default, deserialize_with = "content_files")]
+    pub content_files: Vec<ContentFiles>,
+}
+
+impl Metadata {
+    pub fn tags(&self) -> Vec<&str> {
+        package::split_tags(&self.tags)
+    }
+
+    pub fn authors(&self) -> Vec<&str> {
+        package::split_list(Some(&self.authors))
+    }
+
+    pub fn owners(&self) -> Vec<&str> {
+        package::split_list(self.owners.as_ref().map(String::as_str))
+    }
+
+    /// The dependency groups, as found in feeds.
+    pub fn dependencies(&self) -> DependencyGroups {
+        let mut groups = DependencyGroups::new();
+
+        for group in &self.dependency_groups {
+            let package_group = groups.group_mut(group.target_framework.clone());
+
+            for dependency in &group.dependencies {
+                package_group.dependencies.push(PackageDependency {
+                    package_id: dependency.id.clone(),
+                    version: dependency.version.clone(),
+                    framework: group.target_framework.clone(),
+                });
+            }
+        }
+
+        groups
+    }
+}
+
+/// An SPDX license expression, or the path to a license file inside the package.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct License {
+    #[serde(rename = "type")]
+    pub license_type: String,
+    pub version: Option<String>,
+    #[serde(rename = "$value", default)]
+    pub value: String,
+}
+
+impl License {
+    pub fn is_expression(&self) -> bool {
+        self.license_type.eq_ignore_ascii_case("expression")
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.license_type.eq_ignore_ascii_case("file")
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct Repository {
+    #[serde(rename = "type")]
+    pub repository_type: Option<String>,
+    #[serde(default)]
+    pub url: UrlProperty,
+    pub branch: Option<String>,
+    pub commit: Option<String>,
+}
+
+/// E.g. `Dependency`, `DotnetTool` or `Template`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct PackageType {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct DependencyGroup {
+    #[serde(rename = "targetFramework", default)]
+    pub target_framework: NuGetFramework,
+    #[serde(rename = "dependency", default)]
+    pub dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct Dependency {
+    pub id: String,
+    #[serde(default)]
+    pub version: VersionRange,
+    /// Comma separated asset types to include, e.g. `Compile,Runtime`.
+    pub include: Option<String>,
+    /// Comma separated asset types to exclude, e.g. `Build,Analyzers`.
+    pub exclude: Option<String>,
+}
+
+/// A reference to an assembly in the GAC.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct FrameworkAssembly {
+    #[serde(rename = "assemblyName")]
+    pub assembly_name: String,
+    /// Empty if the assembly is needed for every framework.
+    #[serde(rename = "targetFramework", default, deserialize_with = "frameworks")]
+    pub target_frameworks: Vec<NuGetFramework>,
+}
+
+/// The assemblies in `lib/` that projects targeting a framework should reference.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct ReferenceGroup {
+    #[serde(rename = "targetFramework", default)]
+    pub target_framework: NuGetFramework,
+    #[serde(rename = "reference", default)]
+    pub references: Vec<Reference>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct Reference {
+    pub file: String,
+}
+
+/// How the files matching a glob under `contentFiles/` are added to projects.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct ContentFiles {
+    pub include: String,
+    pub exclude: Option<String>,
+    #[serde(rename = "buildAction")]
+    pub build_action: Option<String>,
+    #[serde(rename = "copyToOutput", default, deserialize_with = "optional_bool")]
+    pub copy_to_output: Option<bool>,
+    #[serde(default, deserialize_with = "optional_bool")]
+    pub flatten: Option<bool>,
+}
+
+// Lists of elements are wrapped in an element of their own, e.g. `<packageTypes>`
+
+#[derive(Deserialize)]
+struct PackageTypes {
+    #[serde(rename = "packageType", default)]
+    package_types: Vec<PackageType>,
+}
+
+fn package_types<'de, D>(deserializer: D) -> Result<Vec<PackageType>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    PackageTypes::deserialize(deserializer).map(|list| list.package_types)
+}
+
+#[derive(Deserialize)]
+struct Dependencies {
+    #[serde(rename = "group", default)]
+    groups: Vec<DependencyGroup>,
+    #[serde(rename = "dependency", default)]
+    dependencies: Vec<Dependency>,
+}
+
+fn dependency_groups<'de, D>(deserializer: D) -> Result<Vec<DependencyGroup>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut list = Dependencies::deserialize(deserializer)?;
+
+    if !list.dependencies.is_empty() {
+        list.groups.push(DependencyGroup {
+            target_framework: NuGetFramework::any(),
+            dependencies: list.dependencies,
+        });
+    }
+
+    Ok(list.groups)
+}
+
+#[derive(Deserialize)]
+struct FrameworkAssemblies {
+    #[serde(rename = "frameworkAssembly", default)]
+    framework_assemblies: Vec<FrameworkAssembly>,
+}
+
+fn framework_assemblies<'de, D>(deserializer: D) -> Result<Vec<FrameworkAssembly>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    FrameworkAssemblies::deserialize(deserializer).map(|list| list.framework_assemblies)
+}
+
+#[derive(Deserialize)]
+struct References {
+    #[serde(rename = "group", default)]
+    groups: Vec<ReferenceGroup>,
+    #[serde(rename = "reference", default)]
+    references: Vec<Reference>,
+}
+
+fn reference_groups<'de, D>(deserializer: D) -> Result<Vec<ReferenceGroup>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut list = References::deserialize(deserializer)?;
+
+    if !list.references.is_empty() {
+        list.groups.push(ReferenceGroup {
+            target_framework: NuGetFramework::any(),
+            references: list.references,
+        });
+    }
+
+    Ok(list.groups)
+}
+
+#[derive(Deserialize)]
+struct ContentFilesList {
+    #[serde(rename = "files", default)]
+    files: Vec<ContentFiles>,
+}
+
+fn content_files<'de, D>(deserializer: D) -> Result<Vec<ContentFiles>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    ContentFilesList::deserialize(deserializer).map(|list| list.files)
+}
+
+// Attributes are written by hand as often as by tools, so accept `True` as well as `true`
+fn optional_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+
+    if value.trim().eq_ignore_ascii_case("true") {
+        Ok(Some(true))
+    } else if value.trim().eq_ignore_ascii_case("false") {
+        Ok(Some(false))
+    } else {
+        Err(DeserializeError::custom(format!(
+            "{} is not a valid boolean",
+            value
+        )))
+    }
+}
+
+// `.NETFramework4.0, Silverlight4`, although it's usually a single framework
+fn frameworks<'de, D>(deserializer: D) -> Result<Vec<NuGetFramework>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let frameworks = String::deserialize(deserializer)?;
+
+    // Long names like `.NETFramework,Version=v4.0` also contain commas
+    if frameworks.contains('=') {
+        return frameworks
+            .parse()
+            .map(|framework| vec![framework])
+            .map_err(DeserializeError::custom);
+    }
+
+    frameworks
+        .split(',')
+        .filter(|framework| !framework.trim().is_empty())
+        .map(|framework| framework.parse().map_err(DeserializeError::custom))
+        .collect()
+}
+
+/// Escapes `value` for use in XML text or attribute values.
+pub(crate) fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+// ` name="value"`, or nothing without a value
+fn attribute<S: AsRef<str>>(name: &str, value: Option<S>) -> String {
+    match value {
+        Some(value) => format!(" {}=\"{}\"", name, escape(value.as_ref())),
+        None => String::new(),
+    }
+}
+
+// Groups for any framework have no `targetFramework`
+fn target_framework(framework: &NuGetFramework) -> String {
+    if framework.is_any() {
+        String::new()
+    } else {
+        attribute("targetFramework", Some(framework.to_short_name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grouped_nuspec() {
+        let nuspec = r##"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://schemas.microsoft.com/packaging/2013/05/nuspec.xsd">
+  <metadata minClientVersion="2.12">
+    <id>Newtonsoft.Json</id>
+    <version>12.0.1</version>
+    <title>Json.NET</title>
+    <authors>James Newton-King</authors>
+    <owners>James Newton-King</owners>
+    <requireLicenseAcceptance>false</requireLicenseAcceptance>
+    <license type="expression">MIT</license>
+    <licenseUrl>https://licenses.nuget.org/MIT</licenseUrl>
+    <projectUrl>https://www.newtonsoft.com/json</projectUrl>
+    <iconUrl>https://www.newtonsoft.com/content/images/nugeticon.png</iconUrl>
+    <description>Json.NET is a popular high-performance JSON framework for .NET</description>
+    <copyright>Copyright © James Newton-King 2008</copyright>
+    <tags>json</tags>
+    <repository type="git" url="https://github.com/JamesNK/Newtonsoft.Json.git" commit="8d9ee5e1b7a0c8b4fa5ef4e7fba4ae03bf1d0b3a" />
+    <packageTypes>
+      <packageType name="Dependency" />
+    </packageTypes>
+    <dependencies>
+      <group targetFramework=".NETFramework2.0" />
+      <group targetFramework=".NETStandard1.0">
+        <dependency id="Microsoft.CSharp" version="4.3.0" exclude="Build,Analyzers" />
+        <dependency id="NETStandard.Library" version="[1.6.1, )" exclude="Build,Analyzers" />
+      </group>
+    </dependencies>
+    <frameworkAssemblies>
+      <frameworkAssembly assemblyName="System.Xml" targetFramework=".NETFramework2.0, .NETFramework3.5" />
+      <frameworkAssembly assemblyName="System.Runtime.Serialization" targetFramework=".NETFramework,Version=v4.0" />
+      <frameworkAssembly assemblyName="System.Data" />
+    </frameworkAssemblies>
+    <references>
+      <group targetFramework="net45">
+        <reference file="Newtonsoft.Json.dll" />
+      </group>
+    </references>
+    <contentFiles>
+      <files include="cs/**/*.cs" buildAction="Compile" copyToOutput="False" />
+    </contentFiles>
+  </metadata>
+</package>"##;
+
+        let metadata = Nuspec::from_reader(nuspec.as_bytes()).unwrap().metadata;
+
+        assert_eq!(metadata.id, "Newtonsoft.Json");
+        assert_eq!(metadata.version, Some("12.0.1".parse().unwrap()));
+        assert_eq!(metadata.min_client_version, Some(String::from("2.12")));
+        assert_eq!(metadata.authors(), vec!["James Newton-King"]);
+        assert_eq!(metadata.tags(), vec!["json"]);
+        assert!(!metadata.require_license_acceptance);
+        assert_eq!(
+            metadata.project_url.raw(),
+            "https://www.newtonsoft.com/json"
+        );
+
+        let license = metadata.license.as_ref().unwrap();
+        assert!(license.is_expression());
+        assert_eq!(license.value, "MIT");
+
+        let repository = metadata.repository.as_ref().unwrap();
+        assert_eq!(repository.repository_type, Some(String::from("git")));
+        assert!(repository.url.url().is_some());
+
+        assert_eq!(
+            metadata.package_types,
+            vec![PackageType {
+                name: String::from("Dependency"),
+                version: None,
+            }]
+        );
+
+        assert_eq!(metadata.dependency_groups.len(), 2);
+        assert!(metadata.dependency_groups[0].dependencies.is_empty());
+        assert_eq!(
+            metadata.dependency_groups[1].dependencies[1],
+            Dependency {
+                id: String::from("NETStandard.Library"),
+                version: "[1.6.1, )".parse().unwrap(),
+                include: None,
+                exclude: Some(String::from("Build,Analyzers")),
+            }
+        );
+
+        let dependencies = metadata.dependencies();
+        let netstandard = "netstandard2.0".parse().unwrap();
+        assert_eq!(dependencies.for_framework(&netstandard).len(), 2);
+        assert!(dependencies
+            .for_framework(&"net45".parse().unwrap())
+            .is_empty());
+
+        assert_eq!(
+            metadata.framework_assemblies[0].target_frameworks,
+            vec!["net20".parse().unwrap(), "net35".parse().unwrap()]
+        );
+        assert_eq!(
+            metadata.framework_assemblies[1].target_frameworks,
+            vec!["net40".parse().unwrap()]
+        );
+        assert!(metadata.framework_assemblies[2]
+            .target_frameworks
+            .is_empty());
+
+        assert_eq!(
+            metadata.reference_groups,
+            vec![ReferenceGroup {
+                target_framework: "net45".parse().unwrap(),
+                references: vec![Reference {
+                    file: String::from("Newtonsoft.Json.dll"),
+                }],
+            }]
+        );
+
+        assert_eq!(
+            metadata.content_files,
+            vec![ContentFiles {
+                include: String::from("cs/**/*.cs"),
+                exclude: None,
+                build_action: Some(String::from("Compile")),
+                copy_to_output: Some(false),
+                flatten: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn ungrouped_nuspec() {
+        let nuspec = r##"<?xml version="1.0"?>
+<package>
+  <metadata>
+    <id>Antlr</id>
+    <version>3.5.0.2</version>
+    <authors>Sam Harwell, Terence Parr</authors>
+    <description>ANTLR runtime</description>
+    <dependencies>
+      <dependency id="Antlr3.Runtime" version="3.5.0.2" />
+    </dependencies>
+    <references>
+      <reference file="Antlr3.Runtime.dll" />
+    </references>
+  </metadata>
+</package>"##;
+
+        let metadata = Nuspec::from_reader(nuspec.as_bytes()).unwrap().metadata;
+
+        assert_eq!(metadata.authors(), vec!["Sam Harwell", "Terence Parr"]);
+        assert!(metadata.owners().is_empty());
+        assert!(metadata.license.is_none());
+        assert_eq!(metadata.project_url, UrlProperty::default());
+
+        assert_eq!(metadata.dependency_groups.len(), 1);
+        assert!(metadata.dependency_groups[0].target_framework.is_any());
+        assert_eq!(
+            metadata.dependency_groups[0].dependencies[0].version,
+            "3.5.0.2".parse().unwrap()
+        );
+        assert!(metadata.reference_groups[0].target_framework.is_any());
+    }
+}
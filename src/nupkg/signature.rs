@@ -0,0 +1,1657 @@
+use base64;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use failure::{Error, Fail};
+use openssl::hash::{Hasher, MessageDigest};
+use openssl::pkcs7::{Pkcs7, Pkcs7Flags};
+use openssl::sign::Verifier;
+use openssl::stack::Stack;
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::verify::X509VerifyParam;
+use openssl::x509::{X509StoreContext, X509};
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+/// The name of the signature file at the root of a signed package.
+pub const SIGNATURE_FILE: &str = ".signature.p7s";
+
+// Object identifiers, from https://github.com/NuGet/Home/wiki/Package-Signatures-Technical-Details
+const SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+const MESSAGE_DIGEST: &str = "1.2.840.113549.1.9.4";
+const COUNTERSIGNATURE: &str = "1.2.840.113549.1.9.6";
+const TIMESTAMP_TOKEN: &str = "1.2.840.113549.1.9.16.2.14";
+const TST_INFO: &str = "1.2.840.113549.1.9.16.1.4";
+const COMMITMENT_TYPE_INDICATION: &str = "1.2.840.113549.1.9.16.2.16";
+const PROOF_OF_ORIGIN: &str = "1.2.840.113549.1.9.16.6.1";
+const PROOF_OF_RECEIPT: &str = "1.2.840.113549.1.9.16.6.2";
+const NUGET_V3_SERVICE_INDEX_URL: &str = "1.3.6.1.4.1.311.84.2.1.1.1";
+const NUGET_PACKAGE_OWNERS: &str = "1.3.6.1.4.1.311.84.2.1.1.2";
+
+/// Who made a signature, from its commitment type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureType {
+    /// Made by the package's author, to show where it came from.
+    Author,
+    /// Made by a feed, to show it received the package.
+    Repository,
+    /// Made without a commitment type NuGet knows.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    fn from_oid(oid: &str) -> Option<HashAlgorithm> {
+        match oid {
+            "2.16.840.1.101.3.4.2.1" => Some(HashAlgorithm::Sha256),
+            "2.16.840.1.101.3.4.2.2" => Some(HashAlgorithm::Sha384),
+            "2.16.840.1.101.3.4.2.3" => Some(HashAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    fn message_digest(self) -> MessageDigest {
+        match self {
+            HashAlgorithm::Sha256 => MessageDigest::sha256(),
+            HashAlgorithm::Sha384 => MessageDigest::sha384(),
+            HashAlgorithm::Sha512 => MessageDigest::sha512(),
+        }
+    }
+}
+
+/// A package signature that isn't valid, or isn't trusted.
+#[derive(Debug, PartialEq)]
+pub enum SignatureError {
+    /// The package has changed since it was signed.
+    ContentHashMismatch { expected: String, actual: String },
+    /// A signature doesn't match what it signs.
+    InvalidSignature { signer: String },
+    /// A signer's certificate doesn't chain to the trust store.
+    UntrustedCertificate { signer: String, reason: String },
+    /// None of the signers' certificates are in the allow-list.
+    NotAllowed { fingerprints: Vec<String> },
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignatureError::ContentHashMismatch { expected, actual } => write!(
+                f,
+                "The package has a hash of {}, but {} was signed",
+                actual, expected
+            ),
+            SignatureError::InvalidSignature { signer } => {
+                write!(f, "The signature by {} is invalid", signer)
+            }
+            SignatureError::UntrustedCertificate { signer, reason } => {
+                write!(f, "The certificate of {} is untrusted: {}", signer, reason)
+            }
+            SignatureError::NotAllowed { fingerprints } => write!(
+                f,
+                "None of the signing certificates ({}) are allowed",
+                fingerprints.join(", ")
+            ),
+        }
+    }
+}
+
+impl Fail for SignatureError {}
+
+/// The certificates each type of signature must chain to, and optionally the signing certificates to accept.
+///
+/// Only signers of a type with trusted roots are checked, so trusting just
+/// authors ignores the repository countersignature nuget.org adds to every
+/// package. At least one signer must be checked.
+#[derive(Default)]
+pub struct TrustPolicy {
+    author_roots: Vec<X509>,
+    repository_roots: Vec<X509>,
+    fingerprints: Vec<String>,
+}
+
+impl TrustPolicy {
+    pub fn new() -> TrustPolicy {
+        TrustPolicy::default()
+    }
+
+    /// Trusts author and repository signatures by certificates issued by `root`.
+    pub fn trust(self, root: X509) -> TrustPolicy {
+        self.trust_author(root.clone()).trust_repository(root)
+    }
+
+    /// Trusts author signatures by certificates issued by `root`.
+    pub fn trust_author(mut self, root: X509) -> TrustPolicy {
+        self.author_roots.push(root);
+        self
+    }
+
+    /// Trusts repository signatures and countersignatures by certificates issued by `root`.
+    pub fn trust_repository(mut self, root: X509) -> TrustPolicy {
+        self.repository_roots.push(root);
+        self
+    }
+
+    /// Only accepts packages signed or countersigned by the certificate with this SHA256 fingerprint.
+    ///
+    /// Without any fingerprints, every certificate that chains to a root is accepted.
+    pub fn allow(mut self, fingerprint: &str) -> TrustPolicy {
+        self.fingerprints.push(normalize_fingerprint(fingerprint));
+        self
+    }
+
+    fn roots(&self, signature_type: SignatureType) -> &[X509] {
+        match signature_type {
+            SignatureType::Author => &self.author_roots,
+            SignatureType::Repository => &self.repository_roots,
+            SignatureType::Unknown => &[],
+        }
+    }
+}
+
+/// A signer, along with what its signed attributes say about it.
+pub struct Signer {
+    signature_type: SignatureType,
+    certificate: X509,
+    v3_service_index_url: Option<String>,
+    package_owners: Vec<String>,
+    timestamp: Option<Timestamp>,
+    // Needed to verify countersignatures
+    digest_algorithm: HashAlgorithm,
+    signed_attributes: Vec<u8>,
+    message_digest: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl Signer {
+    pub fn signature_type(&self) -> SignatureType {
+        self.signature_type
+    }
+
+    pub fn certificate(&self) -> &X509 {
+        &self.certificate
+    }
+
+    /// The SHA256 fingerprint of the signing certificate, as uppercase hex.
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.certificate)
+    }
+
+    /// The subject of the signing certificate, e.g. `CN=NuGet.org Repository by Microsoft`.
+    pub fn subject(&self) -> String {
+        subject(&self.certificate)
+    }
+
+    /// The service index of the feed that made a repository signature.
+    pub fn v3_service_index_url(&self) -> Option<&str> {
+        self.v3_service_index_url.as_ref().map(String::as_str)
+    }
+
+    /// The owners of the package on the feed that made a repository signature.
+    pub fn package_owners(&self) -> &[String] {
+        &self.package_owners
+    }
+
+    /// Whether the signature has an RFC 3161 timestamp.
+    pub fn is_timestamped(&self) -> bool {
+        self.timestamp.is_some()
+    }
+
+    /// When a timestamp authority says the signature was made.
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        self.timestamp.as_ref().map(|timestamp| timestamp.time)
+    }
+}
+
+// An RFC 3161 timestamp token, countersigning a signature value
+struct Timestamp {
+    der: Vec<u8>,
+    time: DateTime<Utc>,
+    hash_algorithm: HashAlgorithm,
+    message_imprint: Vec<u8>,
+    certificate: X509,
+    certificates: Vec<X509>,
+}
+
+/// A package's `.signature.p7s`, a PKCS #7 signature over the hash of the rest of the package.
+pub struct Signature {
+    der: Vec<u8>,
+    hash_algorithm: HashAlgorithm,
+    content_hash: Vec<u8>,
+    signer: Signer,
+    countersigners: Vec<Signer>,
+    certificates: Vec<X509>,
+}
+
+impl Signature {
+    pub fn from_der(der: &[u8]) -> Result<Signature, Error> {
+        let SignedData {
+            content,
+            certificates,
+            signer_infos,
+            ..
+        } = parse_signed_data(der)?;
+        let (hash_algorithm, content_hash) = parse_signed_content(content.contents)?;
+
+        if signer_infos.len() != 1 {
+            bail!(
+                "The signature has {} signers, but only one is allowed",
+                signer_infos.len()
+            );
+        }
+
+        let mut countersigners = Vec::new();
+        let signer = parse_signer(&signer_infos[0], &certificates, Some(&mut countersigners))?;
+
+        Ok(Signature {
+            der: der.to_vec(),
+            hash_algorithm,
+            content_hash,
+            signer,
+            countersigners,
+            certificates: certificates
+                .into_iter()
+                .map(|(_, certificate)| certificate)
+                .collect(),
+        })
+    }
+
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// The hash of the package, without its signature, that was signed.
+    pub fn content_hash(&self) -> &[u8] {
+        &self.content_hash
+    }
+
+    /// The primary signer, an author or a repository.
+    pub fn signer(&self) -> &Signer {
+        &self.signer
+    }
+
+    /// Signers of the primary signature, e.g. a repository countersigning an author signature.
+    pub fn countersigners(&self) -> &[Signer] {
+        &self.countersigners
+    }
+
+    /// The signature made by an author, if any.
+    pub fn author(&self) -> Option<&Signer> {
+        self.signers()
+            .find(|signer| signer.signature_type == SignatureType::Author)
+    }
+
+    /// The signature or countersignature made by a repository, if any.
+    pub fn repository(&self) -> Option<&Signer> {
+        self.signers()
+            .find(|signer| signer.signature_type == SignatureType::Repository)
+    }
+
+    fn signers(&self) -> impl Iterator<Item = &Signer> {
+        Some(&self.signer).into_iter().chain(&self.countersigners)
+    }
+
+    /// Checks `package` hasn't changed since it was signed, and that its signers are trusted.
+    ///
+    /// Everything is checked offline. Certificates are checked as of their
+    /// signature's timestamp, so a package signed before its certificate
+    /// expired stays trusted. Signatures without a timestamp are checked as of now.
+    pub fn verify<R: Read + Seek>(&self, package: R, policy: &TrustPolicy) -> Result<(), Error> {
+        let actual = package_hash(package, self.hash_algorithm)?;
+        if actual != self.content_hash {
+            return Err(SignatureError::ContentHashMismatch {
+                expected: base64::encode(&self.content_hash),
+                actual: base64::encode(&actual),
+            }
+            .into());
+        }
+
+        // Chains are checked separately, as PKCS #7 only accepts S/MIME certificates
+        if !verify_signed_data(&self.der, &self.certificates)? {
+            return Err(SignatureError::InvalidSignature {
+                signer: self.signer.subject(),
+            }
+            .into());
+        }
+
+        for countersigner in &self.countersigners {
+            if !verify_countersignature(&self.signer, countersigner)? {
+                return Err(SignatureError::InvalidSignature {
+                    signer: countersigner.subject(),
+                }
+                .into());
+            }
+        }
+
+        for signer in self.signers() {
+            if let Some(ref timestamp) = signer.timestamp {
+                if !verify_timestamp(signer, timestamp)? {
+                    return Err(SignatureError::InvalidSignature {
+                        signer: subject(&timestamp.certificate),
+                    }
+                    .into());
+                }
+            }
+        }
+
+        let mut trusted = Vec::new();
+        for signer in self.signers() {
+            let roots = policy.roots(signer.signature_type);
+            if roots.is_empty() {
+                continue;
+            }
+
+            let time = match signer.timestamp {
+                Some(ref timestamp) => {
+                    verify_chain(
+                        &timestamp.certificate,
+                        &timestamp.certificates,
+                        roots,
+                        Some(timestamp.time),
+                    )?;
+                    Some(timestamp.time)
+                }
+                None => None,
+            };
+            verify_chain(&signer.certificate, &self.certificates, roots, time)?;
+
+            trusted.push(signer);
+        }
+
+        if trusted.is_empty() {
+            return Err(SignatureError::UntrustedCertificate {
+                signer: self.signer.subject(),
+                reason: String::from("no roots are trusted for its signature type"),
+            }
+            .into());
+        }
+
+        if !policy.fingerprints.is_empty() {
+            let fingerprints = trusted
+                .into_iter()
+                .map(Signer::fingerprint)
+                .collect::<Vec<_>>();
+
+            if !fingerprints
+                .iter()
+                .any(|fingerprint| policy.fingerprints.contains(fingerprint))
+            {
+                return Err(SignatureError::NotAllowed { fingerprints }.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Signature")
+            .field("hash_algorithm", &self.hash_algorithm)
+            .field("signer", &self.signer.subject())
+            .field(
+                "countersigners",
+                &self
+                    .countersigners
+                    .iter()
+                    .map(Signer::subject)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+fn subject(certificate: &X509) -> String {
+    certificate
+        .subject_name()
+        .entries()
+        .filter_map(|entry| {
+            let key = entry.object().nid().short_name().ok()?;
+            let value = String::from_utf8_lossy(entry.data().as_slice());
+            Some(format!("{}={}", key, value))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub(crate) fn fingerprint(certificate: &X509) -> String {
+    certificate
+        .digest(MessageDigest::sha256())
+        .map(|digest| digest.iter().map(|byte| format!("{:02X}", byte)).collect())
+        .unwrap_or_default()
+}
+
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(char::is_ascii_hexdigit)
+        .collect::<String>()
+        .to_uppercase()
+}
+
+struct SignedData<'a> {
+    content_type: String,
+    // The encapsulated content's OCTET STRING
+    content: Der<'a>,
+    certificates: Vec<(Der<'a>, X509)>,
+    signer_infos: Vec<Der<'a>>,
+}
+
+fn parse_signed_data<'a>(der: &'a [u8]) -> Result<SignedData<'a>, Error> {
+    let content_info = Der::parse(der)?.sequence()?;
+    if content_info.len() != 2 || content_info[0].oid()? != SIGNED_DATA {
+        bail!("The signature isn't PKCS #7 signed data");
+    }
+
+    let signed_data = content_info[1].explicit()?.sequence()?;
+    let mut fields = signed_data.iter().skip(1);
+
+    let _digest_algorithms = fields.next();
+    let content = fields
+        .next()
+        .ok_or_else(|| format_err!("The signature has no content"))?
+        .sequence()?;
+    let content_type = content
+        .first()
+        .ok_or_else(|| format_err!("The signature has no content"))?
+        .oid()?;
+    let content = content
+        .get(1)
+        .ok_or_else(|| format_err!("The signature has no content"))?
+        .explicit()?;
+
+    let mut certificates = Vec::new();
+    let mut signer_infos = None;
+    for field in fields {
+        match field.tag {
+            0xa0 => {
+                for certificate in field.children()? {
+                    certificates.push((certificate, X509::from_der(certificate.raw)?));
+                }
+            }
+            0x31 => signer_infos = Some(field.children()?),
+            _ => {}
+        }
+    }
+
+    Ok(SignedData {
+        content_type,
+        content,
+        certificates,
+        signer_infos: signer_infos.ok_or_else(|| format_err!("The signature has no signers"))?,
+    })
+}
+
+fn parse_timestamp(token: &Der) -> Result<Timestamp, Error> {
+    let SignedData {
+        content_type,
+        content,
+        certificates,
+        signer_infos,
+    } = parse_signed_data(token.raw)?;
+    if content_type != TST_INFO {
+        bail!("The timestamp has no timestamp info");
+    }
+
+    // The version, policy, message imprint, serial number and time, then optional fields
+    let info = Der::parse(content.contents)?.sequence()?;
+    if info.len() < 5 {
+        bail!("The timestamp has invalid timestamp info");
+    }
+
+    let message_imprint = info[2].sequence()?;
+    if message_imprint.len() != 2 {
+        bail!("The timestamp has an invalid message imprint");
+    }
+    let hash_algorithm = message_imprint[0]
+        .sequence()?
+        .first()
+        .ok_or_else(|| format_err!("The timestamp has no hash algorithm"))?
+        .oid()?;
+    let hash_algorithm = HashAlgorithm::from_oid(&hash_algorithm)
+        .ok_or_else(|| format_err!("{} is an unsupported hash algorithm", hash_algorithm))?;
+
+    let signer_info = signer_infos
+        .first()
+        .ok_or_else(|| format_err!("The timestamp has no signer"))?
+        .sequence()?;
+    let certificate = find_certificate(
+        signer_info
+            .get(1)
+            .ok_or_else(|| format_err!("The timestamp has an invalid signer"))?,
+        &certificates,
+    )?;
+
+    Ok(Timestamp {
+        der: token.raw.to_vec(),
+        time: info[4].generalized_time()?,
+        hash_algorithm,
+        message_imprint: message_imprint[1].contents.to_vec(),
+        certificate,
+        certificates: certificates
+            .into_iter()
+            .map(|(_, certificate)| certificate)
+            .collect(),
+    })
+}
+
+// `Version:1`, then `{hash algorithm oid}-Hash:{base64 hash}`, separated by blank lines
+fn parse_signed_content(content: &[u8]) -> Result<(HashAlgorithm, Vec<u8>), Error> {
+    let content = ::std::str::from_utf8(content)?;
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+
+    if lines.next() != Some("Version:1") {
+        bail!("The signed content has an unsupported version");
+    }
+
+    for line in lines {
+        let mut key_value = line.splitn(2, ':');
+        let key = key_value.next().unwrap_or_default();
+
+        if key.ends_with("-Hash") {
+            #[allow(deprecated)]
+            let algorithm = HashAlgorithm::from_oid(key.trim_right_matches("-Hash"))
+                .ok_or_else(|| format_err!("{} is an unsupported hash algorithm", key))?;
+            let hash = base64::decode(key_value.next().unwrap_or_default().trim())?;
+
+            return Ok((algorithm, hash));
+        }
+    }
+
+    bail!("The signed content has no hash")
+}
+
+fn parse_signer(
+    signer_info: &Der,
+    certificates: &[(Der, X509)],
+    countersigners: Option<&mut Vec<Signer>>,
+) -> Result<Signer, Error> {
+    let fields = signer_info.sequence()?;
+    if fields.len() < 5 {
+        bail!("The signature has an invalid signer");
+    }
+
+    let certificate = find_certificate(&fields[1], certificates)?;
+    let digest_algorithm = fields[2]
+        .sequence()?
+        .first()
+        .ok_or_else(|| format_err!("The signer has no digest algorithm"))?
+        .oid()?;
+    let digest_algorithm = HashAlgorithm::from_oid(&digest_algorithm)
+        .ok_or_else(|| format_err!("{} is an unsupported digest algorithm", digest_algorithm))?;
+
+    let mut signer = Signer {
+        signature_type: SignatureType::Unknown,
+        certificate,
+        v3_service_index_url: None,
+        package_owners: Vec::new(),
+        timestamp: None,
+        digest_algorithm,
+        signed_attributes: Vec::new(),
+        message_digest: Vec::new(),
+        signature: Vec::new(),
+    };
+
+    let mut rest = fields[3..].iter();
+    for field in &mut rest {
+        match field.tag {
+            // [0] IMPLICIT, but signed as a SET
+            0xa0 => {
+                signer.signed_attributes = field.raw.to_vec();
+                signer.signed_attributes[0] = 0x31;
+
+                for (oid, values) in attributes(field)? {
+                    let value = match values.first() {
+                        Some(value) => value,
+                        None => continue,
+                    };
+
+                    match &*oid {
+                        MESSAGE_DIGEST => signer.message_digest = value.contents.to_vec(),
+                        COMMITMENT_TYPE_INDICATION => {
+                            let commitment_type = value
+                                .sequence()?
+                                .first()
+                                .ok_or_else(|| format_err!("The signer has no commitment type"))?
+                                .oid()?;
+
+                            signer.signature_type = match &*commitment_type {
+                                PROOF_OF_ORIGIN => SignatureType::Author,
+                                PROOF_OF_RECEIPT => SignatureType::Repository,
+                                _ => SignatureType::Unknown,
+                            }
+                        }
+                        NUGET_V3_SERVICE_INDEX_URL => {
+                            signer.v3_service_index_url = Some(value.string()?)
+                        }
+                        NUGET_PACKAGE_OWNERS => {
+                            signer.package_owners = value
+                                .sequence()?
+                                .iter()
+                                .map(Der::string)
+                                .collect::<Result<_, _>>()?
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            // The signature algorithm
+            0x30 => {}
+            0x04 => {
+                signer.signature = field.contents.to_vec();
+                break;
+            }
+            _ => bail!("The signer has an unexpected field"),
+        }
+    }
+
+    // Unsigned attributes
+    if let Some(field) = rest.next().filter(|field| field.tag == 0xa1) {
+        let mut countersigners = countersigners;
+
+        for (oid, values) in attributes(field)? {
+            match (&*oid, countersigners.as_mut()) {
+                (TIMESTAMP_TOKEN, _) => {
+                    if let Some(token) = values.first() {
+                        signer.timestamp = Some(parse_timestamp(token)?);
+                    }
+                }
+                (COUNTERSIGNATURE, Some(countersigners)) => {
+                    for value in values {
+                        // Countersignatures aren't themselves countersigned
+                        countersigners.push(parse_signer(&value, certificates, None)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(signer)
+}
+
+// Finds the certificate matching an `IssuerAndSerialNumber`
+fn find_certificate(signer_identifier: &Der, certificates: &[(Der, X509)]) -> Result<X509, Error> {
+    if signer_identifier.tag != 0x30 {
+        bail!("The signer is identified by an unsupported subject key identifier");
+    }
+
+    let identifier = signer_identifier.sequence()?;
+    if identifier.len() != 2 {
+        bail!("The signer has an invalid issuer and serial number");
+    }
+
+    for (der, certificate) in certificates {
+        let tbs = der.sequence()?[0].sequence()?;
+        // Skip the explicit version
+        let tbs = if tbs[0].tag == 0xa0 {
+            &tbs[1..]
+        } else {
+            &tbs[..]
+        };
+
+        if tbs.len() > 2
+            && tbs[0].contents == identifier[1].contents
+            && tbs[2].raw == identifier[0].raw
+        {
+            return Ok(certificate.clone());
+        }
+    }
+
+    bail!("The signature doesn't include the signer's certificate")
+}
+
+fn attributes<'a>(attributes: &Der<'a>) -> Result<Vec<(String, Vec<Der<'a>>)>, Error> {
+    attributes
+        .children()?
+        .iter()
+        .map(|attribute| {
+            let attribute = attribute.sequence()?;
+            if attribute.len() != 2 {
+                bail!("The signature has an invalid attribute");
+            }
+
+            Ok((attribute[0].oid()?, attribute[1].children()?))
+        })
+        .collect()
+}
+
+// A countersignature signs the signature value of the signer it's on
+fn verify_countersignature(signer: &Signer, countersigner: &Signer) -> Result<bool, Error> {
+    let digest = countersigner.digest_algorithm.message_digest();
+
+    let mut hasher = Hasher::new(digest)?;
+    hasher.update(&signer.signature)?;
+    if *hasher.finish()? != *countersigner.message_digest {
+        return Ok(false);
+    }
+
+    let public_key = countersigner.certificate.public_key()?;
+    let mut verifier = Verifier::new(digest, &public_key)?;
+    verifier.update(&countersigner.signed_attributes)?;
+    Ok(verifier.verify(&countersigner.signature).unwrap_or(false))
+}
+
+// A timestamp signs the hash of the signature value of the signer it's on
+fn verify_timestamp(signer: &Signer, timestamp: &Timestamp) -> Result<bool, Error> {
+    let mut hasher = Hasher::new(timestamp.hash_algorithm.message_digest())?;
+    hasher.update(&signer.signature)?;
+    if *hasher.finish()? != *timestamp.message_imprint {
+        return Ok(false);
+    }
+
+    verify_signed_data(&timestamp.der, &timestamp.certificates)
+}
+
+fn verify_signed_data(der: &[u8], certificates: &[X509]) -> Result<bool, Error> {
+    let mut stack = Stack::new()?;
+    for certificate in certificates {
+        stack.push(certificate.clone())?;
+    }
+
+    let pkcs7 = Pkcs7::from_der(der)?;
+    let empty = X509StoreBuilder::new()?.build();
+    Ok(pkcs7
+        .verify(&stack, &empty, None, None, Pkcs7Flags::NOVERIFY)
+        .is_ok())
+}
+
+// Checks `certificate` chains to one of `roots` as of `time`, or now
+fn verify_chain(
+    certificate: &X509,
+    intermediates: &[X509],
+    roots: &[X509],
+    time: Option<DateTime<Utc>>,
+) -> Result<(), Error> {
+    let mut stack = Stack::new()?;
+    for intermediate in intermediates {
+        stack.push(intermediate.clone())?;
+    }
+
+    let mut store = X509StoreBuilder::new()?;
+    for root in roots {
+        store.add_cert(root.clone())?;
+    }
+    if let Some(time) = time {
+        let mut param = X509VerifyParam::new()?;
+        param.set_time(time.timestamp() as _);
+        store.set_param(&param)?;
+    }
+    let store = store.build();
+
+    let mut context = X509StoreContext::new()?;
+    let result = context.init(&store, certificate, &stack, |context| {
+        context
+            .verify_cert()
+            .map(|trusted| (trusted, context.error()))
+    })?;
+
+    match result {
+        (true, _) => Ok(()),
+        (false, error) => Err(SignatureError::UntrustedCertificate {
+            signer: subject(certificate),
+            reason: error.error_string().to_string(),
+        }
+        .into()),
+    }
+}
+
+/// Hashes a package as it was before it was signed, i.e. without its signature file.
+///
+/// The signature file must be the last file in the archive, so the package
+/// is its files before the signature file, then the central directory
+/// without the signature file's record, and an end of central directory
+/// record adjusted to match.
+pub fn package_hash<R: Read + Seek>(
+    mut package: R,
+    algorithm: HashAlgorithm,
+) -> Result<Vec<u8>, Error> {
+    const END_OF_CENTRAL_DIRECTORY: &[u8] = &[0x50, 0x4b, 0x05, 0x06];
+    const CENTRAL_DIRECTORY_HEADER: &[u8] = &[0x50, 0x4b, 0x01, 0x02];
+
+    // The end of central directory record is at least 22 bytes, plus a comment of up to 64KiB
+    let length = package.seek(SeekFrom::End(0))?;
+    let tail_length = length.min(22 + 0xffff);
+    package.seek(SeekFrom::Start(length - tail_length))?;
+    let mut tail = vec![0; tail_length as usize];
+    package.read_exact(&mut tail)?;
+
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&index| tail[index..].starts_with(END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(|| format_err!("The package isn't a zip archive"))?;
+    let mut end_record = tail[end..].to_vec();
+
+    let entries = u16_at(&end_record, 10);
+    let central_directory_size = u32_at(&end_record, 12);
+    let central_directory_offset = u32_at(&end_record, 16);
+    if entries == 0xffff
+        || central_directory_size == 0xffff_ffff
+        || central_directory_offset == 0xffff_ffff
+    {
+        bail!("Signatures of Zip64 packages aren't supported");
+    }
+
+    package.seek(SeekFrom::Start(u64::from(central_directory_offset)))?;
+    let mut central_directory = vec![0; central_directory_size as usize];
+    package.read_exact(&mut central_directory)?;
+
+    let mut records = Vec::new();
+    let mut signature = None;
+    let mut offset = 0;
+    while offset < central_directory.len() {
+        if !central_directory[offset..].starts_with(CENTRAL_DIRECTORY_HEADER)
+            || central_directory.len() < offset + 46
+        {
+            bail!("The package has an invalid central directory");
+        }
+
+        let header = &central_directory[offset..];
+        let name_length = u16_at(header, 28) as usize;
+        let record_length =
+            46 + name_length + u16_at(header, 30) as usize + u16_at(header, 32) as usize;
+        let record = header
+            .get(..record_length)
+            .ok_or_else(|| format_err!("The package has an invalid central directory"))?;
+        let local_header_offset = u32_at(record, 42);
+
+        if &record[46..46 + name_length] == SIGNATURE_FILE.as_bytes() {
+            signature = Some((local_header_offset, record_length as u32));
+        } else {
+            records.push((local_header_offset, record));
+        }
+
+        offset += record_length;
+    }
+
+    let (signature_offset, signature_record_length) =
+        signature.ok_or_else(|| format_err!("The package isn't signed"))?;
+    if records
+        .iter()
+        .any(|&(local_header_offset, _)| local_header_offset > signature_offset)
+    {
+        bail!("The signature isn't the last file in the package");
+    }
+
+    let mut hasher = Hasher::new(algorithm.message_digest())?;
+
+    package.seek(SeekFrom::Start(0))?;
+    let mut files = package.take(u64::from(signature_offset));
+    let mut chunk = [0; 64 * 1024];
+    loop {
+        let read = files.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&chunk[..read])?;
+    }
+
+    for (_, record) in records {
+        hasher.update(record)?;
+    }
+
+    let entries = le_u16(entries - 1);
+    end_record[8..10].copy_from_slice(&entries);
+    end_record[10..12].copy_from_slice(&entries);
+    end_record[12..16].copy_from_slice(&le_u32(central_directory_size - signature_record_length));
+    end_record[16..20].copy_from_slice(&le_u32(signature_offset));
+    hasher.update(&end_record)?;
+
+    Ok(hasher.finish()?.to_vec())
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from(bytes[offset]) | u16::from(bytes[offset + 1]) << 8
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from(u16_at(bytes, offset)) | u32::from(u16_at(bytes, offset + 2)) << 16
+}
+
+fn le_u16(value: u16) -> [u8; 2] {
+    [value as u8, (value >> 8) as u8]
+}
+
+fn le_u32(value: u32) -> [u8; 4] {
+    [
+        value as u8,
+        (value >> 8) as u8,
+        (value >> 16) as u8,
+        (value >> 24) as u8,
+    ]
+}
+
+// Just enough DER to find the parts of a signature that OpenSSL doesn't expose
+#[derive(Clone, Copy)]
+struct Der<'a> {
+    tag: u8,
+    contents: &'a [u8],
+    // The tag, length and contents
+    raw: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn parse(input: &'a [u8]) -> Result<Der<'a>, Error> {
+        let (der, rest) = Der::next(input)?;
+        if !rest.is_empty() {
+            bail!("The signature has trailing data");
+        }
+        Ok(der)
+    }
+
+    fn next(input: &'a [u8]) -> Result<(Der<'a>, &'a [u8]), Error> {
+        let invalid = || format_err!("The signature isn't valid DER");
+
+        let tag = *input.first().ok_or_else(invalid)?;
+        if tag & 0x1f == 0x1f {
+            bail!("The signature has an unsupported multi-byte tag");
+        }
+
+        let first = *input.get(1).ok_or_else(invalid)?;
+        let (length, header) = match first {
+            0x00..=0x7f => (first as usize, 2),
+            0x81..=0x84 => {
+                let count = (first & 0x7f) as usize;
+                let bytes = input.get(2..2 + count).ok_or_else(invalid)?;
+                let length = bytes
+                    .iter()
+                    .fold(0, |length, &byte| length << 8 | byte as usize);
+                (length, 2 + count)
+            }
+            _ => bail!("The signature has an unsupported length"),
+        };
+
+        let end = header.checked_add(length).ok_or_else(invalid)?;
+        let raw = input.get(..end).ok_or_else(invalid)?;
+
+        Ok((
+            Der {
+                tag,
+                contents: &raw[header..],
+                raw,
+            },
+            &input[end..],
+        ))
+    }
+
+    fn children(&self) -> Result<Vec<Der<'a>>, Error> {
+        let mut children = Vec::new();
+        let mut rest = self.contents;
+
+        while !rest.is_empty() {
+            let (child, next) = Der::next(rest)?;
+            children.push(child);
+            rest = next;
+        }
+
+        Ok(children)
+    }
+
+    fn sequence(&self) -> Result<Vec<Der<'a>>, Error> {
+        if self.tag != 0x30 {
+            bail!("The signature has an unexpected value where a sequence was expected");
+        }
+        self.children()
+    }
+
+    // The value in an `[n] EXPLICIT` tag
+    fn explicit(&self) -> Result<Der<'a>, Error> {
+        Der::parse(self.contents)
+    }
+
+    fn oid(&self) -> Result<String, Error> {
+        if self.tag != 0x06 || self.contents.is_empty() {
+            bail!("The signature has an unexpected value where an object identifier was expected");
+        }
+
+        let first = self.contents[0];
+        let mut arcs = vec![u64::from(first / 40), u64::from(first % 40)];
+        let mut arc = 0u64;
+        for &byte in &self.contents[1..] {
+            arc = arc << 7 | u64::from(byte & 0x7f);
+            if byte & 0x80 == 0 {
+                arcs.push(arc);
+                arc = 0;
+            }
+        }
+
+        Ok(arcs
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join("."))
+    }
+
+    // A GeneralizedTime, to the second
+    fn generalized_time(&self) -> Result<DateTime<Utc>, Error> {
+        let time = ::std::str::from_utf8(self.contents)?;
+        let seconds = time
+            .get(..14)
+            .filter(|_| self.tag == 0x18 && time.ends_with('Z'))
+            .ok_or_else(|| format_err!("The signature has an invalid time"))?;
+
+        Ok(Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(seconds, "%Y%m%d%H%M%S")?))
+    }
+
+    // UTF8String, PrintableString or IA5String
+    fn string(&self) -> Result<String, Error> {
+        match self.tag {
+            0x0c | 0x13 | 0x16 => Ok(String::from_utf8(self.contents.to_vec())?),
+            _ => bail!("The signature has an unexpected value where a string was expected"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use nupkg::builder::PackageBuilder;
+    use nupkg::nuspec::{Metadata, Nuspec};
+    use nupkg::Nupkg;
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Rsa;
+    use openssl::sign::Signer as OpenSslSigner;
+    use openssl::x509::extension::{BasicConstraints, ExtendedKeyUsage};
+    use openssl::x509::{X509Builder, X509NameBuilder};
+    use std::fs::File;
+    use std::io::{Cursor, Write};
+    use tempdir::TempDir;
+
+    const SHA256: &str = "2.16.840.1.101.3.4.2.1";
+    const DATA: &str = "1.2.840.113549.1.7.1";
+    const CONTENT_TYPE: &str = "1.2.840.113549.1.9.3";
+
+    // DER encoding, for building signatures the way NuGet does
+
+    fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut der = vec![tag];
+        match contents.len() {
+            length if length < 0x80 => der.push(length as u8),
+            length if length < 0x100 => der.extend(&[0x81, length as u8]),
+            length => der.extend(&[0x82, (length >> 8) as u8, length as u8]),
+        }
+        der.extend(contents);
+        der
+    }
+
+    fn constructed(tag: u8, children: &[Vec<u8>]) -> Vec<u8> {
+        tlv(tag, &children.concat())
+    }
+
+    fn sequence(children: &[Vec<u8>]) -> Vec<u8> {
+        constructed(0x30, children)
+    }
+
+    // DER sorts the members of a SET OF
+    fn set(children: &[Vec<u8>]) -> Vec<u8> {
+        let mut children = children.to_vec();
+        children.sort();
+        constructed(0x31, &children)
+    }
+
+    fn oid(oid: &str) -> Vec<u8> {
+        let arcs = oid
+            .split('.')
+            .map(|arc| arc.parse::<u64>().unwrap())
+            .collect::<Vec<_>>();
+
+        let mut contents = vec![(arcs[0] * 40 + arcs[1]) as u8];
+        for &arc in &arcs[2..] {
+            let mut bytes = vec![(arc & 0x7f) as u8];
+            let mut rest = arc >> 7;
+            while rest > 0 {
+                bytes.push((rest & 0x7f) as u8 | 0x80);
+                rest >>= 7;
+            }
+            bytes.reverse();
+            contents.extend(bytes);
+        }
+
+        tlv(0x06, &contents)
+    }
+
+    fn algorithm(algorithm: &str) -> Vec<u8> {
+        sequence(&[oid(algorithm), vec![0x05, 0x00]])
+    }
+
+    fn attribute(attribute: &str, value: Vec<u8>) -> Vec<u8> {
+        sequence(&[oid(attribute), set(&[value])])
+    }
+
+    fn key() -> PKey<Private> {
+        PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap()
+    }
+
+    fn certificate(
+        subject: &str,
+        serial: u32,
+        key: &PKey<Private>,
+        issuer: Option<(&X509, &PKey<Private>)>,
+        valid_days: (i64, i64),
+    ) -> X509 {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", subject).unwrap();
+        let name = name.build();
+
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder
+            .set_serial_number(&BigNum::from_u32(serial).unwrap().to_asn1_integer().unwrap())
+            .unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder
+            .set_issuer_name(issuer.map_or(&name, |(issuer, _)| issuer.subject_name()))
+            .unwrap();
+        builder.set_pubkey(key).unwrap();
+        let now = Utc::now().timestamp();
+        let days_from_now = |days: i64| Asn1Time::from_unix((now + days * 86_400) as _).unwrap();
+        builder
+            .set_not_before(&days_from_now(valid_days.0))
+            .unwrap();
+        builder.set_not_after(&days_from_now(valid_days.1)).unwrap();
+        match issuer {
+            Some(_) => builder
+                .append_extension(ExtendedKeyUsage::new().code_signing().build().unwrap())
+                .unwrap(),
+            None => builder
+                .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+                .unwrap(),
+        }
+        builder
+            .sign(issuer.map_or(key, |(_, key)| key), MessageDigest::sha256())
+            .unwrap();
+
+        builder.build()
+    }
+
+    fn signer_info(
+        certificate: &X509,
+        key: &PKey<Private>,
+        mut signed_attributes: Vec<Vec<u8>>,
+        signed: &[u8],
+        unsigned_attributes: Option<Vec<u8>>,
+    ) -> Vec<u8> {
+        let der = certificate.to_der().unwrap();
+        let tbs = Der::parse(&der).unwrap().sequence().unwrap()[0]
+            .sequence()
+            .unwrap();
+        let issuer_and_serial = sequence(&[tbs[3].raw.to_vec(), tbs[1].raw.to_vec()]);
+
+        let mut hasher = Hasher::new(MessageDigest::sha256()).unwrap();
+        hasher.update(signed).unwrap();
+        signed_attributes.push(attribute(
+            MESSAGE_DIGEST,
+            tlv(0x04, &hasher.finish().unwrap()),
+        ));
+        let signed_attributes = set(&signed_attributes);
+
+        let mut signer = OpenSslSigner::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(&signed_attributes).unwrap();
+        let signature = signer.sign_to_vec().unwrap();
+
+        let mut fields = vec![
+            tlv(0x02, &[1]),
+            issuer_and_serial,
+            algorithm(SHA256),
+            tlv(0xa0, Der::parse(&signed_attributes).unwrap().contents),
+            algorithm("1.2.840.113549.1.1.1"),
+            tlv(0x04, &signature),
+        ];
+        if let Some(unsigned_attributes) = unsigned_attributes {
+            fields.push(tlv(0xa1, &unsigned_attributes));
+        }
+
+        sequence(&fields)
+    }
+
+    fn commitment_type(commitment_type: &str) -> Vec<u8> {
+        attribute(
+            COMMITMENT_TYPE_INDICATION,
+            sequence(&[oid(commitment_type)]),
+        )
+    }
+
+    struct Signers {
+        root: X509,
+        root_key: PKey<Private>,
+        author: (X509, PKey<Private>),
+        repository: (X509, PKey<Private>),
+        timestamper: (X509, PKey<Private>),
+    }
+
+    // Certificates valid from 90 days ago until 30 days from now
+    fn signers() -> Signers {
+        let valid = (-90, 30);
+        let root_key = key();
+        let root = certificate("Test Root", 1, &root_key, None, valid);
+        let issuer = Some((&root, &root_key));
+
+        let author_key = key();
+        let author = certificate("Fint Author", 2, &author_key, issuer, valid);
+        let repository_key = key();
+        let repository = certificate("Fint Feed", 3, &repository_key, issuer, valid);
+        let timestamper_key = key();
+        let timestamper = certificate("Fint Timestamps", 4, &timestamper_key, issuer, valid);
+
+        Signers {
+            author: (author, author_key),
+            repository: (repository, repository_key),
+            timestamper: (timestamper, timestamper_key),
+            root,
+            root_key,
+        }
+    }
+
+    // A timestamp token over a signature value, as a timestamp authority would make it
+    fn timestamp_token(signers: &Signers, signature: &[u8], time: DateTime<Utc>) -> Vec<u8> {
+        let mut hasher = Hasher::new(MessageDigest::sha256()).unwrap();
+        hasher.update(signature).unwrap();
+        let info = sequence(&[
+            tlv(0x02, &[1]),
+            oid("1.2.3.4"),
+            sequence(&[algorithm(SHA256), tlv(0x04, &hasher.finish().unwrap())]),
+            tlv(0x02, &[1]),
+            tlv(0x18, time.format("%Y%m%d%H%M%SZ").to_string().as_bytes()),
+        ]);
+
+        let (timestamper, timestamper_key) = &signers.timestamper;
+        let signer = signer_info(
+            timestamper,
+            timestamper_key,
+            vec![attribute(CONTENT_TYPE, oid(TST_INFO))],
+            &info,
+            None,
+        );
+
+        sequence(&[
+            oid(SIGNED_DATA),
+            constructed(
+                0xa0,
+                &[sequence(&[
+                    tlv(0x02, &[3]),
+                    set(&[algorithm(SHA256)]),
+                    sequence(&[oid(TST_INFO), constructed(0xa0, &[tlv(0x04, &info)])]),
+                    constructed(0xa0, &[timestamper.to_der().unwrap()]),
+                    set(&[signer]),
+                ])],
+            ),
+        ])
+    }
+
+    // An author signature countersigned by a repository, and optionally timestamped, over `package`
+    fn sign(signers: &Signers, package: &[u8], timestamp: Option<DateTime<Utc>>) -> Vec<u8> {
+        let mut hasher = Hasher::new(MessageDigest::sha256()).unwrap();
+        hasher.update(package).unwrap();
+        let content = format!(
+            "Version:1\r\n\r\n{}-Hash:{}\r\n\r\n",
+            SHA256,
+            base64::encode(&hasher.finish().unwrap())
+        );
+
+        // The countersignature needs the author's signature value, so sign without it first
+        let author_attributes = || {
+            vec![
+                attribute(CONTENT_TYPE, oid(DATA)),
+                commitment_type(PROOF_OF_ORIGIN),
+            ]
+        };
+        let (author, author_key) = &signers.author;
+        let author_signer = signer_info(
+            author,
+            author_key,
+            author_attributes(),
+            content.as_bytes(),
+            None,
+        );
+        let author_signature = {
+            let fields = Der::parse(&author_signer).unwrap().sequence().unwrap();
+            fields[5].contents.to_vec()
+        };
+
+        let (repository, repository_key) = &signers.repository;
+        let countersignature = signer_info(
+            repository,
+            repository_key,
+            vec![
+                commitment_type(PROOF_OF_RECEIPT),
+                attribute(
+                    NUGET_V3_SERVICE_INDEX_URL,
+                    tlv(0x16, b"https://example.org/v3/index.json"),
+                ),
+                attribute(
+                    NUGET_PACKAGE_OWNERS,
+                    sequence(&[tlv(0x0c, b"fint"), tlv(0x0c, b"sse")]),
+                ),
+            ],
+            &author_signature,
+            None,
+        );
+
+        let mut unsigned_attributes = attribute(COUNTERSIGNATURE, countersignature);
+        if let Some(time) = timestamp {
+            unsigned_attributes.extend(attribute(
+                TIMESTAMP_TOKEN,
+                timestamp_token(signers, &author_signature, time),
+            ));
+        }
+
+        // RSA PKCS #1 v1.5 signatures are deterministic, so this is the same signature
+        let author_signer = signer_info(
+            author,
+            author_key,
+            author_attributes(),
+            content.as_bytes(),
+            Some(unsigned_attributes),
+        );
+
+        sequence(&[
+            oid(SIGNED_DATA),
+            constructed(
+                0xa0,
+                &[sequence(&[
+                    tlv(0x02, &[1]),
+                    set(&[algorithm(SHA256)]),
+                    sequence(&[
+                        oid(DATA),
+                        constructed(0xa0, &[tlv(0x04, content.as_bytes())]),
+                    ]),
+                    constructed(
+                        0xa0,
+                        &[author.to_der().unwrap(), repository.to_der().unwrap()],
+                    ),
+                    set(&[author_signer]),
+                ])],
+            ),
+        ])
+    }
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        !bytes.iter().fold(!0, |crc, &byte| {
+            (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+                if crc & 1 == 1 {
+                    crc >> 1 ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                }
+            })
+        })
+    }
+
+    // Adds the signature as the last file, as NuGet does
+    fn add_signature(package: &[u8], signature: &[u8]) -> Vec<u8> {
+        let end = package.len() - 22;
+        let entries = u16_at(package, end + 10);
+        let central_directory_size = u32_at(package, end + 12) as usize;
+        let central_directory_offset = u32_at(package, end + 16) as usize;
+
+        let name = SIGNATURE_FILE.as_bytes();
+        let crc = le_u32(crc32(signature));
+        let size = le_u32(signature.len() as u32);
+
+        let mut local = vec![0x50, 0x4b, 0x03, 0x04, 20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0];
+        local.extend(&crc);
+        local.extend(&size);
+        local.extend(&size);
+        local.extend(&le_u16(name.len() as u16));
+        local.extend(&[0, 0]);
+        local.extend(name);
+        local.extend(signature);
+
+        let mut record = vec![
+            0x50, 0x4b, 0x01, 0x02, 20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0,
+        ];
+        record.extend(&crc);
+        record.extend(&size);
+        record.extend(&size);
+        record.extend(&le_u16(name.len() as u16));
+        record.extend(&[0; 12]);
+        record.extend(&le_u32(central_directory_offset as u32));
+        record.extend(name);
+
+        let mut signed = package[..central_directory_offset].to_vec();
+        signed.extend(&local);
+        signed.extend(&package[central_directory_offset..end]);
+        signed.extend(&record);
+
+        let mut end_record = package[end..].to_vec();
+        end_record[8..10].copy_from_slice(&le_u16(entries + 1));
+        end_record[10..12].copy_from_slice(&le_u16(entries + 1));
+        end_record[12..16].copy_from_slice(&le_u32((central_directory_size + record.len()) as u32));
+        end_record[16..20]
+            .copy_from_slice(&le_u32((central_directory_offset + local.len()) as u32));
+        signed.extend(&end_record);
+
+        signed
+    }
+
+    fn package() -> Vec<u8> {
+        let dir = TempDir::new("nougat").unwrap();
+        let dll = dir.path().join("Fint.Sse.dll");
+        File::create(&dll).unwrap().write_all(b"dll").unwrap();
+
+        let nuspec = Nuspec {
+            metadata: Metadata {
+                id: String::from("Fint.Sse"),
+                version: Some("1.0.0".parse().unwrap()),
+                authors: String::from("FINTprosjektet"),
+                description: String::from("Server-sent events"),
+                ..Metadata::default()
+            },
+        };
+
+        PackageBuilder::new(nuspec)
+            .file(&dll, "lib/net45/Fint.Sse.dll")
+            .build()
+            .unwrap()
+    }
+
+    fn signature_error(result: Result<(), Error>) -> SignatureError {
+        result.unwrap_err().downcast::<SignatureError>().unwrap()
+    }
+
+    #[test]
+    fn signed_package() {
+        let signers = signers();
+        let unsigned = package();
+        let signed = add_signature(&unsigned, &sign(&signers, &unsigned, None));
+
+        let mut nupkg = Nupkg::from_bytes(signed.clone()).unwrap();
+        assert!(nupkg.is_signed());
+        assert_eq!(nupkg.files(), ["lib/net45/Fint.Sse.dll"]);
+
+        let signature = nupkg.signature().unwrap().unwrap();
+        assert_eq!(signature.hash_algorithm(), HashAlgorithm::Sha256);
+        assert_eq!(
+            signature.content_hash(),
+            &*package_hash(Cursor::new(&signed), HashAlgorithm::Sha256).unwrap()
+        );
+
+        let author = signature.author().unwrap();
+        assert_eq!(author.subject(), "CN=Fint Author");
+        assert!(!author.is_timestamped());
+        assert_eq!(signature.signer().signature_type(), SignatureType::Author);
+
+        let repository = signature.repository().unwrap();
+        assert_eq!(repository.subject(), "CN=Fint Feed");
+        assert_eq!(
+            repository.v3_service_index_url(),
+            Some("https://example.org/v3/index.json")
+        );
+        assert_eq!(repository.package_owners(), ["fint", "sse"]);
+        assert_eq!(signature.countersigners().len(), 1);
+
+        let trusted = || TrustPolicy::new().trust(signers.root.clone());
+        signature.verify(Cursor::new(&signed), &trusted()).unwrap();
+
+        // Either signer can be allowed, with any fingerprint formatting
+        let fingerprint = repository.fingerprint();
+        let formatted = fingerprint
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| String::from_utf8_lossy(pair).to_lowercase())
+            .collect::<Vec<_>>()
+            .join(":");
+        signature
+            .verify(Cursor::new(&signed), &trusted().allow(&formatted))
+            .unwrap();
+        signature
+            .verify(
+                Cursor::new(&signed),
+                &trusted().allow(&author.fingerprint()),
+            )
+            .unwrap();
+
+        match signature_error(signature.verify(Cursor::new(&signed), &trusted().allow("AB01"))) {
+            SignatureError::NotAllowed { fingerprints } => {
+                assert_eq!(fingerprints, vec![author.fingerprint(), fingerprint])
+            }
+            other => panic!("unexpected error {}", other),
+        }
+
+        match signature_error(signature.verify(Cursor::new(&signed), &TrustPolicy::new())) {
+            SignatureError::UntrustedCertificate { signer, .. } => {
+                assert_eq!(signer, "CN=Fint Author")
+            }
+            other => panic!("unexpected error {}", other),
+        }
+
+        // Each signer is only checked against the roots trusted for its type
+        let other_root = certificate("Other Root", 5, &key(), None, (-90, 30));
+        signature
+            .verify(
+                Cursor::new(&signed),
+                &TrustPolicy::new().trust_repository(signers.root.clone()),
+            )
+            .unwrap();
+        signature
+            .verify(
+                Cursor::new(&signed),
+                &TrustPolicy::new().trust_author(signers.root.clone()),
+            )
+            .unwrap();
+        match signature_error(
+            signature.verify(
+                Cursor::new(&signed),
+                &TrustPolicy::new()
+                    .trust_author(signers.root.clone())
+                    .trust_repository(other_root),
+            ),
+        ) {
+            SignatureError::UntrustedCertificate { signer, .. } => {
+                assert_eq!(signer, "CN=Fint Feed")
+            }
+            other => panic!("unexpected error {}", other),
+        }
+    }
+
+    #[test]
+    fn timestamped_package() {
+        let signers = signers();
+        let unsigned = package();
+        let trusted = TrustPolicy::new().trust(signers.root.clone());
+
+        // The author's certificate has expired since the package was signed
+        let author_key = key();
+        let author = certificate(
+            "Fint Author",
+            6,
+            &author_key,
+            Some((&signers.root, &signers.root_key)),
+            (-60, -30),
+        );
+        let signers = Signers {
+            author: (author, author_key),
+            ..signers
+        };
+
+        let time = Utc::now() - Duration::days(45);
+        let signed = add_signature(&unsigned, &sign(&signers, &unsigned, Some(time)));
+        let signature = Nupkg::from_bytes(signed.clone())
+            .unwrap()
+            .signature()
+            .unwrap()
+            .unwrap();
+        assert!(signature.signer().is_timestamped());
+        assert_eq!(
+            signature.signer().timestamp().map(|time| time.timestamp()),
+            Some(time.timestamp())
+        );
+        signature.verify(Cursor::new(&signed), &trusted).unwrap();
+
+        // Without a timestamp, or with one after it expired, the certificate isn't trusted
+        for time in &[None, Some(Utc::now() - Duration::days(10))] {
+            let der = sign(&signers, &unsigned, *time);
+            let signed = add_signature(&unsigned, &der);
+            match signature_error(
+                Signature::from_der(&der)
+                    .unwrap()
+                    .verify(Cursor::new(&signed), &trusted),
+            ) {
+                SignatureError::UntrustedCertificate { signer, .. } => {
+                    assert_eq!(signer, "CN=Fint Author")
+                }
+                other => panic!("unexpected error {}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn tampered_package() {
+        let signers = signers();
+        let unsigned = package();
+        let signed = add_signature(&unsigned, &sign(&signers, &unsigned, None));
+        let signature = Nupkg::from_bytes(signed.clone())
+            .unwrap()
+            .signature()
+            .unwrap()
+            .unwrap();
+
+        let mut tampered = signed.clone();
+        tampered[40] ^= 0xff;
+
+        match signature_error(signature.verify(
+            Cursor::new(&tampered),
+            &TrustPolicy::new().trust(signers.root.clone()),
+        )) {
+            SignatureError::ContentHashMismatch { .. } => {}
+            other => panic!("unexpected error {}", other),
+        }
+
+        // Corrupt the author's signature value
+        let mut der = sign(&signers, &unsigned, None);
+        let offset = {
+            let content_info = Der::parse(&der).unwrap().sequence().unwrap();
+            let signed_data = content_info[1].explicit().unwrap().sequence().unwrap();
+            let signer_info = signed_data[4].children().unwrap()[0].sequence().unwrap();
+            signer_info[5].contents.as_ptr() as usize - der.as_ptr() as usize
+        };
+        der[offset] ^= 0xff;
+
+        let signed = add_signature(&unsigned, &der);
+        match signature_error(
+            Signature::from_der(&der)
+                .unwrap()
+                .verify(Cursor::new(&signed), &TrustPolicy::new()),
+        ) {
+            SignatureError::InvalidSignature { signer } => assert_eq!(signer, "CN=Fint Author"),
+            other => panic!("unexpected error {}", other),
+        }
+
+        // Unsigned packages have no signature to check against
+        assert!(Nupkg::from_bytes(unsigned.clone())
+            .unwrap()
+            .signature()
+            .unwrap()
+            .is_none());
+        assert!(package_hash(Cursor::new(&unsigned), HashAlgorithm::Sha256).is_err());
+    }
+}
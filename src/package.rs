@@ -1,11 +1,21 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use client::Client;
 use failure::Error;
+use framework::NuGetFramework;
+use integrity::{IntegrityError, Verifier};
+use reqwest::header::CONTENT_RANGE;
+use reqwest::StatusCode;
 use serde::de::Error as DeserializeError;
 use serde::{Deserialize, Deserializer};
+use std::ffi::OsString;
 use std::fmt;
-use std::io::Read;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::slice;
 use std::str::FromStr;
 use url::Url;
+use version::{NuGetVersion, VersionRange};
 
 #[derive(Debug, Deserialize)]
 pub struct Package {
@@ -17,15 +27,19 @@ pub struct Package {
     pub title: String,
     pub summary: Option<String>,
     pub content: PackageContent,
-    #[serde(rename = "updated")]
-    pub updated_at: String,
+    #[serde(rename = "updated", deserialize_with = "parse_date")]
+    pub updated_at: DateTime<Utc>,
     pub author: Author,
     pub properties: PackageProperties,
 }
 
 impl PartialEq for Package {
     fn eq(&self, other: &Self) -> bool {
-        self.id() == other.id() && self.version() == other.version()
+        self.id().eq_ignore_ascii_case(other.id())
+            && match (self.version(), other.version()) {
+                (Some(version), Some(other_version)) => version == other_version,
+                _ => self.raw_version() == other.raw_version(),
+            }
     }
 }
 
@@ -45,70 +59,441 @@ pub struct PackageContent {
 
 // Struct fields based on https://joelverhagen.github.io/NuGetUndocs/#package-entity
 #[serde(rename_all = "PascalCase")]
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Default, Deserialize, PartialEq)]
 pub struct PackageProperties {
     // Common fields
-    copyright: String,
-    #[serde(deserialize_with = "parse_dependencies")]
-    dependencies: Vec<PackageDependency>,
-    description: String,
-    download_count: i32,
-    is_absolute_latest_version: bool,
-    is_latest_version: bool,
-    is_prerelease: bool,
-    package_size: u64,
-    project_url: String, // Url
-    release_notes: String,
-    require_license_acceptance: bool,
-    tags: String,
-    title: String,
-    version: String,
+    pub(crate) copyright: String,
+    pub(crate) dependencies: ParsedProperty<DependencyGroups>,
+    pub(crate) description: String,
+    pub(crate) download_count: i32,
+    pub(crate) is_absolute_latest_version: bool,
+    pub(crate) is_latest_version: bool,
+    pub(crate) is_prerelease: bool,
+    pub(crate) package_size: u64,
+    #[serde(default)]
+    pub(crate) project_url: UrlProperty,
+    pub(crate) release_notes: String,
+    pub(crate) require_license_acceptance: bool,
+    pub(crate) tags: String,
+    pub(crate) title: String,
+    pub(crate) version: ParsedProperty<NuGetVersion>,
 
     // Vendor specific fields
-    authors: Option<String>,
-    #[serde(rename = "Created")]
-    created_at: Option<String>, // Date
-    development_dependency: Option<bool>,
-    gallery_details_url: Option<String>, // Option<Url>
-    icon_url: Option<String>,            // Url
-    id: Option<String>,
-    #[serde(rename = "LastEdited")]
-    last_edited_at: Option<String>, // Option<Date>
-    #[serde(rename = "LastUpdated")]
-    last_updated_at: Option<String>, // Option<Date>
-    license_url: Option<String>, // Url
-    license_names: Option<String>,
-    license_report_url: Option<String>, // Option<Url>
+    pub(crate) authors: Option<String>,
+    #[serde(rename = "Created", default, deserialize_with = "parse_optional_date")]
+    pub(crate) created_at: Option<DateTime<Utc>>,
+    pub(crate) development_dependency: Option<bool>,
+    #[serde(default)]
+    pub(crate) gallery_details_url: UrlProperty,
+    #[serde(default)]
+    pub(crate) icon_url: UrlProperty,
+    pub(crate) id: Option<String>,
+    #[serde(
+        rename = "LastEdited",
+        default,
+        deserialize_with = "parse_optional_date"
+    )]
+    pub(crate) last_edited_at: Option<DateTime<Utc>>,
+    #[serde(
+        rename = "LastUpdated",
+        default,
+        deserialize_with = "parse_optional_date"
+    )]
+    pub(crate) last_updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) license_url: UrlProperty,
+    pub(crate) license_names: Option<String>,
+    #[serde(default)]
+    pub(crate) license_report_url: UrlProperty,
     #[serde(rename = "Listed")]
-    is_listed: Option<bool>,
-    language: Option<String>,
+    pub(crate) is_listed: Option<bool>,
+    pub(crate) language: Option<String>,
     #[serde(rename = "MinClientVersion")]
-    minimum_client_version: Option<String>,
-    normalized_version: Option<String>,
-    owners: Option<String>,
-    package_hash: Option<String>,
-    package_hash_algorithm: Option<String>,
-    #[serde(rename = "Published")]
-    published_at: Option<String>, // Date
-    report_abuse_url: Option<String>, // Option<Url>
-    summary: Option<String>,          // String
-    version_download_count: i32,
+    pub(crate) minimum_client_version: Option<String>,
+    #[serde(default, deserialize_with = "parse_optional_version")]
+    pub(crate) normalized_version: Option<NuGetVersion>,
+    pub(crate) owners: Option<String>,
+    pub(crate) package_hash: Option<String>,
+    pub(crate) package_hash_algorithm: Option<String>,
+    #[serde(rename = "Published", default, deserialize_with = "parse_published")]
+    pub(crate) published_at: Option<Published>,
+    #[serde(default)]
+    pub(crate) report_abuse_url: UrlProperty,
+    pub(crate) summary: Option<String>, // String
+    pub(crate) version_download_count: i32,
 
     // ProGet specific fields
-    is_local_package: Option<bool>,
+    pub(crate) is_local_package: Option<bool>,
     #[serde(rename = "IsProGetHosted")]
-    is_proget_hosted: Option<bool>,
-    is_cached: Option<bool>,
+    pub(crate) is_proget_hosted: Option<bool>,
+    pub(crate) is_cached: Option<bool>,
 
-    has_symbols: Option<bool>,
-    has_source: Option<bool>,
+    pub(crate) has_symbols: Option<bool>,
+    pub(crate) has_source: Option<bool>,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+impl PackageProperties {
+    pub fn copyright(&self) -> &str {
+        &self.copyright
+    }
+
+    /// The dependencies, or `None` if the feed sent ones that don't parse.
+    pub fn dependencies(&self) -> Option<&DependencyGroups> {
+        self.dependencies.value()
+    }
+
+    /// The dependencies as sent, in the V2 `id:version:framework|...` format.
+    pub fn raw_dependencies(&self) -> &str {
+        self.dependencies.raw()
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Downloads across every version of the package.
+    pub fn download_count(&self) -> i32 {
+        self.download_count
+    }
+
+    pub fn is_absolute_latest_version(&self) -> bool {
+        self.is_absolute_latest_version
+    }
+
+    pub fn is_latest_version(&self) -> bool {
+        self.is_latest_version
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        self.is_prerelease
+    }
+
+    pub fn package_size(&self) -> u64 {
+        self.package_size
+    }
+
+    pub fn project_url(&self) -> &UrlProperty {
+        &self.project_url
+    }
+
+    pub fn release_notes(&self) -> &str {
+        &self.release_notes
+    }
+
+    pub fn require_license_acceptance(&self) -> bool {
+        self.require_license_acceptance
+    }
+
+    /// The tags as sent, see `tags` for the individual tags.
+    pub fn raw_tags(&self) -> &str {
+        &self.tags
+    }
+
+    /// The tags, which feeds separate with spaces or commas.
+    pub fn tags(&self) -> Vec<&str> {
+        split_tags(&self.tags)
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The version, or `None` if the feed sent one that doesn't parse.
+    pub fn version(&self) -> Option<&NuGetVersion> {
+        self.version.value()
+    }
+
+    /// The version as sent.
+    pub fn raw_version(&self) -> &str {
+        self.version.raw()
+    }
+
+    /// The authors as sent, see `authors` for the individual authors.
+    pub fn raw_authors(&self) -> Option<&str> {
+        self.authors.as_ref().map(String::as_str)
+    }
+
+    /// The comma separated authors.
+    pub fn authors(&self) -> Vec<&str> {
+        split_list(self.authors.as_ref().map(String::as_str))
+    }
+
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        self.created_at
+    }
+
+    pub fn development_dependency(&self) -> Option<bool> {
+        self.development_dependency
+    }
+
+    pub fn gallery_details_url(&self) -> &UrlProperty {
+        &self.gallery_details_url
+    }
+
+    pub fn icon_url(&self) -> &UrlProperty {
+        &self.icon_url
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_ref().map(String::as_str)
+    }
+
+    pub fn last_edited_at(&self) -> Option<DateTime<Utc>> {
+        self.last_edited_at
+    }
+
+    pub fn last_updated_at(&self) -> Option<DateTime<Utc>> {
+        self.last_updated_at
+    }
+
+    pub fn license_url(&self) -> &UrlProperty {
+        &self.license_url
+    }
+
+    pub fn license_names(&self) -> Option<&str> {
+        self.license_names.as_ref().map(String::as_str)
+    }
+
+    pub fn license_report_url(&self) -> &UrlProperty {
+        &self.license_report_url
+    }
+
+    pub fn is_listed(&self) -> Option<bool> {
+        self.is_listed
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_ref().map(String::as_str)
+    }
+
+    pub fn minimum_client_version(&self) -> Option<&str> {
+        self.minimum_client_version.as_ref().map(String::as_str)
+    }
+
+    pub fn normalized_version(&self) -> Option<&NuGetVersion> {
+        self.normalized_version.as_ref()
+    }
+
+    /// The owners as sent, see `owners` for the individual owners.
+    pub fn raw_owners(&self) -> Option<&str> {
+        self.owners.as_ref().map(String::as_str)
+    }
+
+    /// The comma separated owners.
+    pub fn owners(&self) -> Vec<&str> {
+        split_list(self.owners.as_ref().map(String::as_str))
+    }
+
+    /// The base64 encoded hash of the package file.
+    pub fn package_hash(&self) -> Option<&str> {
+        self.package_hash.as_ref().map(String::as_str)
+    }
+
+    /// The algorithm of `package_hash`, e.g. `SHA512`.
+    pub fn package_hash_algorithm(&self) -> Option<&str> {
+        self.package_hash_algorithm.as_ref().map(String::as_str)
+    }
+
+    pub fn published_at(&self) -> Option<Published> {
+        self.published_at
+    }
+
+    pub fn report_abuse_url(&self) -> &UrlProperty {
+        &self.report_abuse_url
+    }
+
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_ref().map(String::as_str)
+    }
+
+    /// Downloads of this version of the package.
+    pub fn version_download_count(&self) -> i32 {
+        self.version_download_count
+    }
+
+    pub fn is_local_package(&self) -> Option<bool> {
+        self.is_local_package
+    }
+
+    pub fn is_proget_hosted(&self) -> Option<bool> {
+        self.is_proget_hosted
+    }
+
+    pub fn is_cached(&self) -> Option<bool> {
+        self.is_cached
+    }
+
+    pub fn has_symbols(&self) -> Option<bool> {
+        self.has_symbols
+    }
+
+    pub fn has_source(&self) -> Option<bool> {
+        self.has_source
+    }
+}
+
+pub(crate) fn split_tags(tags: &str) -> Vec<&str> {
+    tags.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+pub(crate) fn split_list(list: Option<&str>) -> Vec<&str> {
+    list.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// A URL property, which feeds often send empty or malformed.
+///
+/// The value as sent is kept alongside the parsed URL, which is `None`
+/// when the value is empty or isn't a valid absolute URL.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UrlProperty {
+    raw: String,
+    url: Option<Url>,
+}
+
+impl UrlProperty {
+    pub fn url(&self) -> Option<&Url> {
+        self.url.as_ref()
+    }
+
+    /// The value exactly as the feed sent it.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl From<String> for UrlProperty {
+    fn from(raw: String) -> UrlProperty {
+        let url = if raw.trim().is_empty() {
+            None
+        } else {
+            match Url::parse(raw.trim()) {
+                Ok(url) => Some(url),
+                Err(e) => {
+                    debug!("Ignoring malformed URL {}: {}", raw, e);
+                    None
+                }
+            }
+        };
+
+        UrlProperty { raw, url }
+    }
+}
+
+impl<'a> From<&'a str> for UrlProperty {
+    fn from(raw: &'a str) -> UrlProperty {
+        UrlProperty::from(String::from(raw))
+    }
+}
+
+impl From<Option<String>> for UrlProperty {
+    fn from(raw: Option<String>) -> UrlProperty {
+        raw.map(UrlProperty::from).unwrap_or_default()
+    }
+}
+
+impl<'de> Deserialize<'de> for UrlProperty {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer).map(UrlProperty::from)
+    }
+}
+
+/// A property parsed from a string, such as a version, which feeds sometimes send malformed.
+///
+/// The value as sent is kept alongside the parsed one, which is `None` when
+/// it doesn't parse, so one malformed package doesn't fail a whole feed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedProperty<T> {
+    raw: String,
+    value: Option<T>,
+}
+
+impl<T> ParsedProperty<T> {
+    pub fn value(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    /// The value exactly as the feed sent it.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl<T: fmt::Display> ParsedProperty<T> {
+    /// A property for a value that didn't need parsing, such as one from a V3 feed.
+    pub fn new(value: T) -> ParsedProperty<T> {
+        ParsedProperty {
+            raw: value.to_string(),
+            value: Some(value),
+        }
+    }
+}
+
+impl<T> From<String> for ParsedProperty<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    fn from(raw: String) -> ParsedProperty<T> {
+        let value = match raw.parse() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("Ignoring malformed value {:?}: {}", raw, e);
+                None
+            }
+        };
+
+        ParsedProperty { raw, value }
+    }
+}
+
+impl<'a, T> From<&'a str> for ParsedProperty<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    fn from(raw: &'a str) -> ParsedProperty<T> {
+        ParsedProperty::from(String::from(raw))
+    }
+}
+
+// A property the feed didn't send, which has no value.
+impl<T> Default for ParsedProperty<T> {
+    fn default() -> ParsedProperty<T> {
+        ParsedProperty {
+            raw: String::new(),
+            value: None,
+        }
+    }
+}
+
+impl<'de, T> Deserialize<'de> for ParsedProperty<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)
+            .map(|raw| ParsedProperty::from(raw.unwrap_or_default()))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
 pub struct PackageDependency {
     pub package_id: String,
-    pub version: String,
-    pub framework: String,
+    pub version: VersionRange,
+    pub framework: NuGetFramework,
 }
 
 impl FromStr for PackageDependency {
@@ -120,13 +505,13 @@ impl FromStr for PackageDependency {
         match splitted.len() {
             2 => Ok(Self {
                 package_id: String::from(splitted[0]),
-                version: String::from(splitted[1]),
-                framework: String::from(""),
+                version: splitted[1].parse()?,
+                framework: NuGetFramework::any(),
             }),
             3 => Ok(Self {
                 package_id: String::from(splitted[0]),
-                version: String::from(splitted[1]),
-                framework: String::from(splitted[2]),
+                version: splitted[1].parse()?,
+                framework: splitted[2].parse()?,
             }),
             _ => bail!(
                 "2 or 3 elements are required for a package dependency. {} only has {}",
@@ -137,15 +522,235 @@ impl FromStr for PackageDependency {
     }
 }
 
-fn parse_dependencies<'de, D>(deserializer: D) -> Result<Vec<PackageDependency>, D::Error>
+/// A package's dependencies, grouped by the framework they apply to.
+///
+/// A group without any dependencies, such as `::net45` in a V2 feed, means
+/// the package has no dependencies when targeting that framework.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DependencyGroups {
+    groups: Vec<DependencyGroup>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyGroup {
+    pub framework: NuGetFramework,
+    pub dependencies: Vec<PackageDependency>,
+}
+
+impl DependencyGroups {
+    pub fn new() -> DependencyGroups {
+        DependencyGroups::default()
+    }
+
+    /// The group for `framework`, which is created empty if it doesn't exist yet.
+    pub fn group_mut(&mut self, framework: NuGetFramework) -> &mut DependencyGroup {
+        match self
+            .groups
+            .iter()
+            .position(|group| group.framework == framework)
+        {
+            Some(index) => &mut self.groups[index],
+            None => {
+                self.groups.push(DependencyGroup {
+                    framework,
+                    dependencies: Vec::new(),
+                });
+                self.groups.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Adds `dependency` to the group for its framework.
+    pub fn push(&mut self, dependency: PackageDependency) {
+        self.group_mut(dependency.framework.clone())
+            .dependencies
+            .push(dependency);
+    }
+
+    pub fn get(&self, framework: &NuGetFramework) -> Option<&DependencyGroup> {
+        self.groups
+            .iter()
+            .find(|group| group.framework == *framework)
+    }
+
+    pub fn frameworks(&self) -> Vec<&NuGetFramework> {
+        self.groups.iter().map(|group| &group.framework).collect()
+    }
+
+    pub fn iter<'a>(&'a self) -> slice::Iter<'a, DependencyGroup> {
+        self.groups.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// The dependencies of the group nearest to `target`, or none if no group is compatible with it.
+    pub fn for_framework(&self, target: &NuGetFramework) -> &[PackageDependency] {
+        let nearest = target.nearest(self.groups.iter().map(|group| &group.framework));
+
+        match nearest {
+            Some(framework) => &self.get(framework).unwrap().dependencies,
+            None => &[],
+        }
+    }
+}
+
+impl From<Vec<DependencyGroup>> for DependencyGroups {
+    fn from(groups: Vec<DependencyGroup>) -> DependencyGroups {
+        DependencyGroups { groups }
+    }
+}
+
+impl<'a> IntoIterator for &'a DependencyGroups {
+    type Item = &'a DependencyGroup;
+    type IntoIter = slice::Iter<'a, DependencyGroup>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// The V2 `id:version:framework|...` format, where `::framework` is an empty group.
+impl FromStr for DependencyGroups {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut groups = DependencyGroups::new();
+
+        for elem in s.split('|').filter(|elem| !elem.is_empty()) {
+            let dependency = elem.parse::<PackageDependency>()?;
+
+            if dependency.package_id.is_empty() {
+                groups.group_mut(dependency.framework);
+            } else {
+                groups.push(dependency);
+            }
+        }
+
+        Ok(groups)
+    }
+}
+
+impl fmt::Display for DependencyGroups {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut elems = Vec::new();
+
+        for group in &self.groups {
+            // `any` is written as an empty framework, which parses back to it
+            let framework = if group.framework.is_any() {
+                String::new()
+            } else {
+                group.framework.to_short_name()
+            };
+
+            if group.dependencies.is_empty() {
+                elems.push(format!("::{}", framework));
+            }
+
+            for dependency in &group.dependencies {
+                elems.push(format!(
+                    "{}:{}:{}",
+                    dependency.package_id, dependency.version, framework
+                ));
+            }
+        }
+
+        f.write_str(&elems.join("|"))
+    }
+}
+
+// Feeds send an empty element rather than omitting the property
+fn parse_optional_version<'de, D>(deserializer: D) -> Result<Option<NuGetVersion>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    String::deserialize(deserializer)?
-        .split("|")
-        .filter(|&elem| elem != "")
-        .map(|elem| elem.parse().map_err(DeserializeError::custom))
-        .collect()
+    match Option::<String>::deserialize(deserializer)? {
+        Some(ref version) if !version.is_empty() => match version.parse() {
+            Ok(version) => Ok(Some(version)),
+            Err(e) => {
+                warn!("Ignoring malformed normalized version {:?}: {}", version, e);
+                Ok(None)
+            }
+        },
+        _ => Ok(None),
+    }
+}
+
+// The `Published` date nuget.org gives unlisted packages, 1900-01-01T00:00:00Z
+const UNLISTED_TIMESTAMP: i64 = -2_208_988_800;
+
+/// When a package was published, or that it has been unlisted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Published {
+    Listed(DateTime<Utc>),
+    Unlisted,
+}
+
+impl Published {
+    pub fn date(&self) -> Option<DateTime<Utc>> {
+        match *self {
+            Published::Listed(date) => Some(date),
+            Published::Unlisted => None,
+        }
+    }
+
+    pub fn is_listed(&self) -> bool {
+        *self != Published::Unlisted
+    }
+}
+
+impl From<DateTime<Utc>> for Published {
+    fn from(date: DateTime<Utc>) -> Published {
+        if date.timestamp() == UNLISTED_TIMESTAMP {
+            Published::Unlisted
+        } else {
+            Published::Listed(date)
+        }
+    }
+}
+
+// OData dates are either RFC 3339, or offset-less and in UTC, e.g. `2016-01-22T20:46:59.9523998`
+pub(crate) fn date_from_str(s: &str) -> Result<DateTime<Utc>, Error> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|date| date.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+                .map(|date| Utc.from_utc_datetime(&date))
+        }).map_err(|_| format_err!("{} is not a valid date", s))
+}
+
+pub(crate) fn parse_date<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    date_from_str(&String::deserialize(deserializer)?).map_err(DeserializeError::custom)
+}
+
+// Feeds send an empty element rather than omitting the property
+pub(crate) fn parse_optional_date<'de, D>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(ref date) if !date.is_empty() => match date_from_str(date) {
+            Ok(date) => Ok(Some(date)),
+            Err(e) => {
+                warn!("Ignoring malformed date {:?}: {}", date, e);
+                Ok(None)
+            }
+        },
+        _ => Ok(None),
+    }
+}
+
+fn parse_published<'de, D>(deserializer: D) -> Result<Option<Published>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    parse_optional_date(deserializer).map(|date| date.map(Published::from))
 }
 
 #[serde(deny_unknown_fields)]
@@ -159,23 +764,209 @@ impl Package {
         self.properties.id.as_ref().unwrap_or(&self.title)
     }
 
-    pub fn version(&self) -> &str {
-        &self.properties.version
+    /// The version, or `None` if the feed sent one that doesn't parse.
+    pub fn version(&self) -> Option<&NuGetVersion> {
+        self.properties.version()
     }
 
-    pub fn content(&self, client: &Client) -> Result<Vec<u8>, Error> {
-        let url = &self.content.url;
-        let mut resp = client.get(url)?;
+    /// The version as sent, see `version`.
+    pub fn raw_version(&self) -> &str {
+        self.properties.raw_version()
+    }
+
+    /// The dependencies, or `None` if the feed sent ones that don't parse.
+    pub fn dependencies(&self) -> Option<&DependencyGroups> {
+        self.properties.dependencies()
+    }
+
+    /// The dependencies as sent, see `dependencies`.
+    pub fn raw_dependencies(&self) -> &str {
+        self.properties.raw_dependencies()
+    }
+
+    /// Whether the package shows up in search results, from `Listed` or the unlisted `Published` date.
+    pub fn is_listed(&self) -> bool {
+        match (self.properties.is_listed, self.properties.published_at) {
+            (Some(is_listed), _) => is_listed,
+            (None, Some(published)) => published.is_listed(),
+            (None, None) => true,
+        }
+    }
 
+    /// Downloads the package, failing with an `IntegrityError` if it doesn't match its declared hash or size.
+    pub fn content(&self, client: &Client) -> Result<Vec<u8>, Error> {
         let mut buffer = vec![];
-        let _ = resp.read_to_end(&mut buffer);
+        self.download_to(client, &mut buffer, |_, _| {})?;
         Ok(buffer)
     }
+
+    /// Streams the package into `writer`, returning its size.
+    ///
+    /// `progress` is called with the bytes received so far and the total, if known.
+    pub fn download_to<W, F>(&self, client: &Client, writer: W, progress: F) -> Result<u64, Error>
+    where
+        W: Write,
+        F: FnMut(u64, Option<u64>),
+    {
+        let resp = client.get(&self.content.url)?;
+
+        let mut verifier = Verifier::new(self)?;
+        if let Some(length) = resp.content_length() {
+            verifier.expect_size(length)?;
+        }
+
+        verifier.copy(resp, writer, progress)
+    }
+
+    /// Downloads the package to `path`, returning its size.
+    ///
+    /// The download is written next to `path` with a `.partial` extension and
+    /// only renamed into place once verified, so `path` is never incomplete.
+    /// A `.partial` file left by an interrupted download is resumed if the server
+    /// supports range requests, and is removed if the package fails its hash check.
+    pub fn download_to_path<P, F>(
+        &self,
+        client: &Client,
+        path: P,
+        progress: F,
+    ) -> Result<u64, Error>
+    where
+        P: AsRef<Path>,
+        F: FnMut(u64, Option<u64>),
+    {
+        let path = path.as_ref();
+        let partial_path = partial_path(path);
+        let url = &self.content.url;
+
+        let mut partial = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&partial_path)?;
+
+        // Hash what's already been downloaded, so the whole package is verified
+        let mut verifier = Verifier::new(self)?;
+        verifier.update_from(&mut partial)?;
+        let offset = verifier.received();
+
+        let mut resp = if offset > 0 {
+            debug!("Resuming {} from byte {}", self, offset);
+            client.get_from(url, offset)?
+        } else {
+            client.get(url)?
+        };
+
+        let range = if resp.status() == StatusCode::PARTIAL_CONTENT {
+            resp.headers()
+                .get(CONTENT_RANGE)
+                .and_then(|range| range.to_str().ok())
+                .and_then(content_range)
+        } else {
+            None
+        };
+        let resumed = offset > 0 && range.map(|(start, _)| start) == Some(offset);
+
+        if offset > 0 && !resumed {
+            partial.set_len(0)?;
+            verifier = Verifier::new(self)?;
+
+            // Bytes from anywhere else would corrupt the package, so fetch all of it
+            if resp.status() == StatusCode::PARTIAL_CONTENT {
+                debug!("{} resumed from the wrong byte, restarting", url);
+                resp = client.get(url)?;
+            } else {
+                debug!("{} doesn't support range requests, restarting", url);
+            }
+        }
+
+        let total = if resumed {
+            range
+                .and_then(|(_, size)| size)
+                .or_else(|| resp.content_length().map(|length| offset + length))
+        } else {
+            resp.content_length()
+        };
+        if let Some(total) = total {
+            verifier.expect_size(total)?;
+        }
+
+        match verifier.copy(resp, &mut partial, progress) {
+            Ok(size) => {
+                partial.sync_all()?;
+                fs::rename(&partial_path, path)?;
+                Ok(size)
+            }
+            Err(err) => {
+                // A truncated download can be resumed, but corrupt bytes can't be
+                match err.downcast_ref() {
+                    Some(IntegrityError::HashMismatch { .. })
+                    | Some(IntegrityError::TooLarge { .. }) => {
+                        let _ = fs::remove_file(&partial_path);
+                    }
+                    _ => {}
+                }
+
+                Err(err)
+            }
+        }
+    }
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+    file_name.push(".partial");
+    path.with_file_name(file_name)
+}
+
+// The first byte and complete size from a `Content-Range` header, e.g. `bytes 100-199/200`
+fn content_range(range: &str) -> Option<(u64, Option<u64>)> {
+    #[allow(deprecated)]
+    let range = range.trim().trim_left_matches("bytes").trim();
+    let mut parts = range.splitn(2, '/');
+
+    let start = parts
+        .next()
+        .and_then(|bytes| bytes.split('-').next())
+        .and_then(|start| start.trim().parse().ok())?;
+    let size = parts.next().and_then(|size| size.trim().parse().ok());
+
+    Some((start, size))
 }
 
 impl fmt::Display for Package {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} {}", self.title, self.properties.version)
+        write!(f, "{} {}", self.title, self.raw_version())
+    }
+}
+
+#[cfg(test)]
+impl Package {
+    /// A package with an id and version, taking the rest of its properties from `properties`.
+    pub(crate) fn for_test(id: &str, version: &str, properties: PackageProperties) -> Package {
+        Package {
+            id: format!(
+                "https://example.org/Packages(Id='{}',Version='{}')",
+                id, version
+            ),
+            category: PackageCategory {
+                term: String::from("Package"),
+            },
+            title: String::from(id),
+            summary: None,
+            content: PackageContent {
+                mime_type: String::from("application/zip"),
+                url: Url::parse("https://example.org/package").unwrap(),
+            },
+            updated_at: Utc.timestamp_opt(0, 0).unwrap(),
+            author: Author {
+                name: String::new(),
+            },
+            properties: PackageProperties {
+                id: Some(String::from(id)),
+                version: ParsedProperty::from(version),
+                ..properties
+            },
+        }
     }
 }
 
@@ -185,6 +976,10 @@ mod tests {
 
     use super::*;
 
+    fn date(s: &str) -> DateTime<Utc> {
+        date_from_str(s).unwrap()
+    }
+
     #[test]
     fn nuget_gallery_package() {
         // From https://www.nuget.org/api/v2/Packages(Id='_51Wp.AccountSdk',Version='1.0.0')
@@ -248,7 +1043,7 @@ mod tests {
                 },
                 title: String::from("_51Wp.AccountSdk"),
                 summary: None,
-                updated_at: String::from("2015-12-15T15:05:02Z"),
+                updated_at: date("2015-12-15T15:05:02Z"),
                 author: Author {
                     name: String::from("authors")
                 },
@@ -263,35 +1058,35 @@ mod tests {
                     owners: None,
                     development_dependency: None,
                     id: Some(String::from("_51Wp.AccountSdk")),
-                    version: String::from("1.0.0"),
-                    normalized_version: Some(String::from("1.0.0")),
+                    version: ParsedProperty::from("1.0.0"),
+                    normalized_version: Some("1.0.0".parse().unwrap()),
                     authors: Some(String::from("authors")),
                     copyright: String::from(""),
-                    created_at: Some(String::from("2015-12-15T15:05:02.15Z")),
-                    dependencies: vec![],
+                    created_at: Some(date("2015-12-15T15:05:02.15Z")),
+                    dependencies: ParsedProperty::from(""),
                     description: String::from("My package description."),
                     download_count: 2195,
-                    gallery_details_url: Some(String::from(
+                    gallery_details_url: UrlProperty::from(
                         "https://www.nuget.\
                          org/packages/_51Wp.\
                          AccountSdk/1.0.0"
-                    )),
-                    icon_url: Some(String::from("")),
+                    ),
+                    icon_url: UrlProperty::from(""),
                     is_latest_version: false,
                     is_absolute_latest_version: false,
                     is_prerelease: false,
                     language: Some(String::from("")),
-                    last_updated_at: Some(String::from("2015-12-15T15:05:02.15Z")),
-                    published_at: Some(String::from("1900-01-01T00:00:00")),
+                    last_updated_at: Some(date("2015-12-15T15:05:02.15Z")),
+                    published_at: Some(Published::Unlisted),
                     package_hash: Some(String::from("CwkBmkdSDYieaAgZxyrFizngyNfBB76piK7KFe7T8WgRH7opJZLiz6LdO3CCHp0u0E2GVazgbzAPJG+PNpzT1g==")),
                     package_hash_algorithm: Some(String::from("SHA512")),
                     package_size: 212213,
-                    project_url: String::from(""),
-                    report_abuse_url: Some(String::from(
+                    project_url: UrlProperty::from(""),
+                    report_abuse_url: UrlProperty::from(
                         "https://www.nuget.\
                          org/packages/_51Wp.AccountSdk/1.\
                          0.0/ReportAbuse"
-                    )),
+                    ),
                     release_notes: String::from(""),
                     require_license_acceptance: false,
                     summary: Some(String::from("")),
@@ -299,10 +1094,10 @@ mod tests {
                     title: String::from("_51Wp.AccountSdk"),
                     version_download_count: 2195,
                     minimum_client_version: Some(String::from("")),
-                    last_edited_at: Some(String::from("2015-12-15T22:58:39.043Z")),
-                    license_url: Some(String::from("")),
+                    last_edited_at: Some(date("2015-12-15T22:58:39.043Z")),
+                    license_url: UrlProperty::from(""),
                     license_names: Some(String::from("")),
-                    license_report_url: Some(String::from("")),
+                    license_report_url: UrlProperty::from(""),
 
                     // ProGet specific fields
                     is_local_package: None,
@@ -383,7 +1178,7 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
                 },
                 title: String::from("Microsoft.Extensions.Primitives"),
                 summary: Some(String::from("ASP.NET 5 primitives.")),
-                updated_at: String::from("2016-01-22T20:46:59Z"),
+                updated_at: date("2016-01-22T20:46:59Z"),
                 author: Author {
                     name: String::from("Microsoft.Extensions.Primitives")
                 },
@@ -397,38 +1192,45 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
                 },
                 properties: PackageProperties {
                     id: Some(String::from("Microsoft.Extensions.Primitives")),
-                    version: String::from("1.0.0-rc2-16010"),
-                    normalized_version: Some(String::from("1.0.0-rc2-16010")),
+                    version: ParsedProperty::from("1.0.0-rc2-16010"),
+                    normalized_version: Some("1.0.0-rc2-16010".parse().unwrap()),
                     authors: None,
                     copyright: String::from(""),
-                    created_at: Some(String::from("2016-01-22T20:46:59.9523998Z")),
-                    dependencies: vec![
-                        PackageDependency { framework: String::from("net451"), package_id: String::from(""), version: String::from("") },
-                        PackageDependency { framework: String::from(".NETCore50"), package_id: String::from("System.Resources.ResourceManager"), version: String::from("4.0.0") },
-                        PackageDependency { framework: String::from(".NETCore50"), package_id: String::from("System.Runtime"), version: String::from("4.0.20") },
-                        PackageDependency { framework: String::from(".NETCore50"), package_id: String::from("System.Threading"), version: String::from("4.0.10") },
-                        PackageDependency { framework: String::from("dotnet5.4"), package_id: String::from("System.Runtime"), version: String::from("4.0.21-rc2-23706") },
-                        PackageDependency { framework: String::from("dotnet5.4"), package_id: String::from("System.Resources.ResourceManager"), version: String::from("4.0.1-rc2-23706") },
-                    ],
+                    created_at: Some(date("2016-01-22T20:46:59.9523998Z")),
+                    dependencies: ParsedProperty {
+                        raw: String::from("::net451|System.Resources.ResourceManager:4.0.0:.NETCore50|System.Runtime:4.0.20:.NETCore50|System.Threading:4.0.10:.NETCore50|System.Runtime:4.0.21-rc2-23706:dotnet5.4|System.Resources.ResourceManager:4.0.1-rc2-23706:dotnet5.4"),
+                        value: Some(DependencyGroups::from(vec![
+                        DependencyGroup { framework: "net451".parse().unwrap(), dependencies: vec![] },
+                        DependencyGroup { framework: ".NETCore50".parse().unwrap(), dependencies: vec![
+                            PackageDependency { framework: ".NETCore50".parse().unwrap(), package_id: String::from("System.Resources.ResourceManager"), version: "4.0.0".parse().unwrap() },
+                            PackageDependency { framework: ".NETCore50".parse().unwrap(), package_id: String::from("System.Runtime"), version: "4.0.20".parse().unwrap() },
+                            PackageDependency { framework: ".NETCore50".parse().unwrap(), package_id: String::from("System.Threading"), version: "4.0.10".parse().unwrap() },
+                        ] },
+                        DependencyGroup { framework: "dotnet5.4".parse().unwrap(), dependencies: vec![
+                            PackageDependency { framework: "dotnet5.4".parse().unwrap(), package_id: String::from("System.Runtime"), version: "4.0.21-rc2-23706".parse().unwrap() },
+                            PackageDependency { framework: "dotnet5.4".parse().unwrap(), package_id: String::from("System.Resources.ResourceManager"), version: "4.0.1-rc2-23706".parse().unwrap() },
+                        ] },
+                        ])),
+                    },
                     description: String::from("ASP.NET 5 primitives."),
                     development_dependency: None,
                     download_count: 15,
-                    gallery_details_url: Some(String::from(
+                    gallery_details_url: UrlProperty::from(
                         "https://www.myget.org/feed/omnisharp/package/nuget/Microsoft.Extensions.Primitives/1.0.0-rc2-16010"
-                    )),
-                    icon_url: Some(String::from("")),
+                    ),
+                    icon_url: UrlProperty::from(""),
                     is_latest_version: false,
                     is_absolute_latest_version: true,
                     is_prerelease: true,
                     language: Some(String::from("en-US")),
                     last_updated_at: None,
                     owners: None,
-                    published_at: Some(String::from("2016-01-22T20:46:59.9523998Z")),
+                    published_at: Some(Published::Listed(date("2016-01-22T20:46:59.9523998Z"))),
                     package_hash: Some(String::from("OrfLiJc4So4HHOb7lNJyPSNoFHPM4O8VhqhAg6cdRMlzuFaMF/X4tR43AGDFQH50f30Y2r2eE/4egrWx2gy4xg==")),
                     package_hash_algorithm: Some(String::from("SHA512")),
                     package_size: 18238,
-                    project_url: String::from(""),
-                    report_abuse_url: Some(String::from("http://localhost")),
+                    project_url: UrlProperty::from(""),
+                    report_abuse_url: UrlProperty::from("http://localhost"),
                     release_notes: String::from(""),
                     require_license_acceptance: false,
                     summary: None,
@@ -436,10 +1238,10 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
                     title: String::from("Microsoft.Extensions.Primitives"),
                     version_download_count: 15,
                     minimum_client_version: Some(String::from("")),
-                    last_edited_at: Some(String::from("2016-01-22T20:46:59.9523998Z")),
-                    license_url: Some(String::from("")),
+                    last_edited_at: Some(date("2016-01-22T20:46:59.9523998Z")),
+                    license_url: UrlProperty::from(""),
                     license_names: Some(String::from("")),
-                    license_report_url: Some(String::from("")),
+                    license_report_url: UrlProperty::from(""),
 
                     // ProGet specific fields
                     is_local_package: None,
@@ -519,7 +1321,7 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
                     "The runtime library for parsers generated by \
                      the C# target of ANTLR 4."
                 )),
-                updated_at: String::from("2016-08-04T12:27:32Z"),
+                updated_at: date("2016-08-04T12:27:32Z"),
                 author: Author {
                     name: String::from("Sam Harwell, Terence Parr")
                 },
@@ -532,12 +1334,12 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
                 },
                 properties: PackageProperties {
                     id: None,
-                    version: String::from("4.5.3-rc1"),
-                    normalized_version: Some(String::from("4.5.3-rc1")),
+                    version: ParsedProperty::from("4.5.3-rc1"),
+                    normalized_version: Some("4.5.3-rc1".parse().unwrap()),
                     authors: None,
                     copyright: String::from("Copyright © Sam Harwell 2015"),
-                    created_at: Some(String::from("2016-08-04T12:27:32.5030000Z")),
-                    dependencies: vec![],
+                    created_at: Some(date("2016-08-04T12:27:32.5030000Z")),
+                    dependencies: ParsedProperty::from(""),
                     description: String::from(
                         "The runtime library for parsers generated \
                          by the C# target of ANTLR 4. This package \
@@ -548,22 +1350,22 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
                     development_dependency: None,
                     owners: None,
                     download_count: 268,
-                    gallery_details_url: None,
-                    icon_url: Some(String::from("https://raw.github.com/antlr/website-antlr4/master/images/icons/antlr.png")),
+                    gallery_details_url: UrlProperty::default(),
+                    icon_url: UrlProperty::from("https://raw.github.com/antlr/website-antlr4/master/images/icons/antlr.png"),
                     is_latest_version: false,
                     is_absolute_latest_version: false,
                     is_prerelease: true,
                     language: None,
                     last_updated_at: None,
-                    published_at: Some(String::from("2016-08-04T12:27:32.5030000Z")),
+                    published_at: Some(Published::Listed(date("2016-08-04T12:27:32.5030000Z"))),
                     package_hash: Some(String::from("dPb/HRNYfLKDNFj3K1tlZf+f5gyQq03jE3UjJk9f55YoV0lnXJ8m9hFjhooa+K5VcA/N5/LLiOkPSrM2i+sF3Q==")),
                     package_hash_algorithm: Some(String::from("SHA512")),
                     package_size: 1662759,
-                    project_url: String::from(
+                    project_url: UrlProperty::from(
                         "https://github.\
                          com/tunnelvisionlabs/antlr4cs"
                     ),
-                    report_abuse_url: None,
+                    report_abuse_url: UrlProperty::default(),
                     release_notes: String::from(
                         "https://github.\
                          com/tunnelvisionlabs/antlr4cs/releases/v4.\
@@ -579,9 +1381,9 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
                     version_download_count: 116,
                     minimum_client_version: None,
                     last_edited_at: None,
-                    license_url: Some(String::from("https://raw.github.com/tunnelvisionlabs/antlr4cs/master/LICENSE.txt")),
+                    license_url: UrlProperty::from("https://raw.github.com/tunnelvisionlabs/antlr4cs/master/LICENSE.txt"),
                     license_names: None,
-                    license_report_url: None,
+                    license_report_url: UrlProperty::default(),
 
                     // ProGet specific fields
                     is_local_package: Some(true),
@@ -655,7 +1457,7 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
                     "An eventsource(Server-Sent Events client) \
                      implementation for .Net."
                 )),
-                updated_at: String::from("2017-05-04T11:03:27Z"),
+                updated_at: date("2017-05-04T11:03:27Z"),
                 author: Author {
                     name: String::from("erizet")
                 },
@@ -669,14 +1471,19 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
                 },
                 properties: PackageProperties {
                     id: None,
-                    version: String::from("0.4.0.1"),
+                    version: ParsedProperty::from("0.4.0.1"),
                     normalized_version: None,
                     authors: Some(String::from("erizet")),
                     copyright: String::from("Copyright 2017"),
-                    created_at: Some(String::from("2017-05-04T11:03:28")),
-                    dependencies: vec![
-                        PackageDependency { framework: String::from(""), package_id: String::from("slf4net"), version: String::from("0.1.32.1") },
-                    ],
+                    created_at: Some(date("2017-05-04T11:03:28")),
+                    dependencies: ParsedProperty {
+                        raw: String::from("slf4net:0.1.32.1:"),
+                        value: Some(DependencyGroups::from(vec![
+                        DependencyGroup { framework: NuGetFramework::any(), dependencies: vec![
+                            PackageDependency { framework: NuGetFramework::any(), package_id: String::from("slf4net"), version: "0.1.32.1".parse().unwrap() },
+                        ] },
+                        ])),
+                    },
                     development_dependency: None,
                     owners: None,
                     description: String::from(
@@ -684,19 +1491,19 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
                          implementation for .Net."
                     ),
                     download_count: 0,
-                    gallery_details_url: None,
-                    icon_url: None,
+                    gallery_details_url: UrlProperty::default(),
+                    icon_url: UrlProperty::default(),
                     is_latest_version: true,
                     is_absolute_latest_version: true,
                     is_prerelease: false,
                     language: Some(String::from("")),
                     last_updated_at: None,
-                    published_at: Some(String::from("2017-05-04T11:03:28")),
+                    published_at: Some(Published::Listed(date("2017-05-04T11:03:28"))),
                     package_hash: Some(String::from("otpBPpuwCOPT5J12azb9MvStj2+WA1nqX/8aAkNjO7Wuohsg/M+d17l1M6k9D4c+B4k6/3XC376eMmbb7TG68A==")),
                     package_hash_algorithm: Some(String::from("SHA512")),
                     package_size: 9989,
-                    project_url: String::from("https://github.com/fintprosjektet"),
-                    report_abuse_url: None,
+                    project_url: UrlProperty::from("https://github.com/fintprosjektet"),
+                    report_abuse_url: UrlProperty::default(),
                     release_notes: String::from(""),
                     require_license_acceptance: false,
                     summary: None,
@@ -705,9 +1512,9 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
                     version_download_count: 0,
                     minimum_client_version: Some(String::from("")),
                     last_edited_at: None,
-                    license_url: None,
+                    license_url: UrlProperty::default(),
                     license_names: None,
-                    license_report_url: None,
+                    license_report_url: UrlProperty::default(),
 
                     // ProGet specific fields
                     is_local_package: None,
@@ -720,4 +1527,153 @@ r##"<?xml version="1.0" encoding="UTF-8"?>
             }
         );
     }
+
+    #[test]
+    fn dependency_groups() {
+        let groups: DependencyGroups =
+            "A:1.0:net40|::net45|B:[2.0,3.0):netstandard1.0|C:1.0:netstandard2.0"
+                .parse()
+                .unwrap();
+
+        assert_eq!(groups.frameworks().len(), 4);
+        assert!(groups
+            .get(&"net45".parse().unwrap())
+            .unwrap()
+            .dependencies
+            .is_empty());
+
+        let ids = |target: &str| {
+            groups
+                .for_framework(&target.parse().unwrap())
+                .iter()
+                .map(|dependency| dependency.package_id.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("net40"), vec!["A"]);
+        assert!(ids("net48").is_empty());
+        assert_eq!(ids("netcoreapp1.0"), vec!["B"]);
+        assert_eq!(ids("netcoreapp2.0"), vec!["C"]);
+        assert!(ids("net35").is_empty());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(date("2015-12-15T15:05:02.15Z"), date("2015-12-15T15:05:02.150"));
+        assert_eq!(date("2016-01-22T20:46:59+01:00"), date("2016-01-22T19:46:59"));
+        assert_eq!(
+            date("2016-01-22T20:46:59.9523998").timestamp_subsec_nanos(),
+            952_399_800
+        );
+        assert!(date_from_str("15/12/2015").is_err());
+
+        #[derive(Deserialize)]
+        struct Dated {
+            #[serde(default, deserialize_with = "parse_optional_date")]
+            created: Option<DateTime<Utc>>,
+        }
+
+        let dated = |xml: &str| {
+            serde_xml_rs::from_reader::<_, Dated>(xml.as_bytes())
+                .unwrap()
+                .created
+        };
+        assert_eq!(
+            dated("<d><created>2015-12-15T15:05:02.15Z</created></d>"),
+            Some(date("2015-12-15T15:05:02.15Z"))
+        );
+        assert_eq!(dated("<d><created>15/12/2015</created></d>"), None);
+        assert_eq!(dated("<d><created></created></d>"), None);
+
+        assert_eq!(
+            Published::from(date("1900-01-01T00:00:00")),
+            Published::Unlisted
+        );
+        assert_eq!(
+            Published::from(date("2017-05-04T11:03:28")).date(),
+            Some(date("2017-05-04T11:03:28Z"))
+        );
+    }
+
+    #[test]
+    fn url_properties() {
+        let url = UrlProperty::from("https://github.com/fintprosjektet");
+        assert_eq!(
+            url.url(),
+            Some(&Url::parse("https://github.com/fintprosjektet").unwrap())
+        );
+
+        for raw in &["", "  ", "github.com/fintprosjektet", "http://"] {
+            let url = UrlProperty::from(*raw);
+            assert_eq!(url.url(), None, "{}", raw);
+            assert_eq!(url.raw(), *raw);
+        }
+
+        assert_eq!(UrlProperty::from(None), UrlProperty::default());
+    }
+
+    #[test]
+    fn malformed_versions() {
+        let version = ParsedProperty::<NuGetVersion>::from("1.0.0-beta");
+        assert_eq!(version.value(), Some(&"1.0.0-beta".parse().unwrap()));
+
+        let version = ParsedProperty::<NuGetVersion>::from("not a version");
+        assert_eq!(version.value(), None);
+        assert_eq!(version.raw(), "not a version");
+
+        let version = ParsedProperty::<NuGetVersion>::default();
+        assert_eq!(version.value(), None);
+        assert_eq!(version.raw(), "");
+
+        let package = Package::for_test("Foo", "not a version", PackageProperties::default());
+        assert_eq!(package.version(), None);
+        assert_eq!(package.to_string(), "Foo not a version");
+        assert_eq!(
+            package,
+            Package::for_test("foo", "not a version", PackageProperties::default())
+        );
+        assert!(package != Package::for_test("Foo", "1.0.0", PackageProperties::default()));
+    }
+
+    #[test]
+    fn malformed_dependencies() {
+        let raw = "A:1.0:net40|::net45|B:[2.0,3.0):netstandard1.0|C:1.0:";
+        let groups: DependencyGroups = raw.parse().unwrap();
+        assert_eq!(groups.to_string(), raw);
+
+        let mut package = Package::for_test("Foo", "1.0.0", PackageProperties::default());
+        package.properties.dependencies = ParsedProperty::from("A:not a range:net40");
+        assert_eq!(package.dependencies(), None);
+        assert_eq!(package.raw_dependencies(), "A:not a range:net40");
+
+        package.properties.dependencies = ParsedProperty::from("A:1.0:portable-");
+        assert_eq!(package.dependencies(), None);
+    }
+
+    #[test]
+    fn lists() {
+        let properties = PackageProperties {
+            tags: String::from(" fint sse,server-sent  "),
+            authors: Some(String::from("Sam Harwell, Terence Parr")),
+            owners: Some(String::from("")),
+            ..PackageProperties::default()
+        };
+
+        assert_eq!(properties.tags(), vec!["fint", "sse", "server-sent"]);
+        assert_eq!(properties.authors(), vec!["Sam Harwell", "Terence Parr"]);
+        assert!(properties.owners().is_empty());
+        assert_eq!(properties.raw_owners(), Some(""));
+    }
+
+    #[test]
+    fn download_paths() {
+        assert_eq!(
+            partial_path(Path::new("packages/fint.sse.1.0.0.nupkg")),
+            PathBuf::from("packages/fint.sse.1.0.0.nupkg.partial")
+        );
+
+        assert_eq!(content_range("bytes 100-199/200"), Some((100, Some(200))));
+        assert_eq!(content_range("bytes 100-199/*"), Some((100, None)));
+        assert_eq!(content_range("bytes */200"), None);
+    }
 }
@@ -0,0 +1,337 @@
+use client::Client;
+use failure::Error;
+use framework::NuGetFramework;
+use package::Package;
+use std::collections::HashMap;
+use version::{NuGetVersion, VersionRange};
+
+/// Resolves the transitive dependencies of a set of packages for a target framework.
+///
+/// Follows NuGet's rules: each dependency gets the lowest version its range
+/// accepts, and when a package is requested more than once the request
+/// nearest to a root wins. Requests at the same depth are resolved
+/// together, preferring a version every one of them accepts.
+pub struct Resolver<'a> {
+    client: &'a Client,
+    framework: NuGetFramework,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(client: &'a Client, framework: NuGetFramework) -> Resolver<'a> {
+        Resolver { client, framework }
+    }
+
+    pub fn resolve(&self, roots: &[(String, VersionRange)]) -> Result<Resolution, Error> {
+        resolve_graph(roots, &self.framework, |id| {
+            self.client.package_versions(id)
+        })
+    }
+}
+
+/// The packages selected by a `Resolver`, in the order they were resolved.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    pub packages: Vec<ResolvedPackage>,
+    pub downgrades: Vec<Downgrade>,
+    /// The paths that lead back to a package already on them, e.g. `["A", "B", "A"]`.
+    pub cycles: Vec<Vec<String>>,
+}
+
+impl Resolution {
+    pub fn get(&self, id: &str) -> Option<&ResolvedPackage> {
+        self.packages
+            .iter()
+            .find(|resolved| resolved.package.id().eq_ignore_ascii_case(id))
+    }
+}
+
+#[derive(Debug)]
+pub struct ResolvedPackage {
+    pub package: Package,
+    /// The range of the request that selected the package.
+    pub range: VersionRange,
+    /// The ids from a root down to this package, inclusive.
+    pub path: Vec<String>,
+}
+
+/// A dependency that asked for a higher version than the one nearer to a root that won.
+#[derive(Debug, PartialEq)]
+pub struct Downgrade {
+    pub id: String,
+    pub version: NuGetVersion,
+    pub range: VersionRange,
+    /// The ids from a root down to the dependency that was downgraded, inclusive.
+    pub path: Vec<String>,
+}
+
+struct Request {
+    id: String,
+    range: VersionRange,
+    // The ids from a root down to the package that made the request
+    parents: Vec<String>,
+}
+
+impl Request {
+    fn path(&self) -> Vec<String> {
+        let mut path = self.parents.clone();
+        path.push(self.id.clone());
+        path
+    }
+}
+
+fn resolve_graph<F>(
+    roots: &[(String, VersionRange)],
+    framework: &NuGetFramework,
+    mut package_versions: F,
+) -> Result<Resolution, Error>
+where
+    F: FnMut(&str) -> Result<Vec<Package>, Error>,
+{
+    let mut resolution = Resolution::default();
+    let mut resolved: HashMap<String, usize> = HashMap::new();
+
+    let mut level = roots
+        .iter()
+        .map(|(id, range)| Request {
+            id: id.clone(),
+            range: range.clone(),
+            parents: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+
+    while !level.is_empty() {
+        let mut pending: Vec<(String, Vec<Request>)> = Vec::new();
+
+        for request in level {
+            let key = request.id.to_lowercase();
+
+            if request
+                .parents
+                .iter()
+                .any(|parent| parent.eq_ignore_ascii_case(&request.id))
+            {
+                resolution.cycles.push(request.path());
+                continue;
+            }
+
+            // Nearest wins, so a package resolved at a shallower depth is kept
+            if let Some(&index) = resolved.get(&key) {
+                let existing = &resolution.packages[index];
+
+                if let Some(version) = existing
+                    .package
+                    .version()
+                    .filter(|version| request.range.min().map_or(false, |min| *version < min))
+                {
+                    debug!(
+                        "{} {} is a downgrade from {} requested by {}",
+                        request.id,
+                        version,
+                        request.range,
+                        request.parents.join(" -> ")
+                    );
+
+                    resolution.downgrades.push(Downgrade {
+                        id: request.id.clone(),
+                        version: version.clone(),
+                        range: request.range.clone(),
+                        path: request.path(),
+                    });
+                }
+                continue;
+            }
+
+            match pending
+                .iter()
+                .position(|(pending_key, _)| *pending_key == key)
+            {
+                Some(index) => pending[index].1.push(request),
+                None => pending.push((key, vec![request])),
+            }
+        }
+
+        let mut next = Vec::new();
+
+        for (key, requests) in pending {
+            let (package, request) = select(&requests, &mut package_versions)?;
+            let path = request.path();
+
+            let dependencies = package.dependencies().ok_or_else(|| {
+                format_err!(
+                    "{} has malformed dependencies {:?}",
+                    package,
+                    package.raw_dependencies()
+                )
+            })?;
+
+            for dependency in dependencies.for_framework(framework) {
+                next.push(Request {
+                    id: dependency.package_id.clone(),
+                    range: dependency.version.clone(),
+                    parents: path.clone(),
+                });
+            }
+
+            resolved.insert(key, resolution.packages.len());
+            resolution.packages.push(ResolvedPackage {
+                package,
+                range: request.range.clone(),
+                path,
+            });
+        }
+
+        level = next;
+    }
+
+    Ok(resolution)
+}
+
+// Picks a version for requests of the same package at the same depth,
+// returning it along with the request whose range selected it.
+fn select<'r, F>(
+    requests: &'r [Request],
+    package_versions: &mut F,
+) -> Result<(Package, &'r Request), Error>
+where
+    F: FnMut(&str) -> Result<Vec<Package>, Error>,
+{
+    let id = &requests[0].id;
+    let mut packages = package_versions(id)?;
+
+    // Without a version every request accepts, the highest lower bound wins
+    let request = requests
+        .iter()
+        .max_by(|left, right| left.range.min().cmp(&right.range.min()))
+        .unwrap();
+
+    let best = {
+        let versions = packages.iter().filter_map(Package::version);
+        let accepted_by_all = versions
+            .clone()
+            .filter(|version| requests.iter().all(|other| other.range.satisfies(version)));
+
+        request
+            .range
+            .find_best_match(accepted_by_all)
+            .or_else(|| request.range.find_best_match(versions))
+            .cloned()
+    };
+
+    let best = best.ok_or_else(|| {
+        format_err!(
+            "No version of {} satisfies {}, required by {}",
+            id,
+            request.range,
+            if request.parents.is_empty() {
+                String::from("the roots")
+            } else {
+                request.parents.join(" -> ")
+            }
+        )
+    })?;
+
+    let index = packages
+        .iter()
+        .position(|package| package.version() == Some(&best))
+        .expect("the best match is one of the packages");
+
+    Ok((packages.swap_remove(index), request))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use package::{PackageProperties, ParsedProperty};
+
+    // The feed, as `id version dependencies` where dependencies are in the V2 format
+    const FEED: &[(&str, &str, &str)] = &[
+        ("A", "1.0.0", "B:1.0:net45|C:[1.0, 2.0):net45|D:1.0:net45"),
+        ("B", "1.0.0", "D:1.0:net45"),
+        ("B", "2.0.0", ""),
+        ("C", "1.0.0", "D:2.0:net45|A:1.0:net45"),
+        ("C", "1.5.0", ""),
+        ("D", "1.0.0", ""),
+        ("D", "2.0.0", ""),
+        ("E", "1.0.0", "D:1.0:netstandard1.0|B:2.0:net45"),
+    ];
+
+    fn package(id: &str, version: &str, dependencies: &str) -> Package {
+        Package::for_test(
+            id,
+            version,
+            PackageProperties {
+                dependencies: ParsedProperty::from(dependencies),
+                ..PackageProperties::default()
+            },
+        )
+    }
+
+    fn resolve(roots: &[(&str, &str)], framework: &str) -> Result<Resolution, Error> {
+        let roots = roots
+            .iter()
+            .map(|&(id, range)| (String::from(id), range.parse().unwrap()))
+            .collect::<Vec<_>>();
+
+        resolve_graph(&roots, &framework.parse().unwrap(), |id| {
+            Ok(FEED
+                .iter()
+                .filter(|&&(feed_id, _, _)| feed_id.eq_ignore_ascii_case(id))
+                .map(|&(id, version, dependencies)| package(id, version, dependencies))
+                .collect())
+        })
+    }
+
+    fn versions(resolution: &Resolution) -> Vec<String> {
+        resolution
+            .packages
+            .iter()
+            .map(|resolved| resolved.package.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn nearest_wins() {
+        let resolution = resolve(&[("A", "1.0")], "net45").unwrap();
+
+        // A's request for D 1.0 is nearer than C's request for D 2.0
+        assert_eq!(
+            versions(&resolution),
+            vec!["A 1.0.0", "B 1.0.0", "C 1.0.0", "D 1.0.0"]
+        );
+        assert_eq!(resolution.get("d").unwrap().path, vec!["A", "D"]);
+        assert_eq!(
+            resolution.downgrades,
+            vec![Downgrade {
+                id: String::from("D"),
+                version: "1.0.0".parse().unwrap(),
+                range: "2.0".parse().unwrap(),
+                path: vec![String::from("A"), String::from("C"), String::from("D")],
+            }]
+        );
+        assert_eq!(resolution.cycles, vec![vec!["A", "C", "A"]]);
+    }
+
+    #[test]
+    fn cousins() {
+        // Both B ranges are at the same depth, so the higher lower bound wins
+        let resolution = resolve(&[("A", "1.0"), ("E", "1.0")], "net45").unwrap();
+
+        assert_eq!(resolution.get("B").unwrap().package.to_string(), "B 2.0.0");
+        assert_eq!(resolution.get("B").unwrap().path, vec!["E", "B"]);
+        assert!(resolution.get("D").is_some());
+    }
+
+    #[test]
+    fn framework_specific_dependencies() {
+        let resolution = resolve(&[("E", "1.0")], "netstandard2.0").unwrap();
+
+        assert_eq!(versions(&resolution), vec!["E 1.0.0", "D 1.0.0"]);
+        assert!(resolve(&[("E", "1.0")], "net40").unwrap().packages.len() == 1);
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        assert!(resolve(&[("A", "[3.0, )")], "net45").is_err());
+        assert!(resolve(&[("Missing", "1.0")], "net45").is_err());
+    }
+}
@@ -0,0 +1,503 @@
+use chrono::{DateTime, TimeZone, Utc};
+use client::Client;
+use failure::Error;
+use package::parse_optional_date;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::vec;
+use url::Url;
+use v3::registration::DependencyGroup;
+use v3::{self as nuget_v3, one_or_many};
+use version::NuGetVersion;
+
+/// Reads a feed's `Catalog/3.0.0` resource, the append-only log of every package change.
+pub struct Catalog<'a> {
+    client: &'a Client,
+    pub index_url: Url,
+}
+
+impl<'a> Catalog<'a> {
+    pub fn new(client: &'a Client) -> Result<Catalog<'a>, Error> {
+        let index_url = client
+            .service_index()
+            .ok_or_else(|| format_err!("{} is not a V3 feed", client.base_url))?
+            .endpoint_url(nuget_v3::CATALOG)?;
+
+        Ok(Catalog { client, index_url })
+    }
+
+    pub fn index(&self) -> Result<CatalogIndex, Error> {
+        self.client.get_json(&self.index_url)
+    }
+
+    pub fn page(&self, page: &CatalogPageRef) -> Result<CatalogPage, Error> {
+        self.client.get_json(&page.url)
+    }
+
+    /// Fetches the leaf document behind a page item.
+    pub fn event(&self, item: &CatalogItem) -> Result<CatalogEvent, Error> {
+        match item.item_type {
+            CatalogItemType::PackageDetails => self
+                .client
+                .get_json(&item.url)
+                .map(|details| CatalogEvent::PackageDetails(Box::new(details))),
+            CatalogItemType::PackageDelete => self
+                .client
+                .get_json(&item.url)
+                .map(|delete| CatalogEvent::PackageDelete(Box::new(delete))),
+        }
+    }
+
+    /// Every change committed strictly after `cursor`, in commit order.
+    ///
+    /// A commit can hold several events, so persist `CatalogEvents::cursor`
+    /// rather than an event's own timestamp after applying each event. It
+    /// only moves past a commit once all of its events have been handed out,
+    /// so resuming after a failure never skips the rest of a commit.
+    pub fn events_since(&self, cursor: Cursor) -> Result<CatalogEvents<'a>, Error> {
+        let cursor = cursor.0;
+        let pages = self.index()?.pages_since(cursor);
+
+        Ok(CatalogEvents {
+            catalog: Catalog {
+                client: self.client,
+                index_url: self.index_url.clone(),
+            },
+            cursor,
+            completed: Cursor(cursor),
+            pages: pages.into_iter(),
+            items: Vec::new().into_iter(),
+            done: false,
+        })
+    }
+}
+
+pub struct CatalogEvents<'a> {
+    catalog: Catalog<'a>,
+    cursor: DateTime<Utc>,
+    completed: Cursor,
+    pages: vec::IntoIter<CatalogPageRef>,
+    items: vec::IntoIter<CatalogItem>,
+    done: bool,
+}
+
+impl<'a> CatalogEvents<'a> {
+    /// The cursor to persist, covering every commit whose events have all been handed out.
+    pub fn cursor(&self) -> Cursor {
+        self.completed
+    }
+
+    fn next_page(&mut self, page: &CatalogPageRef) -> Result<(), Error> {
+        let cursor = self.cursor;
+        let mut items = self
+            .catalog
+            .page(page)?
+            .items
+            .into_iter()
+            .filter(|item| item.commit_timestamp > cursor)
+            .collect::<Vec<_>>();
+
+        // Items aren't guaranteed to be sorted within a page, but pages never overlap.
+        items.sort_by_key(|item| item.commit_timestamp);
+
+        self.items = items.into_iter();
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for CatalogEvents<'a> {
+    type Item = Result<CatalogEvent, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.done {
+                return None;
+            }
+
+            if let Some(item) = self.items.next() {
+                let event = self.catalog.event(&item);
+                self.done = event.is_err();
+
+                // Commits never span pages, so the commit is complete once
+                // the rest of this page belongs to later ones
+                let commit_complete = match self.items.as_slice().first() {
+                    Some(next) => next.commit_timestamp != item.commit_timestamp,
+                    None => true,
+                };
+                if event.is_ok() && commit_complete {
+                    self.completed = Cursor(item.commit_timestamp);
+                }
+
+                return Some(event);
+            }
+
+            match self.pages.next() {
+                Some(page) => {
+                    if let Err(err) = self.next_page(&page) {
+                        self.done = true;
+                        return Some(Err(err));
+                    }
+                }
+                None => return None,
+            }
+        }
+    }
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct CatalogIndex {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    pub commit_id: String,
+    #[serde(rename = "commitTimeStamp")]
+    pub commit_timestamp: DateTime<Utc>,
+    pub count: u64,
+    pub items: Vec<CatalogPageRef>,
+}
+
+impl CatalogIndex {
+    // The pages with commits after `cursor`, oldest first. The index doesn't
+    // promise an order, and reading a newer page first would let the cursor
+    // skip the commits on older ones.
+    fn pages_since(self, cursor: DateTime<Utc>) -> Vec<CatalogPageRef> {
+        let mut pages = self
+            .items
+            .into_iter()
+            .filter(|page| page.commit_timestamp > cursor)
+            .collect::<Vec<_>>();
+
+        pages.sort_by_key(|page| page.commit_timestamp);
+        pages
+    }
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct CatalogPageRef {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    pub commit_id: String,
+    #[serde(rename = "commitTimeStamp")]
+    pub commit_timestamp: DateTime<Utc>,
+    pub count: u64,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct CatalogPage {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    pub commit_id: String,
+    #[serde(rename = "commitTimeStamp")]
+    pub commit_timestamp: DateTime<Utc>,
+    pub count: u64,
+    pub items: Vec<CatalogItem>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct CatalogItem {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    #[serde(rename = "@type")]
+    pub item_type: CatalogItemType,
+    pub commit_id: String,
+    #[serde(rename = "commitTimeStamp")]
+    pub commit_timestamp: DateTime<Utc>,
+    #[serde(rename = "nuget:id")]
+    pub id: String,
+    #[serde(rename = "nuget:version")]
+    pub version: NuGetVersion,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub enum CatalogItemType {
+    #[serde(rename = "nuget:PackageDetails")]
+    PackageDetails,
+    #[serde(rename = "nuget:PackageDelete")]
+    PackageDelete,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CatalogEvent {
+    PackageDetails(Box<PackageDetails>),
+    PackageDelete(Box<PackageDelete>),
+}
+
+impl CatalogEvent {
+    pub fn commit_timestamp(&self) -> DateTime<Utc> {
+        match *self {
+            CatalogEvent::PackageDetails(ref details) => details.commit_timestamp,
+            CatalogEvent::PackageDelete(ref delete) => delete.commit_timestamp,
+        }
+    }
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct PackageDetails {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    #[serde(rename = "catalog:commitId")]
+    pub commit_id: String,
+    #[serde(rename = "catalog:commitTimeStamp")]
+    pub commit_timestamp: DateTime<Utc>,
+    pub id: String,
+    pub version: NuGetVersion,
+    pub verbatim_version: Option<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub authors: Vec<String>,
+    #[serde(default, deserialize_with = "parse_optional_date")]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub dependency_groups: Vec<DependencyGroup>,
+    pub description: Option<String>,
+    pub icon_url: Option<String>,
+    pub is_prerelease: Option<bool>,
+    pub language: Option<String>,
+    #[serde(default, deserialize_with = "parse_optional_date")]
+    pub last_edited: Option<DateTime<Utc>>,
+    pub license_url: Option<String>,
+    pub listed: Option<bool>,
+    pub min_client_version: Option<String>,
+    pub package_hash: Option<String>,
+    pub package_hash_algorithm: Option<String>,
+    pub package_size: Option<u64>,
+    pub project_url: Option<String>,
+    #[serde(default, deserialize_with = "parse_optional_date")]
+    pub published: Option<DateTime<Utc>>,
+    pub require_license_acceptance: Option<bool>,
+    pub summary: Option<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub tags: Vec<String>,
+    pub title: Option<String>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct PackageDelete {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    #[serde(rename = "catalog:commitId")]
+    pub commit_id: String,
+    #[serde(rename = "catalog:commitTimeStamp")]
+    pub commit_timestamp: DateTime<Utc>,
+    pub id: String,
+    pub version: NuGetVersion,
+    pub original_id: Option<String>,
+    #[serde(default, deserialize_with = "parse_optional_date")]
+    pub published: Option<DateTime<Utc>>,
+}
+
+/// The commit timestamp up to which a replica has applied the catalog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cursor(pub DateTime<Utc>);
+
+impl Cursor {
+    /// A cursor from before the first catalog commit.
+    pub fn start() -> Cursor {
+        Cursor(Utc.timestamp_opt(0, 0).unwrap())
+    }
+
+    /// Reads an RFC 3339 timestamp from `path`, starting from scratch if it doesn't exist yet.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Cursor, Error> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            return Ok(Cursor::start());
+        }
+
+        let mut contents = String::new();
+        File::open(path)?.read_to_string(&mut contents)?;
+
+        Ok(Cursor(contents.trim().parse::<DateTime<Utc>>()?))
+    }
+
+    /// Writes the cursor to `path`, replacing it atomically so a crash never leaves it truncated.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let partial = path.with_extension("partial");
+
+        {
+            let mut file = File::create(&partial)?;
+            writeln!(file, "{}", self.0.to_rfc3339())?;
+            file.sync_all()?;
+        }
+
+        fs::rename(&partial, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn catalog_index() {
+        // Trimmed from https://api.nuget.org/v3/catalog0/index.json
+        let index_serialized = r##"{
+  "@id": "https://api.nuget.org/v3/catalog0/index.json",
+  "@type": ["CatalogRoot", "AppendOnlyCatalog", "Permalink"],
+  "commitId": "3a4b3c7a-1a4b-43f4-8b4e-45a5d4a8d8e1",
+  "commitTimeStamp": "2018-10-05T17:32:49.2411214Z",
+  "count": 2,
+  "nuget:lastCreated": "2018-10-05T17:31:27.863Z",
+  "nuget:lastDeleted": "2018-10-05T10:02:11.131Z",
+  "nuget:lastEdited": "2018-10-05T17:31:27.863Z",
+  "items": [
+    {
+      "@id": "https://api.nuget.org/v3/catalog0/page0.json",
+      "@type": "CatalogPage",
+      "commitId": "00000000-0000-0000-0000-000000000000",
+      "commitTimeStamp": "2015-02-01T06:30:11.7477681Z",
+      "count": 540
+    },
+    {
+      "@id": "https://api.nuget.org/v3/catalog0/page1.json",
+      "@type": "CatalogPage",
+      "commitId": "3a4b3c7a-1a4b-43f4-8b4e-45a5d4a8d8e1",
+      "commitTimeStamp": "2018-10-05T17:32:49.2411214Z",
+      "count": 550
+    }
+  ]
+}"##;
+
+        let mut index: CatalogIndex = serde_json::from_str(index_serialized).unwrap();
+
+        assert_eq!(index.count, 2);
+        assert!(index.items[0].commit_timestamp < index.items[1].commit_timestamp);
+        assert_eq!(
+            index.items[1].commit_timestamp,
+            "2018-10-05T17:32:49.2411214Z"
+                .parse::<DateTime<Utc>>()
+                .unwrap()
+        );
+
+        index.items.reverse();
+        let cursor = "2015-01-01T00:00:00Z".parse().unwrap();
+        let pages = index.pages_since(cursor);
+        assert_eq!(pages.len(), 2);
+        assert!(pages[0].url.as_str().ends_with("page0.json"));
+    }
+
+    #[test]
+    fn catalog_page() {
+        // Trimmed from https://api.nuget.org/v3/catalog0/page2.json
+        let page_serialized = r##"{
+  "@id": "https://api.nuget.org/v3/catalog0/page2.json",
+  "@type": "CatalogPage",
+  "commitId": "8c4f1b55-9d1f-4b47-8ef6-8e4bd5d1d0f7",
+  "commitTimeStamp": "2015-02-01T06:30:11.7477681Z",
+  "count": 2,
+  "items": [
+    {
+      "@id": "https://api.nuget.org/v3/catalog0/data/2015.02.01.06.22.45/owin.1.0.0.json",
+      "@type": "nuget:PackageDetails",
+      "commitId": "8c4f1b55-9d1f-4b47-8ef6-8e4bd5d1d0f7",
+      "commitTimeStamp": "2015-02-01T06:30:11.7477681Z",
+      "nuget:id": "Owin",
+      "nuget:version": "1.0.0"
+    },
+    {
+      "@id": "https://api.nuget.org/v3/catalog0/data/2015.02.01.06.22.44/foo.1.0.0.json",
+      "@type": "nuget:PackageDelete",
+      "commitId": "5b1a9c2e-1d7e-4e34-9b4b-1a7f6a1c7c11",
+      "commitTimeStamp": "2015-02-01T06:22:44.5Z",
+      "nuget:id": "Foo",
+      "nuget:version": "1.0.0"
+    }
+  ],
+  "parent": "https://api.nuget.org/v3/catalog0/index.json"
+}"##;
+
+        let page: CatalogPage = serde_json::from_str(page_serialized).unwrap();
+
+        assert_eq!(page.items[0].item_type, CatalogItemType::PackageDetails);
+        assert_eq!(page.items[1].item_type, CatalogItemType::PackageDelete);
+        assert!(page.items[1].commit_timestamp < page.items[0].commit_timestamp);
+    }
+
+    #[test]
+    fn package_details_leaf() {
+        // Trimmed from https://api.nuget.org/v3/catalog0/data/2015.02.01.06.22.45/owin.1.0.0.json
+        let leaf_serialized = r##"{
+  "@id": "https://api.nuget.org/v3/catalog0/data/2015.02.01.06.22.45/owin.1.0.0.json",
+  "@type": ["PackageDetails", "catalog:Permalink"],
+  "authors": "OWIN startup components contributors",
+  "catalog:commitId": "8c4f1b55-9d1f-4b47-8ef6-8e4bd5d1d0f7",
+  "catalog:commitTimeStamp": "2015-02-01T06:30:11.7477681Z",
+  "created": "2012-11-13T20:19:39.207Z",
+  "description": "OWIN IAppBuilder startup interface",
+  "id": "Owin",
+  "isPrerelease": false,
+  "lastEdited": "0001-01-01T00:00:00Z",
+  "listed": true,
+  "packageHash": "OOyOKj7aNgWsjjvC5BlJ1LUa0QhyuqXsk9YiwYcC3hpLEOBl7CQAGHxVmm9oZLpAtRqJbdS6R4CRxVdDGXxkqQ==",
+  "packageHashAlgorithm": "SHA512",
+  "packageSize": 5947,
+  "projectUrl": "https://github.com/owin-contrib/owin-hosting/",
+  "published": "2012-11-13T20:19:39.207Z",
+  "requireLicenseAcceptance": false,
+  "title": "OWIN",
+  "verbatimVersion": "1.0",
+  "version": "1.0.0",
+  "tags": ["owin"]
+}"##;
+
+        let details: PackageDetails = serde_json::from_str(leaf_serialized).unwrap();
+
+        assert_eq!(details.id, "Owin");
+        assert_eq!(details.verbatim_version, Some(String::from("1.0")));
+        assert_eq!(details.package_size, Some(5947));
+
+        let event = CatalogEvent::PackageDetails(Box::new(details));
+        assert_eq!(
+            event.commit_timestamp(),
+            "2015-02-01T06:30:11.7477681Z"
+                .parse::<DateTime<Utc>>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn package_delete_leaf() {
+        let leaf_serialized = r##"{
+  "@id": "https://api.nuget.org/v3/catalog0/data/2015.02.01.06.22.44/foo.1.0.0.json",
+  "@type": ["PackageDelete", "catalog:Permalink"],
+  "catalog:commitId": "5b1a9c2e-1d7e-4e34-9b4b-1a7f6a1c7c11",
+  "catalog:commitTimeStamp": "2015-02-01T06:22:44.5Z",
+  "id": "Foo",
+  "originalId": "foo",
+  "published": "2015-02-01T06:22:44.5Z",
+  "version": "1.0.0"
+}"##;
+
+        let delete: PackageDelete = serde_json::from_str(leaf_serialized).unwrap();
+
+        assert_eq!(delete.id, "Foo");
+        assert_eq!(delete.original_id, Some(String::from("foo")));
+    }
+
+    #[test]
+    fn cursor_round_trip() {
+        let dir = TempDir::new("nougat-cursor").unwrap();
+        let path = dir.path().join("cursor.txt");
+
+        assert_eq!(Cursor::load(&path).unwrap(), Cursor::start());
+
+        let cursor = Cursor(
+            "2018-10-05T17:32:49.2411214Z"
+                .parse::<DateTime<Utc>>()
+                .unwrap(),
+        );
+        cursor.save(&path).unwrap();
+
+        assert_eq!(Cursor::load(&path).unwrap(), cursor);
+    }
+}
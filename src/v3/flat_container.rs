@@ -0,0 +1,127 @@
+use client::Client;
+use failure::Error;
+use std::io::Read;
+use url::Url;
+use v3 as nuget_v3;
+use version::NuGetVersion;
+
+/// Typed access to a feed's `PackageBaseAddress` resource.
+pub struct FlatContainer<'a> {
+    client: &'a Client,
+    pub base_url: Url,
+}
+
+impl<'a> FlatContainer<'a> {
+    pub fn new(client: &'a Client) -> Result<FlatContainer<'a>, Error> {
+        let base_url = client
+            .service_index()
+            .ok_or_else(|| format_err!("{} is not a V3 feed", client.base_url))?
+            .resource_url(nuget_v3::PACKAGE_BASE_ADDRESS)?;
+
+        Ok(FlatContainer { client, base_url })
+    }
+
+    /// Every version of the package, including unlisted ones.
+    pub fn versions(&self, id: &str) -> Result<Vec<NuGetVersion>, Error> {
+        let versions: PackageVersions = self.client.get_json(&versions_url(&self.base_url, id)?)?;
+        Ok(versions.versions)
+    }
+
+    pub fn download(&self, id: &str, version: &NuGetVersion) -> Result<Vec<u8>, Error> {
+        self.get_bytes(&package_content_url(&self.base_url, id, version)?)
+    }
+
+    pub fn nuspec(&self, id: &str, version: &NuGetVersion) -> Result<Vec<u8>, Error> {
+        self.get_bytes(&nuspec_url(&self.base_url, id, version)?)
+    }
+
+    fn get_bytes(&self, url: &Url) -> Result<Vec<u8>, Error> {
+        let mut buffer = vec![];
+        self.client.get(url)?.read_to_end(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+/// The `{id-lower}/index.json` document listing every version of a package.
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct PackageVersions {
+    pub versions: Vec<NuGetVersion>,
+}
+
+pub fn versions_url(base_url: &Url, id: &str) -> Result<Url, Error> {
+    base_url
+        .join(&format!("{}/index.json", id.to_lowercase()))
+        .map_err(|e| e.into())
+}
+
+pub fn package_content_url(
+    base_url: &Url,
+    id: &str,
+    version: &NuGetVersion,
+) -> Result<Url, Error> {
+    let id = id.to_lowercase();
+    let version = version.to_normalized_string().to_lowercase();
+
+    base_url
+        .join(&format!("{}/{}/{}.{}.nupkg", id, version, id, version))
+        .map_err(|e| e.into())
+}
+
+pub fn nuspec_url(base_url: &Url, id: &str, version: &NuGetVersion) -> Result<Url, Error> {
+    let id = id.to_lowercase();
+    let version = version.to_normalized_string().to_lowercase();
+
+    base_url
+        .join(&format!("{}/{}/{}.nuspec", id, version, id))
+        .map_err(|e| e.into())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use super::*;
+
+    #[test]
+    fn package_versions() {
+        // From https://api.nuget.org/v3-flatcontainer/owin/index.json
+        let versions_serialized = r##"{
+  "versions": [
+    "0.5.0",
+    "0.7.0",
+    "1.0.0"
+  ]
+}"##;
+
+        let versions: PackageVersions = serde_json::from_str(versions_serialized).unwrap();
+
+        assert_eq!(
+            versions.versions,
+            vec![
+                NuGetVersion::new(0, 5, 0),
+                NuGetVersion::new(0, 7, 0),
+                NuGetVersion::new(1, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn urls() {
+        let base_url = Url::parse("https://api.nuget.org/v3-flatcontainer/").unwrap();
+
+        assert_eq!(
+            versions_url(&base_url, "Owin").unwrap().as_str(),
+            "https://api.nuget.org/v3-flatcontainer/owin/index.json"
+        );
+        assert_eq!(
+            package_content_url(&base_url, "Owin", &"1.0.0-Beta".parse().unwrap())
+                .unwrap()
+                .as_str(),
+            "https://api.nuget.org/v3-flatcontainer/owin/1.0.0-beta/owin.1.0.0-beta.nupkg"
+        );
+        assert_eq!(
+            nuspec_url(&base_url, "Owin", &"1.0".parse().unwrap()).unwrap().as_str(),
+            "https://api.nuget.org/v3-flatcontainer/owin/1.0.0/owin.nuspec"
+        );
+    }
+}
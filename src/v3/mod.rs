@@ -0,0 +1,179 @@
+use serde::{Deserialize, Deserializer};
+use url::Url;
+
+pub mod catalog;
+pub mod flat_container;
+pub mod registration;
+pub mod search;
+
+// Resource types, from https://docs.microsoft.com/en-us/nuget/api/overview#resources-and-schema
+pub const CATALOG: &[&str] = &["Catalog/3.0.0"];
+pub const PACKAGE_BASE_ADDRESS: &[&str] = &["PackageBaseAddress/3.0.0"];
+pub const PACKAGE_PUBLISH: &[&str] = &["PackagePublish/2.0.0"];
+pub const REGISTRATIONS_BASE_URL: &[&str] = &[
+    "RegistrationsBaseUrl/3.6.0",
+    "RegistrationsBaseUrl/3.4.0",
+    "RegistrationsBaseUrl/3.0.0-rc",
+    "RegistrationsBaseUrl/3.0.0-beta",
+    "RegistrationsBaseUrl",
+];
+pub const SEARCH_AUTOCOMPLETE_SERVICE: &[&str] = &[
+    "SearchAutocompleteService/3.5.0",
+    "SearchAutocompleteService/3.0.0-rc",
+    "SearchAutocompleteService/3.0.0-beta",
+    "SearchAutocompleteService",
+];
+pub const SEARCH_QUERY_SERVICE: &[&str] = &[
+    "SearchQueryService/3.5.0",
+    "SearchQueryService/3.0.0-rc",
+    "SearchQueryService/3.0.0-beta",
+    "SearchQueryService",
+];
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct ServiceIndex {
+    pub version: String,
+    pub resources: Vec<Resource>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Resource {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    #[serde(rename = "@type")]
+    pub resource_type: String,
+    pub comment: Option<String>,
+}
+
+impl ServiceIndex {
+    /// Finds the first resource matching one of `resource_types`, in order of preference.
+    pub fn resource(&self, resource_types: &[&str]) -> Option<&Resource> {
+        resource_types
+            .iter()
+            .filter_map(|resource_type| {
+                self.resources
+                    .iter()
+                    .find(|resource| resource.resource_type == *resource_type)
+            })
+            .next()
+    }
+
+    /// The URL of an endpoint resource, failing if the feed doesn't provide any of `resource_types`.
+    pub fn endpoint_url(&self, resource_types: &[&str]) -> Result<Url, ::failure::Error> {
+        self.resource(resource_types)
+            .map(|resource| resource.url.clone())
+            .ok_or_else(|| match resource_types.first() {
+                Some(resource_type) => format_err!("Service index has no {} resource", resource_type),
+                None => format_err!("Service index has no matching resource"),
+            })
+    }
+
+    /// The URL of a base address resource, with a trailing '/' so that paths can be joined onto it.
+    pub fn resource_url(&self, resource_types: &[&str]) -> Result<Url, ::failure::Error> {
+        let mut url = self.endpoint_url(resource_types)?;
+
+        // URI joiners will replace the last path segment if the
+        // resource URI doesn't end in '/'
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+
+        Ok(url)
+    }
+}
+
+/// V3 documents are inconsistent about whether some fields (`authors`,
+/// `owners`, `tags`) are a single string or a list of strings.
+pub(crate) fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+        Some(OneOrMany::One(ref value)) if value.is_empty() => vec![],
+        Some(OneOrMany::One(value)) => vec![value],
+        Some(OneOrMany::Many(values)) => values,
+        None => vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use super::*;
+
+    #[test]
+    fn nuget_gallery_service_index() {
+        // Trimmed from https://api.nuget.org/v3/index.json
+        let index_serialized = r##"{
+  "version": "3.0.0",
+  "resources": [
+    {
+      "@id": "https://azuresearch-usnc.nuget.org/query",
+      "@type": "SearchQueryService",
+      "comment": "Query endpoint of NuGet Search service (primary)"
+    },
+    {
+      "@id": "https://azuresearch-usnc.nuget.org/query",
+      "@type": "SearchQueryService/3.5.0",
+      "comment": "Query endpoint of NuGet Search service (primary) used by RC clients"
+    },
+    {
+      "@id": "https://api.nuget.org/v3-flatcontainer/",
+      "@type": "PackageBaseAddress/3.0.0",
+      "comment": "Base URL of where NuGet packages are stored, in the format https://api.nuget.org/v3-flatcontainer/{id-lower}/{version-lower}/{id-lower}.{version-lower}.nupkg"
+    },
+    {
+      "@id": "https://www.nuget.org/api/v2/package",
+      "@type": "PackagePublish/2.0.0"
+    },
+    {
+      "@id": "https://api.nuget.org/v3/registration5-semver1/",
+      "@type": "RegistrationsBaseUrl",
+      "comment": "Base URL of Azure storage where NuGet package registration info is stored"
+    },
+    {
+      "@id": "https://api.nuget.org/v3/registration5-gz-semver2/",
+      "@type": "RegistrationsBaseUrl/3.6.0",
+      "comment": "Base URL of Azure storage where NuGet package registration info is stored in GZIP format. This base URL includes SemVer 2.0.0 packages."
+    }
+  ],
+  "@context": {
+    "@vocab": "http://schema.nuget.org/services#",
+    "comment": "http://www.w3.org/2000/01/rdf-schema#comment"
+  }
+}"##;
+
+        let index: ServiceIndex = serde_json::from_str(index_serialized).unwrap();
+
+        assert_eq!(index.version, "3.0.0");
+        assert_eq!(index.resources.len(), 6);
+
+        assert_eq!(
+            index.resource(SEARCH_QUERY_SERVICE).unwrap().resource_type,
+            "SearchQueryService/3.5.0"
+        );
+        assert_eq!(
+            index.resource_url(REGISTRATIONS_BASE_URL).unwrap().as_str(),
+            "https://api.nuget.org/v3/registration5-gz-semver2/"
+        );
+        assert_eq!(
+            index.resource_url(PACKAGE_PUBLISH).unwrap().as_str(),
+            "https://www.nuget.org/api/v2/package/"
+        );
+        assert_eq!(
+            index.endpoint_url(SEARCH_QUERY_SERVICE).unwrap().as_str(),
+            "https://azuresearch-usnc.nuget.org/query"
+        );
+        assert!(index.resource(&["Catalog/3.0.0"]).is_none());
+        assert!(index.resource_url(&["Catalog/3.0.0"]).is_err());
+    }
+}
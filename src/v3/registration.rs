@@ -0,0 +1,562 @@
+use chrono::{DateTime, TimeZone, Utc};
+use client::Client;
+use failure::Error;
+use framework::NuGetFramework;
+use package::{
+    parse_optional_date, Author, Package, PackageCategory, PackageContent, PackageProperties,
+    ParsedProperty, Published, UrlProperty,
+};
+use serde::{Deserialize, Deserializer};
+use url::Url;
+use v3::{self as nuget_v3, one_or_many};
+use version::{NuGetVersion, VersionRange};
+
+/// Typed access to a feed's `RegistrationsBaseUrl` resource.
+pub struct Registrations<'a> {
+    client: &'a Client,
+    pub base_url: Url,
+}
+
+impl<'a> Registrations<'a> {
+    pub fn new(client: &'a Client) -> Result<Registrations<'a>, Error> {
+        let base_url = client
+            .service_index()
+            .ok_or_else(|| format_err!("{} is not a V3 feed", client.base_url))?
+            .resource_url(nuget_v3::REGISTRATIONS_BASE_URL)?;
+
+        Ok(Registrations { client, base_url })
+    }
+
+    pub fn index(&self, id: &str) -> Result<RegistrationIndex, Error> {
+        self.client.get_json(&index_url(&self.base_url, id)?)
+    }
+
+    /// The leaves of `page`, fetching it first if the index didn't inline them.
+    pub fn page_leaves(&self, page: RegistrationPage) -> Result<Vec<RegistrationLeaf>, Error> {
+        match page.items {
+            Some(leaves) => Ok(leaves),
+            None => {
+                let page: RegistrationPage = self.client.get_json(&page.url)?;
+                Ok(page.items.unwrap_or_default())
+            }
+        }
+    }
+
+    /// Every leaf of the registration, in ascending version order.
+    pub fn leaves(&self, id: &str) -> Result<Vec<RegistrationLeaf>, Error> {
+        let mut leaves = Vec::new();
+
+        for page in self.index(id)?.items {
+            leaves.append(&mut self.page_leaves(page)?);
+        }
+
+        Ok(leaves)
+    }
+
+    /// The standalone leaf document for a single version.
+    pub fn leaf(
+        &self,
+        id: &str,
+        version: &NuGetVersion,
+    ) -> Result<RegistrationLeafDocument, Error> {
+        let url = self.base_url.join(&format!(
+            "{}/{}.json",
+            id.to_lowercase(),
+            version.to_normalized_string().to_lowercase()
+        ))?;
+
+        self.client.get_json(&url)
+    }
+
+    /// Every version of the package, with the V2 latest version flags filled in.
+    pub fn packages(&self, id: &str) -> Result<Vec<Package>, Error> {
+        let mut packages = self
+            .leaves(id)?
+            .into_iter()
+            .map(RegistrationLeaf::into_package)
+            .collect::<Vec<_>>();
+
+        let is_listed = |package: &Package| package.properties.is_listed.unwrap_or(true);
+
+        if let Some(package) = packages.iter_mut().rev().find(|p| is_listed(p)) {
+            package.properties.is_absolute_latest_version = true;
+        }
+
+        if let Some(package) = packages
+            .iter_mut()
+            .rev()
+            .find(|p| is_listed(p) && !p.properties.is_prerelease)
+        {
+            package.properties.is_latest_version = true;
+        }
+
+        Ok(packages)
+    }
+}
+
+/// The `{id-lower}/index.json` document under `RegistrationsBaseUrl`.
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct RegistrationIndex {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    pub count: u64,
+    pub items: Vec<RegistrationPage>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct RegistrationPage {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    pub count: u64,
+    /// Absent when the page has to be fetched separately from `url`.
+    pub items: Option<Vec<RegistrationLeaf>>,
+    pub lower: String,
+    pub upper: String,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct RegistrationLeaf {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    pub catalog_entry: CatalogEntry,
+    pub package_content: Url,
+}
+
+/// The `{id-lower}/{version-lower}.json` document, which links to
+/// the catalog entry rather than inlining it.
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct RegistrationLeafDocument {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    pub catalog_entry: Url,
+    pub listed: Option<bool>,
+    pub package_content: Url,
+    #[serde(default, deserialize_with = "parse_optional_date")]
+    pub published: Option<DateTime<Utc>>,
+    pub registration: Option<Url>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct CatalogEntry {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub dependency_groups: Vec<DependencyGroup>,
+    pub deprecation: Option<Deprecation>,
+    pub description: Option<String>,
+    pub icon_url: Option<String>,
+    pub id: String,
+    pub language: Option<String>,
+    pub license_expression: Option<String>,
+    pub license_url: Option<String>,
+    pub listed: Option<bool>,
+    pub min_client_version: Option<String>,
+    pub project_url: Option<String>,
+    #[serde(default, deserialize_with = "parse_optional_date")]
+    pub published: Option<DateTime<Utc>>,
+    pub require_license_acceptance: Option<bool>,
+    pub summary: Option<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub tags: Vec<String>,
+    pub title: Option<String>,
+    pub version: ParsedProperty<NuGetVersion>,
+    #[serde(default)]
+    pub vulnerabilities: Vec<Vulnerability>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct DependencyGroup {
+    #[serde(default)]
+    pub target_framework: ParsedProperty<NuGetFramework>,
+    #[serde(default)]
+    pub dependencies: Vec<Dependency>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Dependency {
+    pub id: String,
+    #[serde(default)]
+    pub range: ParsedProperty<VersionRange>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Deprecation {
+    /// Any of `Legacy`, `CriticalBugs` or `Other`.
+    pub reasons: Vec<String>,
+    pub message: Option<String>,
+    pub alternate_package: Option<AlternatePackage>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct AlternatePackage {
+    pub id: String,
+    pub range: Option<VersionRange>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Vulnerability {
+    pub advisory_url: Url,
+    pub severity: Severity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// A severity newer than this client, which is logged when it's read.
+    Unknown,
+    Low,
+    Moderate,
+    High,
+    Critical,
+}
+
+impl<'de> Deserialize<'de> for Severity {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Severities are sent as stringified numbers
+        match String::deserialize(deserializer)?.as_str() {
+            "0" => Ok(Severity::Low),
+            "1" => Ok(Severity::Moderate),
+            "2" => Ok(Severity::High),
+            "3" => Ok(Severity::Critical),
+            other => {
+                warn!("Unknown vulnerability severity {}", other);
+                Ok(Severity::Unknown)
+            }
+        }
+    }
+}
+
+pub fn index_url(base_url: &Url, id: &str) -> Result<Url, Error> {
+    base_url
+        .join(&format!("{}/index.json", id.to_lowercase()))
+        .map_err(|e| e.into())
+}
+
+impl RegistrationLeaf {
+    pub fn into_package(self) -> Package {
+        let entry = self.catalog_entry;
+
+        // Written out in the V2 format, so a malformed framework or range is kept the same way
+        let mut dependencies = Vec::new();
+        for group in &entry.dependency_groups {
+            let framework = group.target_framework.raw();
+
+            if group.dependencies.is_empty() {
+                dependencies.push(format!("::{}", framework));
+            }
+
+            for dependency in &group.dependencies {
+                dependencies.push(format!(
+                    "{}:{}:{}",
+                    dependency.id,
+                    dependency.range.raw(),
+                    framework
+                ));
+            }
+        }
+
+        let authors = entry.authors.join(", ");
+        let published = entry.published;
+
+        Package {
+            id: entry.url.to_string(),
+            category: PackageCategory {
+                term: String::from("PackageDetails"),
+            },
+            title: entry.id.clone(),
+            summary: entry.summary.clone(),
+            content: PackageContent {
+                mime_type: String::from("application/zip"),
+                url: self.package_content,
+            },
+            updated_at: published.unwrap_or_else(|| Utc.timestamp_opt(0, 0).unwrap()),
+            author: Author {
+                name: authors.clone(),
+            },
+            properties: PackageProperties {
+                dependencies: ParsedProperty::from(dependencies.join("|")),
+                description: entry.description.unwrap_or_default(),
+                is_prerelease: entry
+                    .version
+                    .value()
+                    .map_or(false, NuGetVersion::is_prerelease),
+                project_url: UrlProperty::from(entry.project_url),
+                require_license_acceptance: entry.require_license_acceptance.unwrap_or(false),
+                tags: entry.tags.join(" "),
+                title: entry.title.unwrap_or_default(),
+                authors: Some(authors),
+                icon_url: UrlProperty::from(entry.icon_url),
+                id: Some(entry.id),
+                is_listed: entry.listed,
+                language: entry.language,
+                license_url: UrlProperty::from(entry.license_url),
+                minimum_client_version: entry.min_client_version,
+                normalized_version: entry.version.value().cloned(),
+                published_at: published.map(Published::from),
+                summary: entry.summary,
+                version: entry.version,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use super::*;
+
+    #[test]
+    fn inline_registration_index() {
+        // Trimmed from https://api.nuget.org/v3/registration3/owin/index.json
+        let index_serialized = r##"{
+  "@id": "https://api.nuget.org/v3/registration3/owin/index.json",
+  "@type": ["catalog:CatalogRoot", "PackageRegistration", "catalog:Permalink"],
+  "commitId": "5b37c1ae-3f5a-4a19-9de8-0f3b3f4b4a8d",
+  "commitTimeStamp": "2018-10-05T17:32:49.2411214Z",
+  "count": 1,
+  "items": [
+    {
+      "@id": "https://api.nuget.org/v3/registration3/owin/index.json#page/1.0.0/1.0.0",
+      "@type": "catalog:CatalogPage",
+      "count": 1,
+      "items": [
+        {
+          "@id": "https://api.nuget.org/v3/registration3/owin/1.0.0.json",
+          "@type": "Package",
+          "catalogEntry": {
+            "@id": "https://api.nuget.org/v3/catalog0/data/2015.02.01.06.22.45/owin.1.0.0.json",
+            "@type": "PackageDetails",
+            "authors": "OWIN startup components contributors",
+            "dependencyGroups": [
+              {
+                "@id": "https://api.nuget.org/v3/catalog0/data/2015.02.01.06.22.45/owin.1.0.0.json#dependencygroup/.netframework4.0",
+                "@type": "PackageDependencyGroup",
+                "targetFramework": ".NETFramework4.0"
+              }
+            ],
+            "description": "OWIN IAppBuilder startup interface",
+            "iconUrl": "",
+            "id": "Owin",
+            "language": "",
+            "licenseUrl": "https://github.com/owin-contrib/owin-hosting/blob/master/LICENSE.txt",
+            "listed": true,
+            "minClientVersion": "",
+            "packageContent": "https://api.nuget.org/packages/owin.1.0.0.nupkg",
+            "projectUrl": "https://github.com/owin-contrib/owin-hosting/",
+            "published": "2012-11-13T20:19:39.207+00:00",
+            "requireLicenseAcceptance": false,
+            "summary": "",
+            "tags": ["owin"],
+            "title": "OWIN",
+            "version": "1.0.0"
+          },
+          "packageContent": "https://api.nuget.org/v3-flatcontainer/owin/1.0.0/owin.1.0.0.nupkg",
+          "registration": "https://api.nuget.org/v3/registration3/owin/index.json"
+        }
+      ],
+      "parent": "https://api.nuget.org/v3/registration3/owin/index.json",
+      "lower": "1.0.0",
+      "upper": "1.0.0"
+    }
+  ]
+}"##;
+
+        let index: RegistrationIndex = serde_json::from_str(index_serialized).unwrap();
+
+        assert_eq!(index.count, 1);
+        assert_eq!(index.items[0].lower, "1.0.0");
+
+        let leaf = index
+            .items
+            .into_iter()
+            .next()
+            .unwrap()
+            .items
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap();
+
+        assert_eq!(
+            leaf.catalog_entry.authors,
+            vec!["OWIN startup components contributors"]
+        );
+        assert_eq!(leaf.catalog_entry.tags, vec!["owin"]);
+
+        let package = leaf.into_package();
+
+        assert_eq!(package.id(), "Owin");
+        assert_eq!(package.version(), Some(&NuGetVersion::new(1, 0, 0)));
+        assert_eq!(format!("{}", package), "Owin 1.0.0");
+        assert_eq!(
+            package.content.url.as_str(),
+            "https://api.nuget.org/v3-flatcontainer/owin/1.0.0/owin.1.0.0.nupkg"
+        );
+        assert_eq!(
+            package.dependencies(),
+            Some(&::package::DependencyGroups::from(vec![
+                ::package::DependencyGroup {
+                    framework: ".NETFramework4.0".parse().unwrap(),
+                    dependencies: vec![],
+                }
+            ]))
+        );
+        assert_eq!(package.raw_dependencies(), "::.NETFramework4.0");
+    }
+
+    #[test]
+    fn malformed_dependency_group() {
+        let group: DependencyGroup = serde_json::from_str(
+            r#"{"targetFramework": "portable-", "dependencies": [{"id": "A", "range": "[1.0"}]}"#,
+        ).unwrap();
+
+        assert_eq!(group.target_framework.value(), None);
+        assert_eq!(group.target_framework.raw(), "portable-");
+        assert_eq!(group.dependencies[0].range.value(), None);
+    }
+
+    #[test]
+    fn paged_registration_index() {
+        // Trimmed from https://api.nuget.org/v3/registration3/newtonsoft.json/index.json
+        let index_serialized = r##"{
+  "@id": "https://api.nuget.org/v3/registration3/newtonsoft.json/index.json",
+  "count": 2,
+  "items": [
+    {
+      "@id": "https://api.nuget.org/v3/registration3/newtonsoft.json/page/3.5.8/8.0.1-beta3.json",
+      "@type": "catalog:CatalogPage",
+      "count": 64,
+      "lower": "3.5.8",
+      "upper": "8.0.1-beta3"
+    },
+    {
+      "@id": "https://api.nuget.org/v3/registration3/newtonsoft.json/page/8.0.1/12.0.1-beta1.json",
+      "@type": "catalog:CatalogPage",
+      "count": 12,
+      "lower": "8.0.1",
+      "upper": "12.0.1-beta1"
+    }
+  ]
+}"##;
+
+        let index: RegistrationIndex = serde_json::from_str(index_serialized).unwrap();
+
+        assert_eq!(index.items.len(), 2);
+        assert!(index.items.iter().all(|page| page.items.is_none()));
+        assert_eq!(index.items[1].upper, "12.0.1-beta1");
+    }
+
+    #[test]
+    fn deprecated_vulnerable_catalog_entry() {
+        // Trimmed from https://api.nuget.org/v3/registration5-semver1/microsoft.data.odata/5.2.0.json
+        let entry_serialized = r##"{
+  "@id": "https://api.nuget.org/v3/catalog0/data/2018.10.15.01.12.32/microsoft.data.odata.5.2.0.json",
+  "@type": "PackageDetails",
+  "authors": "Microsoft",
+  "deprecation": {
+    "@id": "https://api.nuget.org/v3/catalog0/data/2018.10.15.01.12.32/microsoft.data.odata.5.2.0.json#deprecation",
+    "message": "Use the supported OData library instead.",
+    "reasons": ["Legacy", "CriticalBugs"],
+    "alternatePackage": {
+      "@id": "https://api.nuget.org/v3/catalog0/data/2018.10.15.01.12.32/microsoft.data.odata.5.2.0.json#deprecation/alternatePackage",
+      "id": "Microsoft.OData.Core",
+      "range": "*"
+    }
+  },
+  "id": "Microsoft.Data.OData",
+  "licenseExpression": "",
+  "listed": true,
+  "version": "5.2.0",
+  "vulnerabilities": [
+    {
+      "@id": "https://api.nuget.org/v3/catalog0/data/2018.10.15.01.12.32/microsoft.data.odata.5.2.0.json#vulnerability/GitHub/999",
+      "@type": "Vulnerability",
+      "advisoryUrl": "https://github.com/advisories/GHSA-mv2r-q4g5-j8q5",
+      "severity": "2"
+    }
+  ]
+}"##;
+
+        let entry: CatalogEntry = serde_json::from_str(entry_serialized).unwrap();
+
+        assert_eq!(
+            entry.deprecation,
+            Some(Deprecation {
+                reasons: vec![String::from("Legacy"), String::from("CriticalBugs")],
+                message: Some(String::from("Use the supported OData library instead.")),
+                alternate_package: Some(AlternatePackage {
+                    id: String::from("Microsoft.OData.Core"),
+                    range: Some("*".parse().unwrap()),
+                }),
+            })
+        );
+        assert_eq!(
+            entry.vulnerabilities,
+            vec![Vulnerability {
+                advisory_url: Url::parse("https://github.com/advisories/GHSA-mv2r-q4g5-j8q5")
+                    .unwrap(),
+                severity: Severity::High,
+            }]
+        );
+        assert!(entry.dependency_groups.is_empty());
+    }
+
+    #[test]
+    fn unexpected_catalog_entry_values() {
+        let entry_serialized = r##"{
+  "@id": "https://api.nuget.org/v3/catalog0/data/2018.10.15.01.12.32/foo.json",
+  "id": "Foo",
+  "version": "not a version",
+  "vulnerabilities": [
+    {
+      "advisoryUrl": "https://github.com/advisories/GHSA-mv2r-q4g5-j8q5",
+      "severity": "4"
+    }
+  ]
+}"##;
+
+        let entry: CatalogEntry = serde_json::from_str(entry_serialized).unwrap();
+
+        assert_eq!(entry.version.value(), None);
+        assert_eq!(entry.version.raw(), "not a version");
+        assert_eq!(entry.vulnerabilities[0].severity, Severity::Unknown);
+    }
+
+    #[test]
+    fn registration_leaf_document() {
+        // From https://api.nuget.org/v3/registration3/owin/1.0.0.json
+        let leaf_serialized = r##"{
+  "@id": "https://api.nuget.org/v3/registration3/owin/1.0.0.json",
+  "@type": ["Package", "http://schema.nuget.org/catalog#Permalink"],
+  "catalogEntry": "https://api.nuget.org/v3/catalog0/data/2015.02.01.06.22.45/owin.1.0.0.json",
+  "listed": true,
+  "packageContent": "https://api.nuget.org/v3-flatcontainer/owin/1.0.0/owin.1.0.0.nupkg",
+  "published": "2012-11-13T20:19:39.207+00:00",
+  "registration": "https://api.nuget.org/v3/registration3/owin/index.json"
+}"##;
+
+        let leaf: RegistrationLeafDocument = serde_json::from_str(leaf_serialized).unwrap();
+
+        assert_eq!(
+            leaf.catalog_entry.as_str(),
+            "https://api.nuget.org/v3/catalog0/data/2015.02.01.06.22.45/owin.1.0.0.json"
+        );
+        assert_eq!(leaf.listed, Some(true));
+    }
+}
@@ -0,0 +1,433 @@
+use chrono::{TimeZone, Utc};
+use client::Client;
+use failure::Error;
+use package::{
+    Author, Package, PackageCategory, PackageContent, PackageProperties, ParsedProperty,
+    UrlProperty,
+};
+use url::Url;
+use v3::{self as nuget_v3, one_or_many};
+use version::NuGetVersion;
+
+/// Typed access to a feed's `SearchQueryService` resource.
+pub struct Search<'a> {
+    client: &'a Client,
+    pub url: Url,
+}
+
+impl<'a> Search<'a> {
+    pub fn new(client: &'a Client) -> Result<Search<'a>, Error> {
+        let url = client
+            .service_index()
+            .ok_or_else(|| format_err!("{} is not a V3 feed", client.base_url))?
+            .endpoint_url(nuget_v3::SEARCH_QUERY_SERVICE)?;
+
+        Ok(Search { client, url })
+    }
+
+    pub fn search(&self, query: &SearchQuery) -> Result<SearchResponse, Error> {
+        let mut url = self.url.clone();
+        url.query_pairs_mut().extend_pairs(query.query_pairs());
+
+        self.client.get_json(&url)
+    }
+}
+
+/// The parameters of a V3 search, rendered into the query string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    q: String,
+    skip: Option<u64>,
+    take: Option<u64>,
+    prerelease: bool,
+    sem_ver_level: Option<String>,
+    package_type: Option<String>,
+}
+
+impl SearchQuery {
+    pub fn new(q: &str) -> SearchQuery {
+        SearchQuery {
+            q: String::from(q),
+            skip: None,
+            take: None,
+            prerelease: false,
+            sem_ver_level: Some(String::from("2.0.0")),
+            package_type: None,
+        }
+    }
+
+    pub fn skip(mut self, skip: u64) -> SearchQuery {
+        self.skip = Some(skip);
+        self
+    }
+
+    pub fn take(mut self, take: u64) -> SearchQuery {
+        self.take = Some(take);
+        self
+    }
+
+    pub fn prerelease(mut self, prerelease: bool) -> SearchQuery {
+        self.prerelease = prerelease;
+        self
+    }
+
+    /// `2.0.0` (the default) includes SemVer 2.0.0 packages, `None` hides them.
+    pub fn sem_ver_level(mut self, sem_ver_level: Option<&str>) -> SearchQuery {
+        self.sem_ver_level = sem_ver_level.map(String::from);
+        self
+    }
+
+    /// Only return packages of the given type, e.g. `Dependency` or `DotnetTool`.
+    pub fn package_type(mut self, package_type: &str) -> SearchQuery {
+        self.package_type = Some(String::from(package_type));
+        self
+    }
+
+    pub fn query_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = vec![("q", self.q.clone())];
+
+        if let Some(skip) = self.skip {
+            pairs.push(("skip", skip.to_string()));
+        }
+
+        if let Some(take) = self.take {
+            pairs.push(("take", take.to_string()));
+        }
+
+        pairs.push(("prerelease", self.prerelease.to_string()));
+
+        if let Some(ref sem_ver_level) = self.sem_ver_level {
+            pairs.push(("semVerLevel", sem_ver_level.clone()));
+        }
+
+        if let Some(ref package_type) = self.package_type {
+            pairs.push(("packageType", package_type.clone()));
+        }
+
+        pairs
+    }
+}
+
+/// Typed access to a feed's `SearchAutocompleteService` resource.
+pub struct Autocomplete<'a> {
+    client: &'a Client,
+    pub url: Url,
+}
+
+impl<'a> Autocomplete<'a> {
+    pub fn new(client: &'a Client) -> Result<Autocomplete<'a>, Error> {
+        let url = client
+            .service_index()
+            .ok_or_else(|| format_err!("{} is not a V3 feed", client.base_url))?
+            .endpoint_url(nuget_v3::SEARCH_AUTOCOMPLETE_SERVICE)?;
+
+        Ok(Autocomplete { client, url })
+    }
+
+    /// Package ids matching `prefix`.
+    pub fn ids(
+        &self,
+        prefix: &str,
+        skip: u64,
+        take: u64,
+        prerelease: bool,
+    ) -> Result<AutocompleteResponse, Error> {
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .append_pair("q", prefix)
+            .append_pair("skip", &skip.to_string())
+            .append_pair("take", &take.to_string())
+            .append_pair("prerelease", &prerelease.to_string())
+            .append_pair("semVerLevel", "2.0.0");
+
+        self.client.get_json(&url)
+    }
+
+    /// Every listed version of the package `id`.
+    pub fn versions(&self, id: &str, prerelease: bool) -> Result<Vec<NuGetVersion>, Error> {
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .append_pair("id", id)
+            .append_pair("prerelease", &prerelease.to_string())
+            .append_pair("semVerLevel", "2.0.0");
+
+        let response: AutocompleteResponse = self.client.get_json(&url)?;
+        response
+            .data
+            .iter()
+            .map(|version| version.parse())
+            .collect()
+    }
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct AutocompleteResponse {
+    /// Only sent for id prefix queries.
+    pub total_hits: Option<u64>,
+    pub data: Vec<String>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct SearchResponse {
+    pub total_hits: u64,
+    pub data: Vec<SearchResult>,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct SearchResult {
+    pub id: String,
+    pub version: NuGetVersion,
+    pub description: Option<String>,
+    pub summary: Option<String>,
+    pub title: Option<String>,
+    pub icon_url: Option<String>,
+    pub license_url: Option<String>,
+    pub project_url: Option<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub tags: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub authors: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub owners: Vec<String>,
+    pub registration: Option<Url>,
+    pub total_downloads: Option<i64>,
+    pub verified: Option<bool>,
+    #[serde(default)]
+    pub package_types: Vec<PackageType>,
+    #[serde(default)]
+    pub versions: Vec<SearchResultVersion>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct PackageType {
+    pub name: String,
+}
+
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct SearchResultVersion {
+    #[serde(rename = "@id")]
+    pub url: Url,
+    pub version: NuGetVersion,
+    pub downloads: i64,
+}
+
+impl SearchResult {
+    /// Converts the result's latest version into a `Package`, downloadable from `package_content`.
+    pub fn into_package(self, package_content: Url) -> Package {
+        let authors = self.authors.join(", ");
+        let total_downloads = self.total_downloads.unwrap_or(0);
+        let version_downloads = self
+            .versions
+            .iter()
+            .find(|version| version.version == self.version)
+            .map(|version| version.downloads)
+            .unwrap_or(0);
+
+        Package {
+            id: self
+                .versions
+                .iter()
+                .find(|version| version.version == self.version)
+                .map(|version| version.url.to_string())
+                .unwrap_or_else(|| self.id.clone()),
+            category: PackageCategory {
+                term: String::from("Package"),
+            },
+            title: self.id.clone(),
+            summary: self.summary.clone(),
+            content: PackageContent {
+                mime_type: String::from("application/zip"),
+                url: package_content,
+            },
+            // Search results carry no timestamps
+            updated_at: Utc.timestamp_opt(0, 0).unwrap(),
+            author: Author {
+                name: authors.clone(),
+            },
+            properties: PackageProperties {
+                description: self.description.unwrap_or_default(),
+                download_count: clamp(total_downloads),
+                is_latest_version: !self.version.is_prerelease(),
+                // A hit's versions omit prereleases unless they were requested, so
+                // it can't tell whether a newer prerelease exists
+                is_absolute_latest_version: false,
+                is_prerelease: self.version.is_prerelease(),
+                project_url: UrlProperty::from(self.project_url),
+                tags: self.tags.join(" "),
+                title: self.title.unwrap_or_default(),
+                authors: Some(authors),
+                owners: Some(self.owners.join(", ")),
+                icon_url: UrlProperty::from(self.icon_url),
+                id: Some(self.id),
+                license_url: UrlProperty::from(self.license_url),
+                normalized_version: Some(self.version.clone()),
+                summary: self.summary,
+                version: ParsedProperty::new(self.version),
+                version_download_count: clamp(version_downloads),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+// V2 download counts are `Edm.Int32`; nuget.org's most popular packages overflow them.
+fn clamp(downloads: i64) -> i32 {
+    if downloads > i64::from(::std::i32::MAX) {
+        ::std::i32::MAX
+    } else {
+        downloads as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use super::*;
+
+    #[test]
+    fn nuget_gallery_search() {
+        // Trimmed from https://azuresearch-usnc.nuget.org/query?q=owin&take=1
+        let response_serialized = r##"{
+  "@context": {
+    "@vocab": "http://schema.nuget.org/schema#",
+    "@base": "https://api.nuget.org/v3/registration3/"
+  },
+  "totalHits": 1085,
+  "lastReopen": "2018-10-05T17:40:27.0131457Z",
+  "index": "v3-lucene2-v2v3-20181001",
+  "data": [
+    {
+      "@id": "https://api.nuget.org/v3/registration3/owin/index.json",
+      "@type": "Package",
+      "registration": "https://api.nuget.org/v3/registration3/owin/index.json",
+      "id": "Owin",
+      "version": "1.0.0",
+      "description": "OWIN IAppBuilder startup interface",
+      "summary": "",
+      "title": "OWIN",
+      "iconUrl": "",
+      "licenseUrl": "https://github.com/owin-contrib/owin-hosting/blob/master/LICENSE.txt",
+      "projectUrl": "https://github.com/owin-contrib/owin-hosting/",
+      "tags": ["owin"],
+      "authors": ["OWIN startup components contributors"],
+      "owners": "owin",
+      "totalDownloads": 46317291,
+      "packageTypes": [
+        {
+          "name": "Dependency"
+        }
+      ],
+      "verified": false,
+      "versions": [
+        {
+          "version": "1.0.0",
+          "downloads": 46317291,
+          "@id": "https://api.nuget.org/v3/registration3/owin/1.0.0.json"
+        }
+      ]
+    }
+  ]
+}"##;
+
+        let response: SearchResponse = serde_json::from_str(response_serialized).unwrap();
+
+        assert_eq!(response.total_hits, 1085);
+        assert_eq!(response.data.len(), 1);
+        assert_eq!(response.data[0].owners, vec!["owin"]);
+        assert_eq!(
+            response.data[0].package_types,
+            vec![PackageType {
+                name: String::from("Dependency"),
+            }]
+        );
+        assert_eq!(response.data[0].versions[0].downloads, 46317291);
+
+        let package_content =
+            Url::parse("https://api.nuget.org/v3-flatcontainer/owin/1.0.0/owin.1.0.0.nupkg")
+                .unwrap();
+        let package = response
+            .data
+            .into_iter()
+            .next()
+            .unwrap()
+            .into_package(package_content);
+
+        assert_eq!(package.id(), "Owin");
+        assert_eq!(package.version(), Some(&NuGetVersion::new(1, 0, 0)));
+        assert_eq!(package.properties.version_download_count, 46317291);
+    }
+
+    #[test]
+    fn search_query_pairs() {
+        assert_eq!(
+            SearchQuery::new("json").query_pairs(),
+            vec![
+                ("q", String::from("json")),
+                ("prerelease", String::from("false")),
+                ("semVerLevel", String::from("2.0.0")),
+            ]
+        );
+
+        assert_eq!(
+            SearchQuery::new("")
+                .skip(20)
+                .take(10)
+                .prerelease(true)
+                .sem_ver_level(None)
+                .package_type("DotnetTool")
+                .query_pairs(),
+            vec![
+                ("q", String::from("")),
+                ("skip", String::from("20")),
+                ("take", String::from("10")),
+                ("prerelease", String::from("true")),
+                ("packageType", String::from("DotnetTool")),
+            ]
+        );
+    }
+
+    #[test]
+    fn autocomplete_ids() {
+        // From https://api-v2v3search-0.nuget.org/autocomplete?q=storage&take=2&prerelease=true
+        let response_serialized = r##"{
+  "@context": {
+    "@vocab": "http://schema.nuget.org/schema#"
+  },
+  "totalHits": 571,
+  "lastReopen": "2018-10-05T17:40:27.0131457Z",
+  "index": "v3-lucene2-v2v3-20181001",
+  "data": [
+    "WindowsAzure.Storage",
+    "Storage.Net"
+  ]
+}"##;
+
+        let response: AutocompleteResponse = serde_json::from_str(response_serialized).unwrap();
+
+        assert_eq!(response.total_hits, Some(571));
+        assert_eq!(response.data, vec!["WindowsAzure.Storage", "Storage.Net"]);
+    }
+
+    #[test]
+    fn autocomplete_versions() {
+        // From https://api-v2v3search-0.nuget.org/autocomplete?id=owin
+        let response_serialized = r##"{
+  "@context": {
+    "@vocab": "http://schema.nuget.org/schema#"
+  },
+  "data": [
+    "1.0.0"
+  ]
+}"##;
+
+        let response: AutocompleteResponse = serde_json::from_str(response_serialized).unwrap();
+
+        assert_eq!(response.total_hits, None);
+        assert_eq!(response.data, vec!["1.0.0"]);
+    }
+}
@@ -0,0 +1,849 @@
+use failure::Error;
+use serde::de::Error as DeserializeError;
+use serde::{Deserialize, Deserializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// A package version, following https://docs.microsoft.com/en-us/nuget/reference/package-versioning
+///
+/// This is SemVer 2.0, plus the optional fourth `revision` part that
+/// legacy packages use. Equality and ordering ignore build metadata and
+/// the case of release labels, so `1.0` and `1.0.0.0` are the same version.
+#[derive(Debug, Clone)]
+pub struct NuGetVersion {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    revision: u64,
+    release_labels: Vec<String>,
+    metadata: Option<String>,
+    original: String,
+}
+
+impl NuGetVersion {
+    pub fn new(major: u64, minor: u64, patch: u64) -> NuGetVersion {
+        NuGetVersion {
+            major,
+            minor,
+            patch,
+            revision: 0,
+            release_labels: Vec::new(),
+            metadata: None,
+            original: format!("{}.{}.{}", major, minor, patch),
+        }
+    }
+
+    pub fn major(&self) -> u64 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u64 {
+        self.minor
+    }
+
+    pub fn patch(&self) -> u64 {
+        self.patch
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn release_labels(&self) -> &[String] {
+        &self.release_labels
+    }
+
+    /// The prerelease part of the version, without the leading '-'.
+    pub fn release(&self) -> String {
+        self.release_labels.join(".")
+    }
+
+    pub fn metadata(&self) -> Option<&str> {
+        self.metadata.as_ref().map(String::as_str)
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.release_labels.is_empty()
+    }
+
+    /// Whether the version can only be understood by SemVer 2.0 aware clients.
+    pub fn is_semver2(&self) -> bool {
+        self.release_labels.len() > 1 || self.metadata.is_some()
+    }
+
+    /// The version exactly as it was parsed.
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// The version without build metadata, leading zeros, or a zero revision, e.g. `1.0.0-beta`.
+    pub fn to_normalized_string(&self) -> String {
+        let mut normalized = format!("{}.{}.{}", self.major, self.minor, self.patch);
+
+        if self.revision > 0 {
+            normalized.push_str(&format!(".{}", self.revision));
+        }
+
+        if self.is_prerelease() {
+            normalized.push('-');
+            normalized.push_str(&self.release());
+        }
+
+        normalized
+    }
+
+    /// The normalized version, followed by any build metadata.
+    pub fn to_full_string(&self) -> String {
+        match self.metadata {
+            Some(ref metadata) => format!("{}+{}", self.to_normalized_string(), metadata),
+            None => self.to_normalized_string(),
+        }
+    }
+}
+
+impl Default for NuGetVersion {
+    fn default() -> NuGetVersion {
+        NuGetVersion::new(0, 0, 0)
+    }
+}
+
+impl FromStr for NuGetVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let original = s.trim();
+
+        let (version, metadata) = match original.find('+') {
+            Some(index) => (&original[..index], Some(&original[index + 1..])),
+            None => (original, None),
+        };
+
+        let (numbers, release) = match version.find('-') {
+            Some(index) => (&version[..index], Some(&version[index + 1..])),
+            None => (version, None),
+        };
+
+        let numbers = numbers
+            .split('.')
+            .map(|number| {
+                if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+                    bail!("{} is not a valid version", s);
+                }
+                number.parse::<u64>().map_err(Error::from)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if numbers.len() > 4 {
+            bail!("{} has more than 4 version numbers", s);
+        }
+
+        let release_labels = match release {
+            Some(release) => parse_identifiers(release, s)?,
+            None => Vec::new(),
+        };
+
+        let metadata = match metadata {
+            Some(metadata) => Some(parse_identifiers(metadata, s)?.join(".")),
+            None => None,
+        };
+
+        Ok(NuGetVersion {
+            major: numbers[0],
+            minor: numbers.get(1).cloned().unwrap_or(0),
+            patch: numbers.get(2).cloned().unwrap_or(0),
+            revision: numbers.get(3).cloned().unwrap_or(0),
+            release_labels,
+            metadata,
+            original: String::from(original),
+        })
+    }
+}
+
+// Release labels and metadata are dot separated, non-empty, alphanumeric and '-' identifiers.
+fn parse_identifiers(identifiers: &str, version: &str) -> Result<Vec<String>, Error> {
+    identifiers
+        .split('.')
+        .map(|identifier| {
+            if identifier.is_empty()
+                || !identifier
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+            {
+                bail!("{} has an invalid identifier '{}'", version, identifier);
+            }
+            Ok(String::from(identifier))
+        })
+        .collect()
+}
+
+fn compare_labels(left: &[String], right: &[String]) -> Ordering {
+    // A release version is newer than any prerelease of it
+    match (left.is_empty(), right.is_empty()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Greater,
+        (false, true) => return Ordering::Less,
+        (false, false) => {}
+    }
+
+    for (left, right) in left.iter().zip(right.iter()) {
+        let ordering = match (left.parse::<u64>(), right.parse::<u64>()) {
+            (Ok(left), Ok(right)) => left.cmp(&right),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => left.to_ascii_uppercase().cmp(&right.to_ascii_uppercase()),
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    left.len().cmp(&right.len())
+}
+
+impl Ord for NuGetVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch, self.revision)
+            .cmp(&(other.major, other.minor, other.patch, other.revision))
+            .then_with(|| compare_labels(&self.release_labels, &other.release_labels))
+    }
+}
+
+impl PartialOrd for NuGetVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for NuGetVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for NuGetVersion {}
+
+impl Hash for NuGetVersion {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.major.hash(state);
+        self.minor.hash(state);
+        self.patch.hash(state);
+        self.revision.hash(state);
+        for label in &self.release_labels {
+            label.to_ascii_uppercase().hash(state);
+        }
+    }
+}
+
+impl fmt::Display for NuGetVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.original)
+    }
+}
+
+impl<'de> Deserialize<'de> for NuGetVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(DeserializeError::custom)
+    }
+}
+
+/// A range of acceptable versions, following https://docs.microsoft.com/en-us/nuget/concepts/package-versioning#version-ranges
+///
+/// Supports interval notation (`[1.0, 2.0)`, `(, 1.0]`, `[1.0]`), a bare
+/// minimum version (`1.0`), and floating versions (`1.*`, `1.0.0-beta*`,
+/// `*-*`). A floating range is bounded below by its lowest matching version,
+/// and only changes which version `find_best_match` prefers.
+#[derive(Debug, Clone)]
+pub struct VersionRange {
+    min: Option<NuGetVersion>,
+    min_inclusive: bool,
+    max: Option<NuGetVersion>,
+    max_inclusive: bool,
+    float: Option<FloatRange>,
+    original: String,
+}
+
+/// The floating part of a range such as `1.*` or `1.0.0-beta*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatRange {
+    part: Option<FloatingPart>,
+    release_prefix: Option<String>,
+    original: String,
+}
+
+/// Which version number is replaced by a `*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatingPart {
+    Major,
+    Minor,
+    Patch,
+    Revision,
+}
+
+impl VersionRange {
+    /// A range that every version satisfies.
+    pub fn all() -> VersionRange {
+        VersionRange {
+            min: None,
+            min_inclusive: false,
+            max: None,
+            max_inclusive: false,
+            float: None,
+            original: String::new(),
+        }
+    }
+
+    /// A range that only `version` satisfies, i.e. `[version]`.
+    pub fn exact(version: NuGetVersion) -> VersionRange {
+        VersionRange {
+            original: format!("[{}]", version),
+            min: Some(version.clone()),
+            min_inclusive: true,
+            max: Some(version),
+            max_inclusive: true,
+            float: None,
+        }
+    }
+
+    pub fn min(&self) -> Option<&NuGetVersion> {
+        self.min.as_ref()
+    }
+
+    pub fn is_min_inclusive(&self) -> bool {
+        self.min_inclusive
+    }
+
+    pub fn max(&self) -> Option<&NuGetVersion> {
+        self.max.as_ref()
+    }
+
+    pub fn is_max_inclusive(&self) -> bool {
+        self.max_inclusive
+    }
+
+    pub fn float(&self) -> Option<&FloatRange> {
+        self.float.as_ref()
+    }
+
+    pub fn is_floating(&self) -> bool {
+        self.float.is_some()
+    }
+
+    /// The range exactly as it was parsed.
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// The range in interval notation with normalized versions, e.g. `[1.0.0, 2.0.0)`.
+    pub fn to_normalized_string(&self) -> String {
+        let min = match self.float {
+            Some(ref float) => float.original.clone(),
+            None => self
+                .min
+                .as_ref()
+                .map(NuGetVersion::to_normalized_string)
+                .unwrap_or_default(),
+        };
+
+        match (&self.min, &self.max) {
+            (Some(_), None) if self.float.is_some() => min,
+            (Some(min), Some(max)) if min == max => format!("[{}]", min.to_normalized_string()),
+            (_, max) => format!(
+                "{}{}, {}{}",
+                if self.min_inclusive { '[' } else { '(' },
+                min,
+                max.as_ref()
+                    .map(NuGetVersion::to_normalized_string)
+                    .unwrap_or_default(),
+                if self.max_inclusive { ']' } else { ')' }
+            ),
+        }
+    }
+
+    /// Whether `version` lies within the bounds of the range.
+    pub fn satisfies(&self, version: &NuGetVersion) -> bool {
+        let above_min = match self.min {
+            Some(ref min) if self.min_inclusive => version >= min,
+            Some(ref min) => version > min,
+            None => true,
+        };
+
+        let below_max = match self.max {
+            Some(ref max) if self.max_inclusive => version <= max,
+            Some(ref max) => version < max,
+            None => true,
+        };
+
+        above_min && below_max
+    }
+
+    /// The version NuGet would pick from `versions` for this range.
+    ///
+    /// This is the lowest satisfying version, or for floating ranges the
+    /// highest version matching the float, falling back to the lowest
+    /// satisfying version when nothing matches it.
+    pub fn find_best_match<'a, I>(&self, versions: I) -> Option<&'a NuGetVersion>
+    where
+        I: IntoIterator<Item = &'a NuGetVersion>,
+    {
+        let candidates = versions
+            .into_iter()
+            .filter(|version| self.satisfies(version))
+            .collect::<Vec<_>>();
+
+        if let (Some(float), Some(floor)) = (&self.float, &self.min) {
+            let floated = candidates
+                .iter()
+                .filter(|version| float.matches(floor, version))
+                .max();
+
+            if let Some(floated) = floated {
+                return Some(*floated);
+            }
+        }
+
+        candidates.into_iter().min()
+    }
+}
+
+impl FloatRange {
+    pub fn part(&self) -> Option<FloatingPart> {
+        self.part
+    }
+
+    /// The release label prefix before the `*`, if the release label floats.
+    pub fn release_prefix(&self) -> Option<&str> {
+        self.release_prefix.as_ref().map(String::as_str)
+    }
+
+    // Whether `version` shares the fixed parts of the float, whose lowest version is `floor`.
+    fn matches(&self, floor: &NuGetVersion, version: &NuGetVersion) -> bool {
+        let fixed = match self.part {
+            Some(FloatingPart::Major) => 0,
+            Some(FloatingPart::Minor) => 1,
+            Some(FloatingPart::Patch) => 2,
+            Some(FloatingPart::Revision) => 3,
+            None => 4,
+        };
+
+        let numbers = |v: &NuGetVersion| [v.major, v.minor, v.patch, v.revision];
+        if numbers(floor)[..fixed] != numbers(version)[..fixed] {
+            return false;
+        }
+
+        let matches_prefix = |prefix: &str| {
+            let release = version.release().to_ascii_lowercase();
+            version.is_prerelease() && release.starts_with(&prefix.to_ascii_lowercase())
+        };
+
+        match (self.part, &self.release_prefix) {
+            (None, Some(prefix)) => matches_prefix(prefix),
+            (Some(_), Some(prefix)) => !version.is_prerelease() || matches_prefix(prefix),
+            (_, None) => !version.is_prerelease(),
+        }
+    }
+
+    // The lowest version matching the float, e.g. `1.0.0-beta` for `1.*-beta*`.
+    fn floor(&self) -> Result<NuGetVersion, Error> {
+        let numbers = match self.original.find('-') {
+            Some(index) => &self.original[..index],
+            None => &self.original[..],
+        };
+        let mut floor = numbers.replace('*', "0");
+
+        if let Some(ref prefix) = self.release_prefix {
+            #[allow(deprecated)]
+            let prefix = prefix.trim_right_matches(&['.', '-'][..]);
+            floor.push('-');
+            floor.push_str(if prefix.is_empty() { "0" } else { prefix });
+        }
+
+        floor
+            .parse()
+            .map_err(|_| format_err!("{} is not a valid floating version", self.original))
+    }
+}
+
+impl FromStr for FloatRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let original = s.trim();
+
+        let (numbers, release) = match original.find('-') {
+            Some(index) => (&original[..index], Some(&original[index + 1..])),
+            None => (original, None),
+        };
+
+        let release_prefix = match release {
+            Some(release) if release.ends_with('*') => {
+                Some(String::from(&release[..release.len() - 1]))
+            }
+            _ => None,
+        };
+
+        let parts = numbers.split('.').collect::<Vec<_>>();
+        if parts[..parts.len() - 1]
+            .iter()
+            .any(|part| part.contains('*'))
+        {
+            bail!("{} can only float its last version number", s);
+        }
+
+        let part = if parts.last() == Some(&"*") {
+            match parts.len() {
+                1 => Some(FloatingPart::Major),
+                2 => Some(FloatingPart::Minor),
+                3 => Some(FloatingPart::Patch),
+                4 => Some(FloatingPart::Revision),
+                _ => bail!("{} has more than 4 version numbers", s),
+            }
+        } else {
+            None
+        };
+
+        if part.is_none() && release_prefix.is_none() {
+            bail!("{} is not a floating version", s);
+        }
+
+        if part.is_some() && release.is_some() && release_prefix.is_none() {
+            bail!(
+                "{} cannot have a fixed release label after a floating number",
+                s
+            );
+        }
+
+        Ok(FloatRange {
+            part,
+            release_prefix,
+            original: String::from(original),
+        })
+    }
+}
+
+impl Default for VersionRange {
+    fn default() -> VersionRange {
+        VersionRange::all()
+    }
+}
+
+impl FromStr for VersionRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let original = s.trim();
+        let mut range = VersionRange {
+            original: String::from(original),
+            ..VersionRange::all()
+        };
+
+        if original.is_empty() {
+            return Ok(range);
+        }
+
+        // A bare version is a minimum, inclusive bound
+        if !original.starts_with('[') && !original.starts_with('(') {
+            let (min, float) = parse_bound(original)?;
+            range.min = Some(min);
+            range.min_inclusive = true;
+            range.float = float;
+            return Ok(range);
+        }
+
+        if original.len() < 2 || !(original.ends_with(']') || original.ends_with(')')) {
+            bail!("{} is not a valid version range", s);
+        }
+
+        range.min_inclusive = original.starts_with('[');
+        range.max_inclusive = original.ends_with(']');
+
+        let interval = &original[1..original.len() - 1];
+        let bounds = interval.split(',').map(str::trim).collect::<Vec<_>>();
+
+        match bounds.len() {
+            1 => {
+                // An exact version, i.e. `[1.0]`
+                if !range.min_inclusive || !range.max_inclusive || bounds[0].is_empty() {
+                    bail!("{} is not a valid version range", s);
+                }
+                let version = bounds[0].parse::<NuGetVersion>()?;
+                range.min = Some(version.clone());
+                range.max = Some(version);
+            }
+            2 => {
+                if !bounds[0].is_empty() {
+                    let (min, float) = parse_bound(bounds[0])?;
+                    range.min = Some(min);
+                    range.float = float;
+                }
+                if !bounds[1].is_empty() {
+                    range.max = Some(bounds[1].parse()?);
+                }
+            }
+            _ => bail!("{} has more than 2 bounds", s),
+        }
+
+        if let (Some(min), Some(max)) = (&range.min, &range.max) {
+            let exclusive = !range.min_inclusive || !range.max_inclusive;
+            if min > max || (min == max && exclusive) {
+                bail!("{} does not contain any versions", s);
+            }
+        }
+
+        Ok(range)
+    }
+}
+
+// A lower bound, which may float.
+fn parse_bound(bound: &str) -> Result<(NuGetVersion, Option<FloatRange>), Error> {
+    if bound.contains('*') {
+        let float = bound.parse::<FloatRange>()?;
+        Ok((float.floor()?, Some(float)))
+    } else {
+        Ok((bound.parse()?, None))
+    }
+}
+
+impl PartialEq for VersionRange {
+    fn eq(&self, other: &Self) -> bool {
+        self.min == other.min
+            && self.min_inclusive == other.min_inclusive
+            && self.max == other.max
+            && self.max_inclusive == other.max_inclusive
+            && self.float == other.float
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.original)
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionRange {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(DeserializeError::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> NuGetVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parsing() {
+        let parsed = version("1.2.3.4-beta.1+git.abc123");
+
+        assert_eq!(parsed.major(), 1);
+        assert_eq!(parsed.minor(), 2);
+        assert_eq!(parsed.patch(), 3);
+        assert_eq!(parsed.revision(), 4);
+        assert_eq!(parsed.release_labels(), &["beta", "1"]);
+        assert_eq!(parsed.metadata(), Some("git.abc123"));
+        assert!(parsed.is_prerelease());
+        assert!(parsed.is_semver2());
+        assert_eq!(parsed.original(), "1.2.3.4-beta.1+git.abc123");
+
+        assert_eq!(version("1").to_normalized_string(), "1.0.0");
+        assert!(!version("1.0.0-rc2-16010").is_semver2());
+    }
+
+    #[test]
+    fn invalid_versions() {
+        for invalid in &[
+            "",
+            "a.b",
+            "1.2.3.4.5",
+            "1..2",
+            "1.0-",
+            "1.0-beta..1",
+            "1.0+",
+            "1.0-be_ta",
+        ] {
+            assert!(invalid.parse::<NuGetVersion>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn normalization() {
+        assert_eq!(version("1.0").to_normalized_string(), "1.0.0");
+        assert_eq!(version("1.01.1").to_normalized_string(), "1.1.1");
+        assert_eq!(version("1.0.0.0").to_normalized_string(), "1.0.0");
+        assert_eq!(version("1.0.0.1").to_normalized_string(), "1.0.0.1");
+        assert_eq!(
+            version("1.0.0-Beta+meta").to_normalized_string(),
+            "1.0.0-Beta"
+        );
+        assert_eq!(
+            version("1.0.0-Beta+meta").to_full_string(),
+            "1.0.0-Beta+meta"
+        );
+        assert_eq!(version("1.0").to_string(), "1.0");
+    }
+
+    #[test]
+    fn equality() {
+        assert_eq!(version("1.0"), version("1.0.0"));
+        assert_eq!(version("1.0.0.0"), version("1.0.0"));
+        assert_eq!(version("1.0.0-BETA"), version("1.0.0-beta"));
+        assert_eq!(version("1.0.0+a"), version("1.0.0+b"));
+        assert_ne!(version("1.0.0.1"), version("1.0.0"));
+    }
+
+    #[test]
+    fn precedence() {
+        let ordered = [
+            "0.9.9",
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.0.1",
+            "1.0.1",
+            "1.10.0",
+        ];
+
+        for pair in ordered.windows(2) {
+            assert!(
+                version(pair[0]) < version(pair[1]),
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+        }
+
+        let mut shuffled = ordered.iter().rev().map(|v| version(v)).collect::<Vec<_>>();
+        shuffled.sort();
+        assert_eq!(
+            shuffled.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+            ordered.to_vec()
+        );
+    }
+
+    fn range(s: &str) -> VersionRange {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn range_parsing() {
+        let parsed = range("[1.0, 2.0)");
+        assert_eq!(parsed.min(), Some(&version("1.0")));
+        assert!(parsed.is_min_inclusive());
+        assert_eq!(parsed.max(), Some(&version("2.0")));
+        assert!(!parsed.is_max_inclusive());
+        assert_eq!(parsed.to_normalized_string(), "[1.0.0, 2.0.0)");
+
+        assert_eq!(range("1.0").to_normalized_string(), "[1.0.0, )");
+        assert_eq!(range("[1.0]").to_normalized_string(), "[1.0.0]");
+        assert_eq!(range("(,1.0]").to_normalized_string(), "(, 1.0.0]");
+        assert_eq!(range("(1.0,)").to_normalized_string(), "(1.0.0, )");
+        assert_eq!(range("[1.*, 2.0)").to_normalized_string(), "[1.*, 2.0.0)");
+        assert_eq!(range(""), VersionRange::all());
+        assert_eq!(range("1.0.0"), range("[1.0,)"));
+        assert_eq!(range("[1.0]"), VersionRange::exact(version("1.0.0")));
+
+        for invalid in &[
+            "[1.0",
+            "(1.0)",
+            "[1.0)",
+            "[]",
+            "[2.0, 1.0]",
+            "(1.0, 1.0]",
+            "[1.0, 2.0, 3.0]",
+            "[1.0, 2.*]",
+            "1.*.0",
+            "*.1",
+            "a",
+        ] {
+            assert!(invalid.parse::<VersionRange>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn floating_parsing() {
+        let parsed = range("1.*");
+        assert_eq!(parsed.min(), Some(&version("1.0.0")));
+        assert_eq!(parsed.float().unwrap().part(), Some(FloatingPart::Minor));
+        assert_eq!(parsed.float().unwrap().release_prefix(), None);
+
+        let parsed = range("1.0.0-beta*");
+        assert_eq!(parsed.min(), Some(&version("1.0.0-beta")));
+        assert_eq!(parsed.float().unwrap().part(), None);
+        assert_eq!(parsed.float().unwrap().release_prefix(), Some("beta"));
+
+        assert_eq!(range("*").min(), Some(&version("0.0.0")));
+        assert_eq!(range("*-*").min(), Some(&version("0.0.0-0")));
+        assert_eq!(range("1.0.*-rc.*").min(), Some(&version("1.0.0-rc")));
+        assert!(!range("1.0").is_floating());
+    }
+
+    #[test]
+    fn satisfies() {
+        let bounded = range("[1.0, 2.0)");
+        assert!(bounded.satisfies(&version("1.0")));
+        assert!(bounded.satisfies(&version("1.5")));
+        assert!(bounded.satisfies(&version("2.0.0-beta")));
+        assert!(!bounded.satisfies(&version("2.0")));
+        assert!(!bounded.satisfies(&version("0.9")));
+
+        assert!(range("(1.0,)").satisfies(&version("1.0.1")));
+        assert!(!range("(1.0,)").satisfies(&version("1.0")));
+        assert!(range("(,1.0]").satisfies(&version("1.0")));
+        assert!(range("[1.0]").satisfies(&version("1.0.0.0")));
+        assert!(!range("[1.0]").satisfies(&version("1.0.1")));
+        assert!(range("").satisfies(&version("0.0.1-alpha")));
+    }
+
+    #[test]
+    fn best_match() {
+        let versions = [
+            "0.9.0",
+            "1.0.0",
+            "1.1.0",
+            "1.2.0-beta.1",
+            "1.2.0-beta.2",
+            "1.2.0-rc",
+            "2.0.0",
+        ]
+        .iter()
+        .map(|v| version(v))
+        .collect::<Vec<_>>();
+
+        let best = |r: &str| range(r).find_best_match(&versions).map(|v| v.to_string());
+
+        // Lowest applicable version
+        assert_eq!(best("1.0"), Some(String::from("1.0.0")));
+        assert_eq!(best("(1.0, 2.0]"), Some(String::from("1.1.0")));
+        assert_eq!(best("[3.0, )"), None);
+
+        // Highest version matching the float
+        assert_eq!(best("*"), Some(String::from("2.0.0")));
+        assert_eq!(best("1.*"), Some(String::from("1.1.0")));
+        assert_eq!(best("1.*-*"), Some(String::from("1.2.0-rc")));
+        assert_eq!(best("1.2.0-beta*"), Some(String::from("1.2.0-beta.2")));
+        assert_eq!(best("[1.*, 1.1)"), Some(String::from("1.0.0")));
+
+        // Falls back to the lowest applicable version
+        assert_eq!(best("1.5.*"), Some(String::from("2.0.0")));
+    }
+}